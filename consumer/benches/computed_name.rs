@@ -0,0 +1,63 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+// Compares computing a name from a large number of descendants with
+// retrieving the cached name from an unchanged tree. This uses a plain
+// `main` function, so it runs on stable Rust: `cargo bench`.
+
+use std::num::NonZeroU64;
+use std::time::Instant;
+
+use accesskit_consumer::Tree;
+use accesskit_schema::{Node, NodeId, Role, StringEncoding, Tree as TreeData, TreeId, TreeUpdate};
+
+const TEXT_COUNT: u64 = 10_000;
+const ITERATIONS: u32 = 1000;
+
+fn node_id(n: u64) -> NodeId {
+    NodeId(NonZeroU64::new(n).unwrap())
+}
+
+fn main() {
+    let root_id = node_id(1);
+    let button_id = node_id(2);
+    let text_ids = (0..TEXT_COUNT).map(|i| node_id(i + 3)).collect::<Vec<_>>();
+    let mut nodes = vec![
+        Node {
+            children: Box::new([button_id]),
+            ..Node::new(root_id, Role::Window)
+        },
+        Node {
+            children: text_ids.clone().into_boxed_slice(),
+            ..Node::new(button_id, Role::Button)
+        },
+    ];
+    nodes.extend(text_ids.iter().map(|id| Node {
+        name: Some(format!("word{}", id.0).into()),
+        ..Node::new(*id, Role::StaticText)
+    }));
+    let tree = Tree::new(TreeUpdate {
+        nodes,
+        tree: Some(TreeData::new(
+            TreeId("bench".into()),
+            root_id,
+            StringEncoding::Utf8,
+        )),
+        ..Default::default()
+    });
+
+    let reader = tree.read();
+    let button = reader.node_by_id(button_id).unwrap();
+    let start = Instant::now();
+    let name = button.computed_name();
+    let first = start.elapsed();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert_eq!(name, button.computed_name());
+    }
+    let cached = start.elapsed() / ITERATIONS;
+    println!("computed_name, first call:  {:?}", first);
+    println!("computed_name, cached call: {:?}", cached);
+}
@@ -0,0 +1,170 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use accesskit_schema::NodeAttributeKind;
+
+use crate::NodeData;
+
+/// The categories of a node's data that changed in an update, as reported
+/// by [`TreeChange::NodeUpdated`](crate::TreeChange::NodeUpdated). This
+/// lets a consumer skip work for categories it doesn't care about, e.g.
+/// re-rendering text when only the bounds changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirtyFlags {
+    /// The name, description, or other text that identifies the node.
+    pub name: bool,
+    /// The value, including the value and limits of a range.
+    pub value: bool,
+    /// States such as checked, expanded, selected, or disabled,
+    /// or the focus moving to or from the node.
+    pub state: bool,
+    /// The bounds, scroll position, or character offsets.
+    pub bounds: bool,
+    /// The children, indirect children, or embedded child tree.
+    pub children: bool,
+    /// References to other nodes, such as [`NodeData::labelled_by`].
+    pub relationships: bool,
+    /// Anything else, including the role and text styles.
+    pub other: bool,
+}
+
+impl DirtyFlags {
+    /// Returns the categories of data that differ between the two nodes.
+    pub fn between(old: &NodeData, new: &NodeData) -> Self {
+        let mut flags = Self {
+            children: old.children != new.children,
+            other: old.role != new.role,
+            ..Self::default()
+        };
+        for kind in old.changed_attribute_kinds(new) {
+            flags.add(kind);
+        }
+        flags
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn add(&mut self, kind: NodeAttributeKind) {
+        use NodeAttributeKind::*;
+
+        let category = match kind {
+            Name | NameFrom | Description | DescriptionFrom | Placeholder | RoleDescription
+            | Tooltip => &mut self.name,
+            Value
+            | ValueForRange
+            | MinValueForRange
+            | MaxValueForRange
+            | StepValueForRange
+            | LargeStepValueForRange
+            | ColorValue => &mut self.value,
+            AutofillAvailable
+            | Default
+            | Editable
+            | Focusable
+            | Hovered
+            | Ignored
+            | Invisible
+            | Linked
+            | Multiline
+            | Multiselectable
+            | Protected
+            | Required
+            | Visited
+            | Busy
+            | Modal
+            | Scrollable
+            | Clickable
+            | SelectedFromFocus
+            | ReadOnly
+            | Disabled
+            | Expanded
+            | Selected
+            | Grabbed
+            | InvalidState
+            | CheckedState
+            | CheckedStateDescription
+            | AriaCurrent
+            | SortDirection
+            | TextSelection => &mut self.state,
+            Bounds | ScrollX | ScrollXMin | ScrollXMax | ScrollY | ScrollYMin | ScrollYMax
+            | CharacterOffsets => &mut self.bounds,
+            IndirectChildren | ChildTree => &mut self.children,
+            ActiveDescendant | ErrorMessage | InPageLinkTarget | MemberOf | NextOnLine
+            | PreviousOnLine | PopupFor | TableHeader | TableRowHeader | TableColumnHeader
+            | PreviousFocus | NextFocus | Controls | Details | DescribedBy | FlowTo
+            | LabelledBy | RadioGroups => &mut self.relationships,
+            NonatomicTextFieldRoot
+            | ContainerLiveAtomic
+            | ContainerLiveBusy
+            | LiveAtomic
+            | CanvasHasFallback
+            | ClipsChildren
+            | NotUserSelectableStyle
+            | IsLineBreakingObject
+            | IsPageBreakingObject
+            | HasAriaAttribute
+            | TouchPassThrough
+            | Bold
+            | Italic
+            | Orientation
+            | TextDirection
+            | AccessKey
+            | AutoComplete
+            | ClassName
+            | ContainerLiveRelevant
+            | ContainerLiveStatus
+            | CssDisplay
+            | FontFamily
+            | HtmlTag
+            | InnerHtml
+            | InputType
+            | KeyShortcuts
+            | Language
+            | LiveRelevant
+            | LiveStatus
+            | AriaRole
+            | Url
+            | DefaultActionVerb
+            | AriaColumnCount
+            | AriaCellColumnIndex
+            | AriaCellColumnSpan
+            | AriaRowCount
+            | AriaCellRowIndex
+            | AriaCellRowSpan
+            | TableRowCount
+            | TableColumnCount
+            | TableRowIndex
+            | TableColumnIndex
+            | TableCellColumnIndex
+            | TableCellColumnSpan
+            | TableCellRowIndex
+            | TableCellRowSpan
+            | HierarchicalLevel
+            | SetSize
+            | PosInSet
+            | BackgroundColor
+            | ForegroundColor
+            | HasPopup
+            | ListStyle
+            | TextAlign
+            | VerticalOffset
+            | Overline
+            | Strikethrough
+            | Underline
+            | FontSize
+            | FontWeight
+            | TextIndent
+            | Actions
+            | DropEffects
+            | Markers
+            | Words
+            | CustomActions
+            | CustomAttributes => &mut self.other,
+        };
+        *category = true;
+    }
+}
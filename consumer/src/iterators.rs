@@ -8,6 +8,7 @@
 // Use of this source code is governed by a BSD-style license that can be
 // found in the LICENSE.chromium file.
 
+use std::collections::VecDeque;
 use std::iter::FusedIterator;
 
 use accesskit_schema::NodeId;
@@ -410,6 +411,72 @@ impl<'a> DoubleEndedIterator for UnignoredChildren<'a> {
 
 impl<'a> FusedIterator for UnignoredChildren<'a> {}
 
+/// The order in which [`traverse`](crate::TreeReader::traverse)
+/// visits the nodes of a tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraversalOrder {
+    /// Each node is visited before its children, i.e. in document order.
+    PreOrder,
+    /// Each node is visited after its children.
+    PostOrder,
+    /// All nodes at one depth are visited before any at the next depth.
+    BreadthFirst,
+}
+
+/// An iterator that yields every node in a tree in a given order.
+///
+/// This struct is created by the [traverse](crate::TreeReader::traverse) method on
+/// [TreeReader](crate::TreeReader).
+pub struct Traversal<'a> {
+    order: TraversalOrder,
+    // Each node is paired with whether its children have already been
+    // queued, which is only needed for post-order.
+    pending: VecDeque<(Node<'a>, bool)>,
+}
+
+impl<'a> Traversal<'a> {
+    pub(crate) fn new(root: Node<'a>, order: TraversalOrder) -> Self {
+        let mut pending = VecDeque::new();
+        pending.push_back((root, false));
+        Self { order, pending }
+    }
+}
+
+impl<'a> Iterator for Traversal<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.order {
+            TraversalOrder::PreOrder => {
+                let (node, _) = self.pending.pop_back()?;
+                for child in node.children().rev() {
+                    self.pending.push_back((child, false));
+                }
+                Some(node)
+            }
+            TraversalOrder::PostOrder => loop {
+                let (node, children_queued) = self.pending.pop_back()?;
+                if children_queued || node.children().len() == 0 {
+                    return Some(node);
+                }
+                self.pending.push_back((node, true));
+                for child in node.children().rev() {
+                    self.pending.push_back((child, false));
+                }
+            },
+            TraversalOrder::BreadthFirst => {
+                let (node, _) = self.pending.pop_front()?;
+                for child in node.children() {
+                    self.pending.push_back((child, false));
+                }
+                Some(node)
+            }
+        }
+    }
+}
+
+impl<'a> FusedIterator for Traversal<'a> {}
+
 #[cfg(test)]
 mod tests {
     use crate::tests::*;
@@ -743,4 +810,72 @@ mod tests {
             .next_back()
             .is_none());
     }
+
+    #[test]
+    fn traverse() {
+        use super::TraversalOrder;
+
+        let tree = test_tree();
+        let reader = tree.read();
+        let traverse = |order| {
+            reader
+                .traverse(order)
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        };
+        assert_eq!(
+            vec![
+                ROOT_ID,
+                PARAGRAPH_0_ID,
+                STATIC_TEXT_0_0_IGNORED_ID,
+                PARAGRAPH_1_IGNORED_ID,
+                STATIC_TEXT_1_0_ID,
+                PARAGRAPH_2_ID,
+                STATIC_TEXT_2_0_ID,
+                PARAGRAPH_3_IGNORED_ID,
+                EMPTY_CONTAINER_3_0_IGNORED_ID,
+                LINK_3_1_IGNORED_ID,
+                STATIC_TEXT_3_1_0_ID,
+                BUTTON_3_2_ID,
+                EMPTY_CONTAINER_3_3_IGNORED_ID
+            ],
+            traverse(TraversalOrder::PreOrder)
+        );
+        assert_eq!(
+            vec![
+                STATIC_TEXT_0_0_IGNORED_ID,
+                PARAGRAPH_0_ID,
+                STATIC_TEXT_1_0_ID,
+                PARAGRAPH_1_IGNORED_ID,
+                STATIC_TEXT_2_0_ID,
+                PARAGRAPH_2_ID,
+                EMPTY_CONTAINER_3_0_IGNORED_ID,
+                STATIC_TEXT_3_1_0_ID,
+                LINK_3_1_IGNORED_ID,
+                BUTTON_3_2_ID,
+                EMPTY_CONTAINER_3_3_IGNORED_ID,
+                PARAGRAPH_3_IGNORED_ID,
+                ROOT_ID
+            ],
+            traverse(TraversalOrder::PostOrder)
+        );
+        assert_eq!(
+            vec![
+                ROOT_ID,
+                PARAGRAPH_0_ID,
+                PARAGRAPH_1_IGNORED_ID,
+                PARAGRAPH_2_ID,
+                PARAGRAPH_3_IGNORED_ID,
+                STATIC_TEXT_0_0_IGNORED_ID,
+                STATIC_TEXT_1_0_ID,
+                STATIC_TEXT_2_0_ID,
+                EMPTY_CONTAINER_3_0_IGNORED_ID,
+                LINK_3_1_IGNORED_ID,
+                BUTTON_3_2_ID,
+                EMPTY_CONTAINER_3_3_IGNORED_ID,
+                STATIC_TEXT_3_1_0_ID
+            ],
+            traverse(TraversalOrder::BreadthFirst)
+        );
+    }
 }
@@ -6,15 +6,34 @@
 pub use accesskit_schema::{Node as NodeData, Tree as TreeData};
 
 pub(crate) mod tree;
-pub use tree::{Change as TreeChange, Reader as TreeReader, Tree};
+pub use tree::{
+    diff_summary, Change as TreeChange, ChangeRecord as TreeChangeRecord, EventSink, NodeDiff,
+    Reader as TreeReader, Snapshot as TreeSnapshot, Tree, TreeUpdateError, UpdateStatus,
+};
 
 pub(crate) mod node;
-pub use node::{Node, WeakNode};
+pub use node::{FilterMode, Node, RangeStep, WeakNode};
+
+pub(crate) mod text;
+
+#[cfg(feature = "serde")]
+pub(crate) mod snapshot;
+
+pub(crate) mod dirty;
+pub use dirty::DirtyFlags;
+
+pub(crate) mod store;
+pub use store::NodeStateStore;
+
+#[cfg(feature = "async")]
+pub(crate) mod stream;
+#[cfg(feature = "async")]
+pub use stream::{change_channel, ChangeSender, ChangeStream, NextBatch};
 
 pub(crate) mod iterators;
 pub use iterators::{
     FollowingSiblings, FollowingUnignoredSiblings, PrecedingSiblings, PrecedingUnignoredSiblings,
-    UnignoredChildren,
+    Traversal, TraversalOrder, UnignoredChildren,
 };
 
 #[cfg(test)]
@@ -130,7 +149,6 @@ mod tests {
             ..Node::new(EMPTY_CONTAINER_3_3_IGNORED_ID, Role::GenericContainer)
         };
         let initial_update = TreeUpdate {
-            clear: None,
             nodes: vec![
                 root,
                 paragraph_0,
@@ -151,7 +169,7 @@ mod tests {
                 ROOT_ID,
                 StringEncoding::Utf8,
             )),
-            focus: None,
+            ..Default::default()
         };
         crate::tree::Tree::new(initial_update)
     }
@@ -3,18 +3,147 @@
 // the LICENSE-APACHE file) or the MIT license (found in
 // the LICENSE-MIT file), at your option.
 
+use std::collections::HashSet;
 use std::iter::FusedIterator;
+use std::ops::Range;
 use std::sync::{Arc, Weak};
 
-use accesskit_schema::{NodeId, Rect, Role};
+use accesskit_schema::{
+    parse_live_relevant, parse_live_status, Action, ActionData, ActionRequest, AriaCurrent, Color,
+    DropEffect, LivePoliteness, LiveRelevant, NameFrom, NodeId, Orientation, Rect, RelativeBounds,
+    Role, StringEncoding, TextDirection, TextSelection, Transform,
+};
 
 use crate::iterators::{
     FollowingSiblings, FollowingUnignoredSiblings, PrecedingSiblings, PrecedingUnignoredSiblings,
     UnignoredChildren,
 };
+use crate::text;
 use crate::tree::{NodeState, ParentAndIndex, Reader as TreeReader, Tree};
 use crate::NodeData;
 
+/// The size of the adjustment requested by [`Node::increment`]
+/// and [`Node::decrement`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeStep {
+    /// [`NodeData::step_value_for_range`], e.g. for an arrow key.
+    Small,
+    /// [`Node::large_step_value_for_range`], e.g. for Page Up or Page Down.
+    Large,
+}
+
+/// How ignored nodes are treated in a filtered view of the tree,
+/// as traversed with [`Node::filtered_children`] and
+/// [`Node::filtered_parent`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterMode {
+    /// An ignored node is skipped, and its children take its place
+    /// among its parent's children. This is what
+    /// [`NodeData::ignored`] means in the schema, and is how the
+    /// `unignored_*` methods behave.
+    #[default]
+    SkipNode,
+    /// An ignored node is removed from the view along with all of its
+    /// descendants, even those that aren't ignored themselves.
+    PruneSubtree,
+}
+
+/// A 2D affine transform, mapping `(x, y)` to
+/// `(a * x + c * y + e, b * x + d * y + f)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Affine {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+}
+
+impl Affine {
+    const IDENTITY: Self = Self::translate(0.0, 0.0);
+
+    const fn translate(x: f32, y: f32) -> Self {
+        Self {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: x,
+            f: y,
+        }
+    }
+
+    fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+
+    /// Returns the transform that applies `self`, then `next`.
+    fn then(&self, next: &Self) -> Self {
+        Self {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    fn to_transform(self) -> Transform {
+        Transform {
+            matrix: [
+                self.a, self.b, 0.0, 0.0, self.c, self.d, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, self.e,
+                self.f, 0.0, 1.0,
+            ],
+        }
+    }
+
+    fn inverse(&self) -> Option<Self> {
+        let determinant = self.a * self.d - self.b * self.c;
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let a = self.d / determinant;
+        let b = -self.b / determinant;
+        let c = -self.c / determinant;
+        let d = self.a / determinant;
+        Some(Self {
+            a,
+            b,
+            c,
+            d,
+            e: -(a * self.e + c * self.f),
+            f: -(b * self.e + d * self.f),
+        })
+    }
+}
+
+// Only the 2D part of the matrix is used, since bounds are
+// two-dimensional.
+impl From<&Transform> for Affine {
+    fn from(transform: &Transform) -> Self {
+        let m = &transform.matrix;
+        Self {
+            a: m[0],
+            b: m[1],
+            c: m[4],
+            d: m[5],
+            e: m[12],
+            f: m[13],
+        }
+    }
+}
+
+/// A read-only view of a node, borrowed from a [`TreeReader`]. Getting
+/// a node, e.g. with [`TreeReader::root`] or [`Node::children`], doesn't
+/// copy the node's data, and the view itself is cheap to copy.
+/// To keep a reference to a node beyond the lifetime of the reader,
+/// use [`Node::downgrade`]; to get an owned copy of its data, clone
+/// [`Node::data`].
 #[derive(Copy, Clone)]
 pub struct Node<'a> {
     pub tree_reader: &'a TreeReader<'a>,
@@ -92,12 +221,81 @@ impl<'a> Node<'a> {
             .map(move |id| reader.node_by_id(*id).unwrap())
     }
 
+    /// Returns this node's children, followed by its
+    /// [indirect children](NodeData::indirect_children), such as the cells
+    /// of a table column, each in the order given. A node that appears in
+    /// both lists, or more than once, is only returned the first time,
+    /// and indirect child IDs that aren't in the tree are skipped.
+    pub fn all_children_including_indirect(self) -> Vec<Node<'a>> {
+        let mut seen = HashSet::new();
+        let indirect = self
+            .data()
+            .indirect_children
+            .iter()
+            .filter_map(|id| self.tree_reader.node_by_id(*id));
+        self.children()
+            .chain(indirect)
+            .filter(|node| seen.insert(node.id()))
+            .collect()
+    }
+
     pub fn unignored_children(
         self,
     ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
         UnignoredChildren::new(self)
     }
 
+    /// Returns the children of this node in a view of the tree that's
+    /// filtered according to `mode`. In [`FilterMode::SkipNode`] mode,
+    /// this is the same as [`Node::unignored_children`].
+    pub fn filtered_children(
+        self,
+        mode: FilterMode,
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        let (skipped, pruned) = match mode {
+            FilterMode::SkipNode => (Some(self.unignored_children()), None),
+            FilterMode::PruneSubtree => (
+                None,
+                Some(self.children().filter(|child| !child.is_ignored())),
+            ),
+        };
+        skipped
+            .into_iter()
+            .flatten()
+            .chain(pruned.into_iter().flatten())
+    }
+
+    /// Returns the parent of this node in a view of the tree that's
+    /// filtered according to `mode`. In [`FilterMode::PruneSubtree`] mode,
+    /// a node that isn't in the view, because it or one of its ancestors
+    /// is ignored, has no parent.
+    pub fn filtered_parent(self, mode: FilterMode) -> Option<Node<'a>> {
+        match mode {
+            FilterMode::SkipNode => self.unignored_parent(),
+            FilterMode::PruneSubtree => {
+                if self.is_filtered_out(mode) {
+                    None
+                } else {
+                    self.parent()
+                }
+            }
+        }
+    }
+
+    /// Returns true if this node isn't in a view of the tree that's
+    /// filtered according to `mode`.
+    pub fn is_filtered_out(&self, mode: FilterMode) -> bool {
+        match mode {
+            FilterMode::SkipNode => self.is_ignored(),
+            FilterMode::PruneSubtree => {
+                self.is_ignored()
+                    || self
+                        .parent()
+                        .is_some_and(|parent| parent.is_filtered_out(mode))
+            }
+        }
+    }
+
     pub fn following_siblings(
         self,
     ) -> impl DoubleEndedIterator<Item = Node<'a>>
@@ -162,6 +360,298 @@ impl<'a> Node<'a> {
         Some(deepest_child)
     }
 
+    /// Returns the node that follows this one in document order,
+    /// i.e. a pre-order traversal of the whole tree, including ignored nodes.
+    pub fn next_in_document_order(self) -> Option<Node<'a>> {
+        if let Some(child) = self.children().next() {
+            return Some(child);
+        }
+        let mut current = self;
+        loop {
+            if let Some(sibling) = current.following_siblings().next() {
+                return Some(sibling);
+            }
+            current = current.parent()?;
+        }
+    }
+
+    /// Returns the node that precedes this one in document order,
+    /// i.e. a pre-order traversal of the whole tree, including ignored nodes.
+    pub fn previous_in_document_order(self) -> Option<Node<'a>> {
+        if let Some(sibling) = self.preceding_siblings().next() {
+            return Some(sibling.deepest_last_child().unwrap_or(sibling));
+        }
+        self.parent()
+    }
+
+    /// Returns true if sequential focus navigation (e.g. with the Tab key)
+    /// can land on this node.
+    pub fn is_focus_navigable(&self) -> bool {
+        self.is_focusable() && !self.is_disabled() && !self.is_invisible()
+    }
+
+    /// Returns the node that sequential focus navigation should move to
+    /// from this node. If this node has a [`NodeData::next_focus`] that
+    /// resolves to a node in the tree, that node is returned. Otherwise,
+    /// this is the next node in document order that is
+    /// [focus-navigable](Node::is_focus_navigable).
+    ///
+    /// This node doesn't need to be focusable itself, so this can also
+    /// be used to find the next focus target from a sequential focus
+    /// navigation starting point, as set by
+    /// [`Action::SetSequentialFocusNavigationStartingPoint`].
+    pub fn next_in_focus_order(self) -> Option<Node<'a>> {
+        if let Some(node) = self
+            .data()
+            .next_focus
+            .and_then(|id| self.tree_reader.node_by_id(id))
+        {
+            return Some(node);
+        }
+        let mut current = self.next_in_document_order();
+        while let Some(node) = current {
+            if node.is_focus_navigable() {
+                return Some(node);
+            }
+            current = node.next_in_document_order();
+        }
+        None
+    }
+
+    /// Returns the node that reverse sequential focus navigation
+    /// (e.g. with Shift+Tab) should move to from this node. This is
+    /// the counterpart of [`Node::next_in_focus_order`], using
+    /// [`NodeData::previous_focus`] when present.
+    pub fn previous_in_focus_order(self) -> Option<Node<'a>> {
+        if let Some(node) = self
+            .data()
+            .previous_focus
+            .and_then(|id| self.tree_reader.node_by_id(id))
+        {
+            return Some(node);
+        }
+        let mut current = self.previous_in_document_order();
+        while let Some(node) = current {
+            if node.is_focus_navigable() {
+                return Some(node);
+            }
+            current = node.previous_in_document_order();
+        }
+        None
+    }
+
+    /// Returns true if this node is a table-like container, i.e. one whose
+    /// cells can be addressed by row and column.
+    pub fn is_table(&self) -> bool {
+        matches!(
+            self.role(),
+            Role::Table | Role::Grid | Role::TreeGrid | Role::LayoutTable | Role::ListGrid
+        )
+    }
+
+    /// Returns the nearest ancestor that is a [table](Node::is_table),
+    /// skipping any intervening rows, row groups, or other containers.
+    /// This is useful for implementing e.g. UIA's
+    /// `IGridItemProvider::ContainingGrid`.
+    pub fn containing_table(self) -> Option<Node<'a>> {
+        let mut current = self.parent();
+        while let Some(node) = current {
+            if node.is_table() {
+                return Some(node);
+            }
+            current = node.parent();
+        }
+        None
+    }
+
+    /// Returns the child that captions this node: a [`Role::Caption`] for
+    /// a [table](Node::is_table), a [`Role::Legend`] for a group such as
+    /// an HTML `fieldset`, or a [`Role::FigureCaption`] for a
+    /// [`Role::Figure`]. Returns `None` for other nodes, or if there is
+    /// no such child.
+    pub fn caption(self) -> Option<Node<'a>> {
+        let caption_role = if self.is_table() {
+            Role::Caption
+        } else {
+            match self.role() {
+                Role::Group => Role::Legend,
+                Role::Figure => Role::FigureCaption,
+                _ => return None,
+            }
+        };
+        self.children().find(|child| child.role() == caption_role)
+    }
+
+    /// Returns the cells of this [table](Node::is_table) as a dense grid,
+    /// indexed by row and then by column, according to each cell's
+    /// [`NodeData::table_cell_row_index`] and
+    /// [`NodeData::table_cell_column_index`]. A cell that spans several
+    /// rows or columns appears in every position that it covers, so that
+    /// the grid can be indexed directly, e.g. to implement UIA's
+    /// `IGridProvider::GetItem`. Positions that aren't covered by any cell
+    /// are `None`.
+    ///
+    /// The grid has [`NodeData::table_row_count`] rows and
+    /// [`NodeData::table_column_count`] columns if they're set, or else
+    /// just enough to cover all of the cells. Cells of nested tables
+    /// aren't included.
+    pub fn table_cells(self) -> Vec<Vec<Option<Node<'a>>>> {
+        fn collect_cells<'a>(node: Node<'a>, cells: &mut Vec<Node<'a>>) {
+            for child in node.children() {
+                let data = child.data();
+                if data.table_cell_row_index.is_some() && data.table_cell_column_index.is_some() {
+                    cells.push(child);
+                } else if !child.is_table() {
+                    collect_cells(child, cells);
+                }
+            }
+        }
+
+        let mut cells = Vec::new();
+        collect_cells(self, &mut cells);
+        // A span of zero is treated as one, so that every cell is in the grid.
+        let extent = |index: Option<usize>, span: Option<usize>| {
+            let index = index.unwrap();
+            index..(index + span.unwrap_or(1).max(1))
+        };
+        let data = self.data();
+        let row_count = data.table_row_count.unwrap_or_else(|| {
+            cells
+                .iter()
+                .map(|cell| {
+                    let data = cell.data();
+                    extent(data.table_cell_row_index, data.table_cell_row_span).end
+                })
+                .max()
+                .unwrap_or(0)
+        });
+        let column_count = data.table_column_count.unwrap_or_else(|| {
+            cells
+                .iter()
+                .map(|cell| {
+                    let data = cell.data();
+                    extent(data.table_cell_column_index, data.table_cell_column_span).end
+                })
+                .max()
+                .unwrap_or(0)
+        });
+
+        let mut grid = vec![vec![None; column_count]; row_count];
+        for cell in cells {
+            let data = cell.data();
+            let rows = extent(data.table_cell_row_index, data.table_cell_row_span);
+            let columns = extent(data.table_cell_column_index, data.table_cell_column_span);
+            for row in grid.iter_mut().take(rows.end).skip(rows.start) {
+                for position in row.iter_mut().take(columns.end).skip(columns.start) {
+                    *position = Some(cell);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Returns true if this node is an item in a set, such as a list,
+    /// tree, menu, or tab list, so that its position within the set
+    /// can be computed from its siblings.
+    pub fn is_set_item(&self) -> bool {
+        matches!(
+            self.role(),
+            Role::ListItem
+                | Role::ListBoxOption
+                | Role::MenuItem
+                | Role::MenuItemCheckBox
+                | Role::MenuItemRadio
+                | Role::MenuListOption
+                | Role::RadioButton
+                | Role::Tab
+                | Role::TreeItem
+        )
+    }
+
+    /// Returns [`NodeData::pos_in_set`], or if that isn't set and this node
+    /// is a [set item](Node::is_set_item), its 1-based position among
+    /// the unignored siblings with the same role.
+    pub fn position_in_set(self) -> Option<usize> {
+        if let Some(pos_in_set) = self.data().pos_in_set {
+            return Some(pos_in_set);
+        }
+        let id = self.id();
+        self.set_items()?
+            .position(|item| item.id() == id)
+            .map(|index| index + 1)
+    }
+
+    /// Returns [`NodeData::set_size`], or if that isn't set and this node
+    /// is a [set item](Node::is_set_item), the number of unignored siblings
+    /// with the same role, including this node.
+    pub fn size_of_set(self) -> Option<usize> {
+        if let Some(set_size) = self.data().set_size {
+            return Some(set_size);
+        }
+        self.set_items().map(Iterator::count)
+    }
+
+    fn set_items(self) -> Option<impl Iterator<Item = Node<'a>>> {
+        if !self.is_set_item() {
+            return None;
+        }
+        let role = self.role();
+        let parent = self.unignored_parent()?;
+        Some(
+            parent
+                .unignored_children()
+                .filter(move |child| child.role() == role),
+        )
+    }
+
+    /// Returns true if this node is the tree's
+    /// [root scroller](accesskit_schema::Tree::root_scroller).
+    pub fn is_root_scroller(&self) -> bool {
+        self.tree_reader.state.data.root_scroller == Some(self.id())
+    }
+
+    /// Returns true if this node is a [`Role::Window`]. Only the root
+    /// of a tree is normally a top-level window; a window elsewhere in
+    /// the tree is one drawn by the application inside its own window.
+    pub fn is_window(&self) -> bool {
+        self.role() == Role::Window
+    }
+
+    /// Returns true if this node is a [`Role::Application`], i.e. a region
+    /// that handles its own keyboard input, so that a screen reader
+    /// should pass keys through rather than using them for navigation.
+    pub fn is_application(&self) -> bool {
+        self.role() == Role::Application
+    }
+
+    /// Returns true if this node is the root of a web document or PDF,
+    /// i.e. the boundary at which platforms start document-level
+    /// navigation, such as moving by heading or landmark.
+    pub fn is_document_root(&self) -> bool {
+        matches!(self.role(), Role::RootWebArea | Role::PdfRoot)
+    }
+
+    /// Returns true if this node is the root of a graphic that should be
+    /// navigated like a document, i.e. an SVG root or an ARIA
+    /// `graphics-document`, such as a chart built from SVG elements.
+    pub fn is_graphics_document(&self) -> bool {
+        matches!(self.role(), Role::SvgRoot | Role::GraphicsDocument)
+    }
+
+    /// Returns true if this node is a [document root](Node::is_document_root)
+    /// or is inside one. A [`Role::WebView`] only hosts web content,
+    /// so it isn't considered web content itself.
+    pub fn is_web_content(&self) -> bool {
+        let mut current = Some(*self);
+        while let Some(node) = current {
+            if node.is_document_root() {
+                return true;
+            }
+            current = node.parent();
+        }
+        false
+    }
+
     pub fn is_descendant_of(&self, ancestor: &Node) -> bool {
         if self.id() == ancestor.id() {
             return true;
@@ -172,29 +662,521 @@ impl<'a> Node<'a> {
         false
     }
 
+    pub fn child_tree_id(&self) -> Option<&str> {
+        self.data().child_tree.as_ref().map(|id| &*id.0)
+    }
+
+    /// Returns the tree embedded at this node, if it has a
+    /// [child tree ID](Node::child_tree_id) and that tree has been
+    /// registered with [`Tree::register_child_tree`].
+    pub fn child_tree(&self) -> Option<Arc<Tree>> {
+        let id = self.data().child_tree.as_ref()?;
+        self.tree_reader.tree.child_tree(id)
+    }
+
+    /// Returns the [registered child tree](Node::child_tree) along with
+    /// the ID of its root. The child tree's root takes the place of this
+    /// node's children. A `Node` only refers to nodes in its own tree,
+    /// so [`Node::children`] and the iterators don't descend into
+    /// the child tree; navigation into this node should continue
+    /// from the returned root instead.
+    pub fn child_tree_root(&self) -> Option<(Arc<Tree>, NodeId)> {
+        let child_tree = self.child_tree()?;
+        let root_id = child_tree.read().root().id();
+        Some((child_tree, root_id))
+    }
+
+    /// If this is the root of a registered child tree, returns the host
+    /// tree along with the ID of the node that hosts this tree, which
+    /// takes the place of this node's parent. This is the reverse of
+    /// [`Node::child_tree_root`]; see [`Tree::host`].
+    pub fn host_node(&self) -> Option<(Arc<Tree>, NodeId)> {
+        if !self.is_root() {
+            return None;
+        }
+        self.tree_reader.tree.host()
+    }
+
     pub fn global_id(&self) -> String {
         format!("{}:{}", self.tree_reader.id().0, self.id().0)
     }
 
     /// Returns the node's bounds relative to the root of the tree.
+    /// If the node or one of its offset containers is transformed,
+    /// this is the smallest rectangle that contains the transformed bounds.
+    /// The result is cached until the node or one of its offset
+    /// containers changes.
     pub fn bounds(&self) -> Option<Rect> {
-        self.data().bounds.as_ref().map(|bounds| {
-            let mut rect = bounds.rect;
-
-            if let Some(offset_id) = bounds.offset_container {
-                let offset_node = self.tree_reader.node_by_id(offset_id).unwrap();
-                let offset_rect = offset_node.bounds().unwrap();
-                rect.left += offset_rect.left;
-                rect.top += offset_rect.top;
+        self.tree_reader.cached(
+            |cache| &mut cache.bounds,
+            self.id(),
+            || {
+                let mut dependencies = Vec::new();
+                let bounds = self.compute_bounds(&mut dependencies);
+                (bounds, dependencies)
+            },
+        )
+    }
+
+    fn compute_bounds(&self, dependencies: &mut Vec<NodeId>) -> Option<Rect> {
+        let rect = self.data().bounds.as_ref()?.rect;
+        let local_rect = Rect {
+            left: 0.0,
+            top: 0.0,
+            ..rect
+        };
+        Some(self.local_rect_to_global(&local_rect, dependencies))
+    }
+
+    // Returns the smallest rectangle that contains the given rectangle
+    // in local coordinates once it's converted to global coordinates.
+    fn local_rect_to_global(&self, rect: &Rect, dependencies: &mut Vec<NodeId>) -> Rect {
+        let transform = self.local_to_global_transform(dependencies);
+        let right = rect.left + rect.width;
+        let bottom = rect.top + rect.height;
+        let corners = [
+            transform.apply(rect.left, rect.top),
+            transform.apply(right, rect.top),
+            transform.apply(rect.left, bottom),
+            transform.apply(right, bottom),
+        ];
+        let left = corners
+            .iter()
+            .map(|(x, _)| *x)
+            .fold(f32::INFINITY, f32::min);
+        let top = corners
+            .iter()
+            .map(|(_, y)| *y)
+            .fold(f32::INFINITY, f32::min);
+        let right = corners
+            .iter()
+            .map(|(x, _)| *x)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let bottom = corners
+            .iter()
+            .map(|(_, y)| *y)
+            .fold(f32::NEG_INFINITY, f32::max);
+        Rect {
+            left,
+            top,
+            width: right - left,
+            height: bottom - top,
+        }
+    }
+
+    /// Returns the bounds of the character at `index` in an inline text
+    /// box, in the same coordinate space as [`Node::bounds`], or `None`
+    /// if the node has no bounds or fewer character offsets. Each of
+    /// [`NodeData::character_offsets`] is the end of a character,
+    /// measured from the edge of the node's bounds where the text starts,
+    /// as given by the [effective text direction](Node::effective_text_direction). The character spans the full height of the
+    /// node for horizontal text, or the full width for vertical text.
+    pub fn character_bounds(&self, index: usize) -> Option<Rect> {
+        let data = self.data();
+        let rect = data.bounds.as_ref()?.rect;
+        let offsets = &data.character_offsets;
+        let end = *offsets.get(index)?;
+        let start = if index == 0 { 0.0 } else { offsets[index - 1] };
+        let local_rect = match self.effective_text_direction() {
+            TextDirection::LeftToRight => Rect {
+                left: start,
+                top: 0.0,
+                width: end - start,
+                height: rect.height,
+            },
+            TextDirection::RightToLeft => Rect {
+                left: rect.width - end,
+                top: 0.0,
+                width: end - start,
+                height: rect.height,
+            },
+            TextDirection::TopToBottom => Rect {
+                left: 0.0,
+                top: start,
+                width: rect.width,
+                height: end - start,
+            },
+            TextDirection::BottomToTop => Rect {
+                left: 0.0,
+                top: rect.height - end,
+                width: rect.width,
+                height: end - start,
+            },
+        };
+        Some(self.local_rect_to_global(&local_rect, &mut Vec::new()))
+    }
+
+    /// Returns true if the user can edit text in this node, either because
+    /// it has one of the text field roles or because it's
+    /// [editable](NodeData::editable).
+    pub fn is_text_field(&self) -> bool {
+        matches!(
+            self.role(),
+            Role::TextField | Role::SearchBox | Role::TextFieldWithComboBox
+        ) || self.data().editable
+    }
+
+    /// Returns true if the text in this node can currently be edited,
+    /// which determines e.g. whether a platform exposes the text as
+    /// read-only. The rules are applied in this order:
+    ///
+    /// 1. A [read-only](NodeData::read_only) or
+    ///    [disabled](Node::is_disabled) node isn't editable, even if it's
+    ///    marked [editable](NodeData::editable).
+    /// 2. The root of a non-atomic text field, such as a content-editable
+    ///    region or an ARIA textbox with interactive descendants, is only
+    ///    editable if it's marked editable, since such a textbox may not
+    ///    currently accept input.
+    /// 3. Otherwise, any [text field](Node::is_text_field) is editable,
+    ///    including the editable descendants of a content-editable region.
+    pub fn is_editable_text(&self) -> bool {
+        let data = self.data();
+        if data.read_only || self.is_disabled() {
+            false
+        } else if data.nonatomic_text_field_root {
+            data.editable
+        } else {
+            self.is_text_field()
+        }
+    }
+
+    /// Returns the nearest ancestor that is a
+    /// [text field](Node::is_text_field), not including this node.
+    /// For a [`Role::Caret`], this is the field the caret belongs to.
+    pub fn containing_text_field(self) -> Option<Node<'a>> {
+        std::iter::successors(self.parent(), |node| node.parent()).find(|node| node.is_text_field())
+    }
+
+    /// Returns the first [`Role::Caret`] among this node's descendants,
+    /// in document order, that belongs to this node rather than to a
+    /// nested text field.
+    pub fn caret(self) -> Option<Node<'a>> {
+        self.children().find_map(|child| {
+            if child.role() == Role::Caret {
+                Some(child)
+            } else if child.is_text_field() {
+                None
+            } else {
+                child.caret()
+            }
+        })
+    }
+
+    /// Returns the bounds of this text field's [caret](Node::caret),
+    /// i.e. the insertion point, in the same coordinate space as
+    /// [`Node::bounds`]. Returns `None` if the field has no caret node,
+    /// or the caret has no bounds.
+    pub fn caret_position(&self) -> Option<Rect> {
+        self.caret()?.bounds()
+    }
+
+    /// Returns the nodes that hold the text of this text field or
+    /// document, in document order: its [inline text boxes](Role::InlineTextBox),
+    /// not including those of nested text fields. If it has none,
+    /// the node holds its own [text](Node::text).
+    pub fn text_runs(self) -> Vec<Node<'a>> {
+        let mut runs = Vec::new();
+        self.add_text_runs(&mut runs);
+        if runs.is_empty() {
+            runs.push(self);
+        }
+        runs
+    }
+
+    fn add_text_runs(self, runs: &mut Vec<Node<'a>>) {
+        for child in self.children() {
+            if child.role() == Role::InlineTextBox {
+                runs.push(child);
+            } else if !child.is_text_field() {
+                child.add_text_runs(runs);
+            }
+        }
+    }
+
+    /// Returns the horizontal scroll position as a percentage of the
+    /// scrollable range, clamped to 0–100. [`NodeData::scroll_x_min`]
+    /// defaults to zero. Returns `None` if [`NodeData::scroll_x`] isn't set,
+    /// or if the range is empty, i.e. the node can't scroll horizontally.
+    pub fn scroll_percent_x(&self) -> Option<f32> {
+        let data = self.data();
+        scroll_percent(data.scroll_x?, data.scroll_x_min, data.scroll_x_max?)
+    }
+
+    /// The vertical equivalent of [`Node::scroll_percent_x`].
+    pub fn scroll_percent_y(&self) -> Option<f32> {
+        let data = self.data();
+        scroll_percent(data.scroll_y?, data.scroll_y_min, data.scroll_y_max?)
+    }
+
+    /// Converts a point in this node's local coordinate space, whose origin
+    /// is the top left corner of the node's bounds, to the coordinate space
+    /// of [`Node::bounds`]. This walks up the node's offset containers,
+    /// applying each one's offset, scroll position, and transform.
+    /// A node without bounds is treated as if its local origin were
+    /// the origin of its parent's coordinate space, so the point is
+    /// returned unchanged.
+    pub fn local_to_global(&self, x: f32, y: f32) -> (f32, f32) {
+        self.local_to_global_transform(&mut Vec::new()).apply(x, y)
+    }
+
+    /// The inverse of [`Node::local_to_global`]. Returns `None` if the node
+    /// or one of its offset containers has a transform that can't be
+    /// inverted, e.g. one that scales by zero.
+    pub fn global_to_local(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        self.local_to_global_transform(&mut Vec::new())
+            .inverse()
+            .map(|transform| transform.apply(x, y))
+    }
+
+    // Returns bounds equivalent to the node's own, but with the effect
+    // of its offset containers folded into the transform, so they no
+    // longer refer to any other node.
+    pub(crate) fn standalone_bounds(&self) -> Option<RelativeBounds> {
+        let rect = self.data().bounds.as_ref()?.rect;
+        let transform = self.local_to_global_transform(&mut Vec::new());
+        Some(RelativeBounds {
+            offset_container: None,
+            rect: Rect {
+                left: 0.0,
+                top: 0.0,
+                ..rect
+            },
+            transform: Some(Box::new(transform.to_transform())),
+        })
+    }
+
+    fn local_to_global_transform(&self, dependencies: &mut Vec<NodeId>) -> Affine {
+        dependencies.push(self.id());
+        let bounds = match &self.data().bounds {
+            Some(bounds) => bounds,
+            None => return Affine::IDENTITY,
+        };
+        let mut transform = Affine::translate(bounds.rect.left, bounds.rect.top);
+        if let Some(node_transform) = &bounds.transform {
+            transform = transform.then(&Affine::from(&**node_transform));
+        }
+        if let Some(offset_id) = bounds.offset_container {
+            let container = self.tree_reader.node_by_id(offset_id).unwrap();
+            let container_data = container.data();
+            transform = transform
+                .then(&Affine::translate(
+                    -container_data.scroll_x.unwrap_or(0.0),
+                    -container_data.scroll_y.unwrap_or(0.0),
+                ))
+                .then(&container.local_to_global_transform(dependencies));
+        }
+        transform
+    }
+
+    /// Returns the actions that can be performed on this node, including
+    /// the ones implied by its role and state. In addition to the explicit
+    /// [`NodeData::actions`], the following are derived:
+    ///
+    /// * [`Action::Focus`] if the node is focusable.
+    /// * [`Action::Default`] if the node can be checked, e.g. a check box
+    ///   or anything with a checked state.
+    /// * [`Action::Increment`] and [`Action::Decrement`] if the node is
+    ///   a range control, e.g. a slider, that isn't read-only.
+    /// * [`Action::Expand`] if the node is collapsed, or [`Action::Collapse`]
+    ///   if it is expanded.
+    ///
+    /// A disabled node has no implicit actions. The result is in
+    /// the order in which [`Action`] variants are declared.
+    pub fn effective_actions(&self) -> Vec<Action> {
+        let data = self.data();
+        let mut actions = data.actions;
+        if !self.is_disabled() {
+            if self.is_focusable() {
+                actions |= Action::Focus;
+            }
+            if self.is_checkable() {
+                actions |= Action::Default;
             }
+            if self.is_range() && !data.read_only {
+                actions |= Action::Increment | Action::Decrement;
+            }
+            match data.expanded {
+                Some(true) => actions |= Action::Collapse,
+                Some(false) => actions |= Action::Expand,
+                None => (),
+            }
+        }
+        actions.iter().collect()
+    }
 
-            // TODO: handle transform
-            assert!(bounds.transform.is_none());
+    fn is_checkable(&self) -> bool {
+        self.data().checked_state.is_some()
+            || matches!(
+                self.role(),
+                Role::CheckBox
+                    | Role::MenuItemCheckBox
+                    | Role::MenuItemRadio
+                    | Role::RadioButton
+                    | Role::Switch
+                    | Role::ToggleButton
+            )
+    }
+
+    // A splitter is only adjustable if it has a position, as with
+    // a focusable ARIA separator.
+    fn is_range(&self) -> bool {
+        match self.role() {
+            Role::Slider | Role::SpinButton | Role::ScrollBar => true,
+            Role::Splitter => self.data().value_for_range.is_some(),
+            _ => false,
+        }
+    }
 
-            rect
+    /// Returns [`NodeData::orientation`] if it's set. Otherwise, a splitter
+    /// is horizontal, which is the ARIA default for a separator, and any
+    /// other node has no orientation.
+    pub fn orientation(&self) -> Option<Orientation> {
+        self.data().orientation.or(match self.role() {
+            Role::Splitter => Some(Orientation::Horizontal),
+            _ => None,
         })
     }
 
+    pub fn step_value_for_range(&self) -> Option<f32> {
+        self.data().step_value_for_range
+    }
+
+    /// Returns [`NodeData::large_step_value_for_range`] if it's set,
+    /// or else 10 times [`NodeData::step_value_for_range`].
+    pub fn large_step_value_for_range(&self) -> Option<f32> {
+        let data = self.data();
+        data.large_step_value_for_range
+            .or_else(|| data.step_value_for_range.map(|step| step * 10.0))
+    }
+
+    /// Requests that the value of this range control be increased
+    /// by one step. A small step is requested as [`Action::Increment`],
+    /// leaving the step size up to the provider. There's no action
+    /// for a large step, so it's requested as [`Action::SetValue`] with
+    /// the new value, clamped to the range's minimum and maximum; if
+    /// the current value or the large step is unknown, this falls back
+    /// to [`Action::Increment`].
+    pub fn increment(&self, step: RangeStep) {
+        self.step(step, Action::Increment, 1.0);
+    }
+
+    /// Requests that the value of this range control be decreased
+    /// by one step. See [`Node::increment`].
+    pub fn decrement(&self, step: RangeStep) {
+        self.step(step, Action::Decrement, -1.0);
+    }
+
+    fn step(&self, step: RangeStep, action: Action, direction: f32) {
+        let data = self.data();
+        let large_step = match step {
+            RangeStep::Small => None,
+            RangeStep::Large => self.large_step_value_for_range(),
+        };
+        if let (Some(value), Some(large_step)) = (data.value_for_range, large_step) {
+            let mut value = value + direction * large_step;
+            if let Some(min) = data.min_value_for_range {
+                value = value.max(min);
+            }
+            if let Some(max) = data.max_value_for_range {
+                value = value.min(max);
+            }
+            self.set_numeric_value(value.into());
+        } else {
+            self.tree_reader.tree.do_action(ActionRequest {
+                action,
+                target: self.id(),
+                data: None,
+            });
+        }
+    }
+
+    /// Requests that the value of this range control be set to
+    /// the given value.
+    pub fn set_numeric_value(&self, value: f64) {
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::SetValue,
+            target: self.id(),
+            data: Some(ActionData::NumericValue(value)),
+        });
+    }
+
+    /// Requests the node's default action, e.g. a click on a button or
+    /// toggling a check box, as [`Action::Default`].
+    pub fn do_default_action(&self) {
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::Default,
+            target: self.id(),
+            data: None,
+        });
+    }
+
+    /// Requests that this node's tooltip be shown, as
+    /// [`Action::ShowTooltip`], e.g. when a screen reader user asks
+    /// for more information about the node. See [`Node::tooltip_node`].
+    pub fn show_tooltip(&self) {
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::ShowTooltip,
+            target: self.id(),
+            data: None,
+        });
+    }
+
+    /// Requests that this node's tooltip be hidden, as
+    /// [`Action::HideTooltip`].
+    pub fn hide_tooltip(&self) {
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::HideTooltip,
+            target: self.id(),
+            data: None,
+        });
+    }
+
+    /// Requests that the text selection in this text field or document
+    /// be set to the given selection, as [`Action::SetTextSelection`].
+    pub fn set_text_selection(&self, selection: TextSelection) {
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::SetTextSelection,
+            target: self.id(),
+            data: Some(ActionData::SetTextSelection(selection)),
+        });
+    }
+
+    /// Requests that any scrollable containers be scrolled to make this
+    /// node visible, as [`Action::ScrollIntoView`].
+    pub fn scroll_into_view(&self) {
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::ScrollIntoView,
+            target: self.id(),
+            data: None,
+        });
+    }
+
+    /// Requests that the selected text in this editable node be replaced
+    /// with the given text, as [`Action::ReplaceSelectedText`]. If the
+    /// selection is collapsed or unknown, this inserts the text at the
+    /// caret, so replacing it with empty text does nothing and no request
+    /// is sent. Nothing is sent either if the node
+    /// [isn't editable](Node::is_editable_text). The new value and
+    /// selection take effect when the application sends the corresponding
+    /// tree update.
+    pub fn replace_selected_text(&self, text: &str) {
+        if !self.is_editable_text() {
+            return;
+        }
+        let is_insertion = match self.data().text_selection {
+            Some(selection) => selection.is_collapsed(),
+            None => true,
+        };
+        if is_insertion && text.is_empty() {
+            return;
+        }
+        self.tree_reader.tree.do_action(ActionRequest {
+            action: Action::ReplaceSelectedText,
+            target: self.id(),
+            data: Some(ActionData::Value(text.into())),
+        });
+    }
+
     // Convenience getters
 
     pub fn id(&self) -> NodeId {
@@ -213,6 +1195,31 @@ impl<'a> Node<'a> {
         self.data().disabled
     }
 
+    /// Returns false if this node or any of its ancestors is disabled,
+    /// since a disabled container, such as a disabled group of form
+    /// controls, disables everything in it.
+    pub fn is_effectively_enabled(&self) -> bool {
+        std::iter::successors(Some(*self), |node| node.parent()).all(|node| !node.is_disabled())
+    }
+
+    /// Returns true if this is a meter, i.e. a gauge showing a measurement
+    /// within a known range, such as battery charge or disk usage, rather
+    /// than a [`Role::ProgressIndicator`] showing how far along a task is.
+    /// Both report their value with the range attributes.
+    pub fn is_meter(&self) -> bool {
+        self.role() == Role::Meter
+    }
+
+    /// Returns true if this is a progress indicator or meter that's busy
+    /// but doesn't report how far along it is, i.e. it has
+    /// [`NodeData::busy`] set and no [`NodeData::value_for_range`].
+    pub fn is_indeterminate(&self) -> bool {
+        let data = self.data();
+        matches!(data.role, Role::ProgressIndicator | Role::Meter)
+            && data.busy
+            && data.value_for_range.is_none()
+    }
+
     pub fn name(&self) -> Option<&str> {
         if let Some(name) = &self.data().name {
             Some(name)
@@ -221,50 +1228,498 @@ impl<'a> Node<'a> {
         }
     }
 
-    pub(crate) fn first_unignored_child(self) -> Option<Node<'a>> {
-        for child in self.children() {
-            if !child.is_ignored() {
-                return Some(child);
-            }
-            if let Some(descendant) = child.first_unignored_child() {
-                return Some(descendant);
-            }
-        }
-        None
+    /// Returns true if this node can be grabbed for drag and drop,
+    /// i.e. [`NodeData::grabbed`] is set, whether or not it's
+    /// currently grabbed.
+    pub fn is_draggable(&self) -> bool {
+        self.data().grabbed.is_some()
     }
 
-    pub(crate) fn last_unignored_child(self) -> Option<Node<'a>> {
-        for child in self.children().rev() {
-            if !child.is_ignored() {
-                return Some(child);
-            }
-            if let Some(descendant) = child.last_unignored_child() {
-                return Some(descendant);
-            }
+    /// Returns true if this node is currently grabbed for drag and drop.
+    pub fn is_grabbed(&self) -> bool {
+        self.data().grabbed == Some(true)
+    }
+
+    /// Returns the nodes that are being dragged along with this one, i.e.
+    /// this node and any of its siblings that are also grabbed, in order.
+    /// If this node isn't grabbed, there are none.
+    pub fn grabbed_items(self) -> Vec<Node<'a>> {
+        if !self.is_grabbed() {
+            return Vec::new();
+        }
+        match self.parent() {
+            Some(parent) => parent
+                .children()
+                .filter(|sibling| sibling.is_grabbed())
+                .collect(),
+            None => vec![self],
         }
-        None
     }
-}
 
-#[derive(Clone)]
-pub struct WeakNode {
-    pub tree: Weak<Tree>,
-    pub id: NodeId,
-}
+    /// Returns the effects of dropping a dragged item onto this node,
+    /// in the order in which [`DropEffect`] variants are declared.
+    /// The node is a drop target if this isn't empty.
+    pub fn drop_effects(&self) -> Vec<DropEffect> {
+        self.data().drop_effects.iter().collect()
+    }
 
-impl WeakNode {
-    pub fn map<F, T>(&self, f: F) -> Option<T>
-    where
-        for<'a> F: FnOnce(Node<'a>) -> T,
-    {
-        self.tree
-            .upgrade()
-            .map(|tree| tree.read().node_by_id(self.id).map(|node| f(node)))
-            .flatten()
+    /// Returns how changes to this node should be announced, parsed with
+    /// [`parse_live_status`] from [`NodeData::live_status`], or if that isn't
+    /// set, from [`NodeData::container_live_status`], which describes the
+    /// live region the node is in. Returns [`LivePoliteness::Off`] if
+    /// neither is set.
+    pub fn live_status(&self) -> LivePoliteness {
+        let data = self.data();
+        data.live_status
+            .as_deref()
+            .or(data.container_live_status.as_deref())
+            .map_or(LivePoliteness::Off, parse_live_status)
     }
-}
 
-impl Node<'_> {
+    /// Returns the kinds of changes to this node that should be announced,
+    /// parsed with [`parse_live_relevant`] from [`NodeData::live_relevant`],
+    /// or if that isn't set, from [`NodeData::container_live_relevant`].
+    /// If neither is set, the result is the default of additions and text.
+    /// The kinds are in the order in which [`LiveRelevant`] variants
+    /// are declared.
+    pub fn live_relevant(&self) -> Vec<LiveRelevant> {
+        let data = self.data();
+        parse_live_relevant(
+            data.live_relevant
+                .as_deref()
+                .or(data.container_live_relevant.as_deref())
+                .unwrap_or(""),
+        )
+        .iter()
+        .collect()
+    }
+
+    /// Returns the keyboard shortcuts that activate or focus this node,
+    /// as given by [`NodeData::key_shortcuts`]. As in `aria-keyshortcuts`,
+    /// shortcuts are separated by whitespace, and each is a series of keys
+    /// joined by `+`, e.g. `"Control+Shift+S"`.
+    pub fn key_shortcuts(&self) -> Vec<&str> {
+        self.data()
+            .key_shortcuts
+            .as_deref()
+            .map_or_else(Vec::new, |shortcuts| {
+                shortcuts.split_ascii_whitespace().collect()
+            })
+    }
+
+    /// Returns the first of the node's [key shortcuts](Node::key_shortcuts),
+    /// which is the one a menu item would normally show as its accelerator.
+    pub fn accelerator(&self) -> Option<&str> {
+        self.key_shortcuts().into_iter().next()
+    }
+
+    /// Returns how this node offers completions as the user types, as given
+    /// by [`NodeData::auto_complete`]. As with `aria-autocomplete`, this is
+    /// usually `"inline"`, `"list"`, or `"both"`.
+    pub fn auto_complete(&self) -> Option<&str> {
+        self.data().auto_complete.as_deref()
+    }
+
+    /// Returns which kind of current item this node is, e.g. the current
+    /// page in a navigation menu, as given by [`NodeData::aria_current`].
+    /// [`AriaCurrent::False`] is treated the same as no value.
+    pub fn aria_current(&self) -> Option<AriaCurrent> {
+        self.data()
+            .aria_current
+            .filter(|current| *current != AriaCurrent::False)
+    }
+
+    /// Returns the class name of the underlying UI element, as given by
+    /// [`NodeData::class_name`], e.g. a CSS class or a native widget class.
+    pub fn class_name(&self) -> Option<&str> {
+        self.data().class_name.as_deref()
+    }
+
+    /// Returns the tag name of the underlying HTML element, e.g. `"div"`,
+    /// as given by [`NodeData::html_tag`].
+    pub fn html_tag(&self) -> Option<&str> {
+        self.data().html_tag.as_deref()
+    }
+
+    /// Returns the type of an HTML input, e.g. `"email"` or `"password"`,
+    /// as given by [`NodeData::input_type`].
+    pub fn input_type(&self) -> Option<&str> {
+        self.data().input_type.as_deref()
+    }
+
+    /// Returns the selected color of a [`Role::ColorWell`], or of any
+    /// other node with a [`NodeData::color_value`].
+    pub fn color_value(&self) -> Option<Color> {
+        self.data().color_value.map(Color::from)
+    }
+
+    /// Returns the target of a link, or the URL of a document or image.
+    pub fn url(&self) -> Option<&str> {
+        self.data().url.as_deref()
+    }
+
+    /// Returns the node's text. The text of an inline text box or static
+    /// text is its name, while that of a text field is its value.
+    pub fn text(&self) -> &str {
+        let data = self.data();
+        data.value.as_deref().or(data.name.as_deref()).unwrap_or("")
+    }
+
+    /// Returns [`NodeData::words`] if it's set, or else word boundaries
+    /// computed from the node's text, i.e. its value or else its name.
+    /// Either way, the ranges are in code units for
+    /// [`TreeData::source_string_encoding`](crate::TreeData::source_string_encoding).
+    pub fn word_ranges(&self) -> Vec<Range<usize>> {
+        let words = &self.data().words;
+        if words.is_empty() {
+            text::word_ranges(self.text(), self.string_encoding())
+        } else {
+            words.to_vec()
+        }
+    }
+
+    /// Returns sentence boundaries computed from the node's text, in the
+    /// same units as [`Node::word_ranges`].
+    pub fn sentence_ranges(&self) -> Vec<Range<usize>> {
+        text::sentence_ranges(self.text(), self.string_encoding())
+    }
+
+    fn string_encoding(&self) -> StringEncoding {
+        self.tree_reader.string_encoding()
+    }
+
+    /// Returns the node's name, or if it has none, the name of its
+    /// [caption](Node::caption), or if its name may come from its contents,
+    /// as for a button or link, the names of its visible descendants joined
+    /// with spaces. The result is cached until the node
+    /// or one of its descendants changes.
+    pub fn computed_name(&self) -> Option<String> {
+        self.tree_reader.cached(
+            |cache| &mut cache.names,
+            self.id(),
+            || {
+                let mut dependencies = vec![self.id()];
+                let name = if let Some(name) = self.name() {
+                    Some(name.into())
+                } else if let Some(caption) = self.caption_for_name() {
+                    dependencies.push(caption.id());
+                    if let Some(name) = caption.name() {
+                        Some(name.into())
+                    } else {
+                        let mut parts = Vec::new();
+                        caption.collect_name_parts(&mut parts, &mut dependencies);
+                        if parts.is_empty() {
+                            None
+                        } else {
+                            Some(parts.join(" "))
+                        }
+                    }
+                } else if self.is_name_from_contents() {
+                    let mut parts = Vec::new();
+                    self.collect_name_parts(&mut parts, &mut dependencies);
+                    if parts.is_empty() {
+                        None
+                    } else {
+                        Some(parts.join(" "))
+                    }
+                } else {
+                    None
+                };
+                (name, dependencies)
+            },
+        )
+    }
+
+    fn caption_for_name(&self) -> Option<Node<'a>> {
+        match self.data().name_from {
+            None | Some(NameFrom::Caption) => self.caption(),
+            _ => None,
+        }
+    }
+
+    fn is_name_from_contents(&self) -> bool {
+        self.data().name_from == Some(NameFrom::Contents)
+            || matches!(
+                self.role(),
+                Role::Button
+                    | Role::Cell
+                    | Role::CheckBox
+                    | Role::ColumnHeader
+                    | Role::Heading
+                    | Role::Link
+                    | Role::ListBoxOption
+                    | Role::MenuItem
+                    | Role::MenuItemCheckBox
+                    | Role::MenuItemRadio
+                    | Role::MenuListOption
+                    | Role::RadioButton
+                    | Role::RowHeader
+                    | Role::Switch
+                    | Role::Tab
+                    | Role::ToggleButton
+                    | Role::Tooltip
+                    | Role::TreeItem
+            )
+    }
+
+    /// Returns the node's name along with where it came from, applying
+    /// the precedence of the accessible name computation: the nodes in
+    /// [`NodeData::labelled_by`], then [`NodeData::name`], then the node's
+    /// [caption](Node::caption), then the value of a button, then its
+    /// contents if its name may come from them, and finally its
+    /// [tooltip](NodeData::tooltip) or [placeholder](NodeData::placeholder).
+    /// The name is the same as [`Node::computed_name`] whenever the
+    /// latter returns one, except when labels are given. Unlike
+    /// [`Node::computed_name`], the result isn't cached.
+    pub fn resolved_name(&self) -> Option<(String, NameFrom)> {
+        let labels = self
+            .labelled_by()
+            .filter_map(|label| label.name_or_contents())
+            .filter(|label| !label.is_empty())
+            .collect::<Vec<_>>();
+        if !labels.is_empty() {
+            return Some((labels.join(" "), NameFrom::RelatedElement));
+        }
+        let data = self.data();
+        if let Some(name) = self.name() {
+            return Some((name.into(), data.name_from.unwrap_or(NameFrom::Attribute)));
+        }
+        if let Some(caption) = self.caption_for_name() {
+            if let Some(name) = caption.name_or_contents() {
+                let name_from = match caption.role() {
+                    Role::Caption => NameFrom::Caption,
+                    _ => NameFrom::RelatedElement,
+                };
+                return Some((name, name_from));
+            }
+        }
+        fn non_empty(text: &Option<Box<str>>) -> Option<&str> {
+            text.as_deref().filter(|text| !text.is_empty())
+        }
+        if self.role() == Role::Button {
+            if let Some(value) = non_empty(&data.value) {
+                return Some((value.into(), NameFrom::Value));
+            }
+        }
+        if self.is_name_from_contents() {
+            if let Some(name) = self.contents_name() {
+                return Some((name, NameFrom::Contents));
+            }
+        }
+        if let Some(tooltip) = non_empty(&data.tooltip) {
+            return Some((tooltip.into(), NameFrom::Title));
+        }
+        non_empty(&data.placeholder).map(|placeholder| (placeholder.into(), NameFrom::Placeholder))
+    }
+
+    // The node's own name, or failing that, the names of its visible
+    // descendants, as used when this node names another one.
+    fn name_or_contents(self) -> Option<String> {
+        match self.name() {
+            Some(name) => Some(name.into()),
+            None => self.contents_name(),
+        }
+    }
+
+    fn contents_name(self) -> Option<String> {
+        let mut parts = Vec::new();
+        self.collect_name_parts(&mut parts, &mut Vec::new());
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+
+    fn collect_name_parts(self, parts: &mut Vec<String>, dependencies: &mut Vec<NodeId>) {
+        for child in self.children() {
+            dependencies.push(child.id());
+            if child.is_invisible() {
+                continue;
+            }
+            match child.name() {
+                Some(name) if !child.is_ignored() => {
+                    if !name.is_empty() {
+                        parts.push(name.into());
+                    }
+                }
+                _ => child.collect_name_parts(parts, dependencies),
+            }
+        }
+    }
+
+    /// Returns true if this node is an abbreviation or acronym,
+    /// i.e. a [`Role::Abbr`].
+    pub fn is_abbreviation(&self) -> bool {
+        self.role() == Role::Abbr
+    }
+
+    /// Returns the expanded form of an [abbreviation](Node::is_abbreviation),
+    /// which is its [tooltip](NodeData::tooltip), as with the `title`
+    /// attribute of an HTML `abbr` element, or failing that, its
+    /// [description](NodeData::description). Returns `None` for other nodes.
+    pub fn abbreviation_expansion(&self) -> Option<&str> {
+        if !self.is_abbreviation() {
+            return None;
+        }
+        let data = self.data();
+        data.tooltip.as_deref().or(data.description.as_deref())
+    }
+
+    /// Returns the application's localized description of the node's role,
+    /// e.g. "tab" in the application's language, if it provided one.
+    pub fn role_description(&self) -> Option<&str> {
+        self.data().role_description.as_deref()
+    }
+
+    /// Returns the application's localized description of the node's
+    /// checked state, e.g. "on" for a switch, if it provided one.
+    pub fn checked_state_description(&self) -> Option<&str> {
+        self.data().checked_state_description.as_deref()
+    }
+
+    /// Returns the node that describes why this node's value is invalid,
+    /// if [`NodeData::error_message`] refers to a node in this tree.
+    pub fn error_message(self) -> Option<Node<'a>> {
+        self.data()
+            .error_message
+            .and_then(|id| self.tree_reader.node_by_id(id))
+    }
+
+    /// Returns the nodes that this node controls, as listed in
+    /// [`NodeData::controls`]. Like the other relation iterators below,
+    /// the IDs are resolved lazily, in order, and any that aren't in
+    /// the tree are skipped.
+    pub fn controls(
+        self,
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        self.related(&self.state.data.controls)
+    }
+
+    /// Returns the nodes listed in [`NodeData::details`].
+    pub fn details(
+        self,
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        self.related(&self.state.data.details)
+    }
+
+    /// Returns the nodes listed in [`NodeData::described_by`].
+    pub fn described_by(
+        self,
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        self.related(&self.state.data.described_by)
+    }
+
+    /// Returns the nodes that follow this one in an alternate reading
+    /// order, as listed in [`NodeData::flow_to`].
+    pub fn flows_to(
+        self,
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        self.related(&self.state.data.flow_to)
+    }
+
+    /// Returns the nodes listed in [`NodeData::labelled_by`].
+    pub fn labelled_by(
+        self,
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        self.related(&self.state.data.labelled_by)
+    }
+
+    fn related(
+        self,
+        ids: &'a [NodeId],
+    ) -> impl DoubleEndedIterator<Item = Node<'a>> + FusedIterator<Item = Node<'a>> + 'a {
+        let reader = self.tree_reader;
+        ids.iter().filter_map(move |id| reader.node_by_id(*id))
+    }
+
+    /// Returns the tooltip that describes this node, i.e. the first node
+    /// in [`NodeData::described_by`] with [`Role::Tooltip`], if any.
+    /// The tooltip may be invisible until it's requested with
+    /// [`Node::show_tooltip`].
+    pub fn tooltip_node(self) -> Option<Node<'a>> {
+        self.described_by()
+            .find(|node| node.role() == Role::Tooltip)
+    }
+
+    /// Returns this node's [`NodeData::font_family`], or if that isn't set,
+    /// that of the nearest ancestor that sets it, since the font family
+    /// is only specified where it differs from the parent.
+    pub fn effective_font_family(self) -> Option<&'a str> {
+        self.inherited(|data| data.font_family.as_deref())
+    }
+
+    /// Returns this node's [`NodeData::language`], or if that isn't set,
+    /// that of the nearest ancestor that sets it, since the language
+    /// is only specified where it differs from the parent.
+    pub fn effective_language(self) -> Option<&'a str> {
+        self.inherited(|data| data.language.as_deref())
+    }
+
+    /// Returns this node's [`NodeData::text_direction`], or if that isn't
+    /// set, that of the nearest ancestor that sets it. If no ancestor sets
+    /// it either, the text is assumed to be left-to-right.
+    pub fn effective_text_direction(&self) -> TextDirection {
+        self.inherited(|data| data.text_direction.as_ref())
+            .copied()
+            .unwrap_or(TextDirection::LeftToRight)
+    }
+
+    fn inherited<T: ?Sized>(self, f: impl Fn(&'a NodeData) -> Option<&'a T>) -> Option<&'a T> {
+        let mut node = Some(self);
+        while let Some(current) = node {
+            if let Some(value) = f(&current.state.data) {
+                return Some(value);
+            }
+            node = current.parent();
+        }
+        None
+    }
+
+    pub(crate) fn first_unignored_child(self) -> Option<Node<'a>> {
+        for child in self.children() {
+            if !child.is_ignored() {
+                return Some(child);
+            }
+            if let Some(descendant) = child.first_unignored_child() {
+                return Some(descendant);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn last_unignored_child(self) -> Option<Node<'a>> {
+        for child in self.children().rev() {
+            if !child.is_ignored() {
+                return Some(child);
+            }
+            if let Some(descendant) = child.last_unignored_child() {
+                return Some(descendant);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone)]
+pub struct WeakNode {
+    pub tree: Weak<Tree>,
+    pub id: NodeId,
+}
+
+impl WeakNode {
+    pub fn map<F, T>(&self, f: F) -> Option<T>
+    where
+        for<'a> F: FnOnce(Node<'a>) -> T,
+    {
+        self.tree
+            .upgrade()
+            .map(|tree| tree.read().node_by_id(self.id).map(|node| f(node)))
+            .flatten()
+    }
+}
+
+impl Node<'_> {
     pub fn downgrade(&self) -> WeakNode {
         WeakNode {
             tree: Arc::downgrade(self.tree_reader.tree),
@@ -273,10 +1728,27 @@ impl Node<'_> {
     }
 }
 
+fn scroll_percent(value: f32, min: Option<f32>, max: f32) -> Option<f32> {
+    let min = min.unwrap_or(0.0);
+    if max > min {
+        Some(((value - min) / (max - min) * 100.0).clamp(0.0, 100.0))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use accesskit_schema::Rect;
+    use accesskit_schema::{
+        Action, ActionData, ActionHandler, ActionRequest, AriaCurrent, CheckedState, Color,
+        DropEffect, LivePoliteness, LiveRelevant, NameFrom, Node, NodeId, Orientation, Rect,
+        RelativeBounds, Role, TextDirection, TextSelection, Transform, TreeId, TreeUpdate,
+        TreeUpdateBuilder,
+    };
+    use std::num::NonZeroU64;
+    use std::sync::{Arc, Mutex};
 
+    use super::{FilterMode, RangeStep};
     use crate::tests::*;
 
     #[test]
@@ -309,6 +1781,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn borrowed_and_owned_views_agree() {
+        let tree = test_tree();
+        let owned = tree.serialize().nodes;
+        let reader = tree.read();
+        for data in &owned {
+            let node = reader.node_by_id(data.id).unwrap();
+            assert_eq!(data, node.data());
+            // Every view of the node borrows the same data.
+            let again = reader.node_by_id(data.id).unwrap();
+            assert!(std::ptr::eq(node.data(), again.data()));
+            for child in node.children() {
+                let parent = child.parent().unwrap();
+                assert!(std::ptr::eq(node.data(), parent.data()));
+            }
+        }
+    }
+
     #[test]
     fn deepest_first_child() {
         let tree = test_tree();
@@ -333,6 +1823,62 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn filtered_children_in_each_mode() {
+        let tree = test_tree();
+        let reader = tree.read();
+        let root = reader.root();
+        let ids = |mode| {
+            root.filtered_children(mode)
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        };
+        assert_eq!(
+            vec![
+                PARAGRAPH_0_ID,
+                STATIC_TEXT_1_0_ID,
+                PARAGRAPH_2_ID,
+                STATIC_TEXT_3_1_0_ID,
+                BUTTON_3_2_ID
+            ],
+            ids(FilterMode::SkipNode)
+        );
+        assert_eq!(
+            vec![PARAGRAPH_0_ID, PARAGRAPH_2_ID],
+            ids(FilterMode::PruneSubtree)
+        );
+        assert_eq!(
+            vec![PARAGRAPH_2_ID, PARAGRAPH_0_ID],
+            root.filtered_children(FilterMode::PruneSubtree)
+                .rev()
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        );
+    }
+
+    #[test]
+    fn filtered_parent_in_each_mode() {
+        let tree = test_tree();
+        let reader = tree.read();
+        let button = reader.node_by_id(BUTTON_3_2_ID).unwrap();
+        assert!(!button.is_filtered_out(FilterMode::SkipNode));
+        assert_eq!(
+            Some(ROOT_ID),
+            button
+                .filtered_parent(FilterMode::SkipNode)
+                .map(|node| node.id())
+        );
+        assert!(button.is_filtered_out(FilterMode::PruneSubtree));
+        assert!(button.filtered_parent(FilterMode::PruneSubtree).is_none());
+        let text = reader.node_by_id(STATIC_TEXT_2_0_ID).unwrap();
+        assert!(!text.is_filtered_out(FilterMode::PruneSubtree));
+        assert_eq!(
+            Some(PARAGRAPH_2_ID),
+            text.filtered_parent(FilterMode::PruneSubtree)
+                .map(|node| node.id())
+        );
+    }
+
     #[test]
     fn deepest_first_unignored_child() {
         let tree = test_tree();
@@ -453,6 +1999,40 @@ mod tests {
         assert!(!tree.read().node_by_id(PARAGRAPH_0_ID).unwrap().is_root());
     }
 
+    #[test]
+    fn url() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            name: Some("Home".into()),
+            url: Some("https://example.com/".into()),
+            ..Node::new(LEAF_ID, Role::Link)
+        }));
+        assert_eq!(
+            Some("https://example.com/"),
+            tree.read().node_by_id(LEAF_ID).unwrap().url()
+        );
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::Link)));
+        assert_eq!(None, tree.read().node_by_id(LEAF_ID).unwrap().url());
+    }
+
+    #[test]
+    fn web_content() {
+        let tree = test_tree();
+        let reader = tree.read();
+        let root = reader.node_by_id(ROOT_ID).unwrap();
+        assert_eq!(Role::RootWebArea, root.role());
+        assert!(root.is_document_root());
+        assert!(root.is_web_content());
+        let text = reader.node_by_id(STATIC_TEXT_1_0_ID).unwrap();
+        assert!(!text.is_document_root());
+        assert!(text.is_web_content());
+
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::WebView)));
+        let reader = tree.read();
+        let web_view = reader.node_by_id(LEAF_ID).unwrap();
+        assert!(!web_view.is_document_root());
+        assert!(!web_view.is_web_content());
+    }
+
     #[test]
     fn bounds() {
         let tree = test_tree();
@@ -479,4 +2059,1769 @@ mod tests {
             tree.read().node_by_id(STATIC_TEXT_1_0_ID).unwrap().bounds()
         );
     }
+
+    const SCROLLER_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+    const CONTENT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+
+    fn scaled_and_scrolled_tree() -> std::sync::Arc<crate::tree::Tree> {
+        let bounds = |offset_container, left, top, transform| RelativeBounds {
+            offset_container,
+            rect: Rect {
+                left,
+                top,
+                width: 10.0,
+                height: 10.0,
+            },
+            transform,
+        };
+        let scale_2 = Transform::scale(2.0);
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([SCROLLER_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                Node {
+                    bounds: Some(bounds(None, 10.0, 20.0, Some(Box::new(scale_2)))),
+                    children: Box::new([CONTENT_ID]),
+                    scroll_x: Some(5.0),
+                    scroll_y: Some(30.0),
+                    ..Node::new(SCROLLER_ID, Role::ScrollView)
+                },
+                Node {
+                    bounds: Some(bounds(Some(SCROLLER_ID), 3.0, 4.0, None)),
+                    ..Node::new(CONTENT_ID, Role::GenericContainer)
+                },
+            ])
+            .build();
+        crate::tree::Tree::new(update)
+    }
+
+    #[test]
+    fn local_to_global() {
+        let tree = scaled_and_scrolled_tree();
+        let reader = tree.read();
+        let content = reader.node_by_id(CONTENT_ID).unwrap();
+        // The content is offset by (3, 4) within the scroller, which is
+        // scrolled by (5, 30), offset by (10, 20), and then scaled by 2.
+        assert_eq!((18.0, -10.0), content.local_to_global(1.0, 1.0));
+        assert_eq!(Some((1.0, 1.0)), content.global_to_local(18.0, -10.0));
+        for (x, y) in [(0.0, 0.0), (2.5, -7.0), (100.0, 0.25)] {
+            let (global_x, global_y) = content.local_to_global(x, y);
+            assert_eq!(Some((x, y)), content.global_to_local(global_x, global_y));
+        }
+        assert_eq!(
+            Some(Rect {
+                left: 16.0,
+                top: -12.0,
+                width: 20.0,
+                height: 20.0,
+            }),
+            content.bounds()
+        );
+
+        let root = reader.root();
+        assert_eq!((1.0, 2.0), root.local_to_global(1.0, 2.0));
+        assert_eq!(Some((1.0, 2.0)), root.global_to_local(1.0, 2.0));
+    }
+
+    #[test]
+    fn scale_transform() {
+        let tree = scaled_and_scrolled_tree();
+        let content_bounds = || tree.read().node_by_id(CONTENT_ID).unwrap().bounds();
+        let set_scroller_transform = |transform: Option<Transform>| {
+            let mut scroller = tree.read().node_by_id(SCROLLER_ID).unwrap().data().clone();
+            scroller.bounds.as_mut().unwrap().transform = transform.map(Box::new);
+            tree.update(TreeUpdate {
+                nodes: vec![scroller],
+                ..Default::default()
+            });
+        };
+        set_scroller_transform(None);
+        let unscaled = content_bounds().unwrap();
+        assert_eq!(
+            Rect {
+                left: 8.0,
+                top: -6.0,
+                width: 10.0,
+                height: 10.0,
+            },
+            unscaled
+        );
+        // Scaling the scroller scales everything it contains,
+        // including the offset of the scroller itself.
+        for factor in [0.5, 1.25, 3.0] {
+            set_scroller_transform(Some(Transform::scale(factor)));
+            assert_eq!(Some(unscaled.scale(factor)), content_bounds());
+        }
+    }
+
+    #[test]
+    fn global_to_local_with_singular_transform() {
+        let tree = scaled_and_scrolled_tree();
+        let mut scroller = tree.read().node_by_id(SCROLLER_ID).unwrap().data().clone();
+        scroller.bounds.as_mut().unwrap().transform =
+            Some(Box::new(Transform { matrix: [0.0; 16] }));
+        tree.update(TreeUpdate {
+            nodes: vec![scroller],
+            ..Default::default()
+        });
+        let reader = tree.read();
+        let content = reader.node_by_id(CONTENT_ID).unwrap();
+        assert_eq!(None, content.global_to_local(0.0, 0.0));
+    }
+
+    #[test]
+    fn bounds_cache_invalidation() {
+        let tree = test_tree();
+        let static_text_bounds = || {
+            tree.read()
+                .node_by_id(STATIC_TEXT_1_0_ID)
+                .unwrap()
+                .bounds()
+                .unwrap()
+        };
+        assert_eq!(20.0, static_text_bounds().left);
+        assert_eq!(20.0, static_text_bounds().left);
+        assert_eq!(1, tree.cache_hits());
+
+        let mut paragraph = tree
+            .read()
+            .node_by_id(PARAGRAPH_1_IGNORED_ID)
+            .unwrap()
+            .data()
+            .clone();
+        paragraph.bounds.as_mut().unwrap().rect.left = 30.0;
+        tree.update(TreeUpdate {
+            nodes: vec![paragraph],
+            ..Default::default()
+        });
+        assert_eq!(40.0, static_text_bounds().left);
+        assert_eq!(1, tree.cache_hits());
+    }
+
+    #[test]
+    fn computed_name() {
+        let tree = test_tree();
+        let link_name = || {
+            tree.read()
+                .node_by_id(LINK_3_1_IGNORED_ID)
+                .unwrap()
+                .computed_name()
+        };
+        assert_eq!(Some("static_text_3_1_0".into()), link_name());
+        assert_eq!(
+            Some("button_3_2".into()),
+            tree.read()
+                .node_by_id(BUTTON_3_2_ID)
+                .unwrap()
+                .computed_name()
+        );
+        assert_eq!(
+            None,
+            tree.read()
+                .node_by_id(PARAGRAPH_3_IGNORED_ID)
+                .unwrap()
+                .computed_name()
+        );
+        assert_eq!(0, tree.cache_hits());
+        assert_eq!(Some("static_text_3_1_0".into()), link_name());
+        assert_eq!(1, tree.cache_hits());
+
+        // Changing a node that the link's name wasn't computed from
+        // keeps the cached name.
+        tree.update(TreeUpdate {
+            nodes: vec![Node {
+                name: Some("renamed button".into()),
+                ..Node::new(BUTTON_3_2_ID, Role::Button)
+            }],
+            ..Default::default()
+        });
+        assert_eq!(Some("static_text_3_1_0".into()), link_name());
+        assert_eq!(2, tree.cache_hits());
+
+        tree.update(TreeUpdate {
+            nodes: vec![Node {
+                name: Some("renamed text".into()),
+                ..Node::new(STATIC_TEXT_3_1_0_ID, Role::StaticText)
+            }],
+            ..Default::default()
+        });
+        assert_eq!(Some("renamed text".into()), link_name());
+        assert_eq!(2, tree.cache_hits());
+    }
+
+    const LEAF_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+
+    fn leaf_update(leaf: Node) -> TreeUpdate {
+        TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([LEAF_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .node(leaf)
+            .build()
+    }
+
+    #[test]
+    fn effective_actions_of_slider() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            focusable: true,
+            value_for_range: Some(50.0),
+            ..Node::new(LEAF_ID, Role::Slider)
+        }));
+        assert_eq!(
+            vec![Action::Focus, Action::Decrement, Action::Increment],
+            tree.read().node_by_id(LEAF_ID).unwrap().effective_actions()
+        );
+    }
+
+    #[test]
+    fn adjustable_splitter() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            focusable: true,
+            value_for_range: Some(30.0),
+            ..Node::new(LEAF_ID, Role::Splitter)
+        }));
+        let reader = tree.read();
+        let splitter = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(
+            vec![Action::Focus, Action::Decrement, Action::Increment],
+            splitter.effective_actions()
+        );
+        assert_eq!(Some(Orientation::Horizontal), splitter.orientation());
+
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            orientation: Some(Orientation::Vertical),
+            ..Node::new(LEAF_ID, Role::Splitter)
+        }));
+        let reader = tree.read();
+        let splitter = reader.node_by_id(LEAF_ID).unwrap();
+        assert!(splitter.effective_actions().is_empty());
+        assert_eq!(Some(Orientation::Vertical), splitter.orientation());
+        assert_eq!(None, reader.root().orientation());
+    }
+
+    #[test]
+    fn indeterminate_progress() {
+        for (role, busy, value_for_range, expected) in [
+            (Role::ProgressIndicator, true, None, true),
+            (Role::Meter, true, None, true),
+            (Role::ProgressIndicator, true, Some(50.0), false),
+            (Role::ProgressIndicator, false, None, false),
+            (Role::Slider, true, None, false),
+        ] {
+            let tree = crate::tree::Tree::new(leaf_update(Node {
+                busy,
+                value_for_range,
+                ..Node::new(LEAF_ID, role)
+            }));
+            assert_eq!(
+                role == Role::Meter,
+                tree.read().node_by_id(LEAF_ID).unwrap().is_meter()
+            );
+            assert_eq!(
+                expected,
+                tree.read().node_by_id(LEAF_ID).unwrap().is_indeterminate(),
+                "{:?}, busy: {}, value: {:?}",
+                role,
+                busy,
+                value_for_range
+            );
+        }
+    }
+
+    fn text_box_with_direction(direction: Option<TextDirection>) -> Arc<crate::tree::Tree> {
+        crate::tree::Tree::new(leaf_update(Node {
+            name: Some("abc".into()),
+            bounds: Some(RelativeBounds {
+                offset_container: None,
+                rect: Rect {
+                    left: 100.0,
+                    top: 20.0,
+                    width: 30.0,
+                    height: 10.0,
+                },
+                transform: None,
+            }),
+            text_direction: direction,
+            character_offsets: Box::new([10.0, 25.0, 30.0]),
+            ..Node::new(LEAF_ID, Role::InlineTextBox)
+        }))
+    }
+
+    #[test]
+    fn character_bounds_left_to_right() {
+        let rect = |left, width| Rect {
+            left,
+            top: 20.0,
+            width,
+            height: 10.0,
+        };
+        for direction in [None, Some(TextDirection::LeftToRight)] {
+            let tree = text_box_with_direction(direction);
+            let reader = tree.read();
+            let node = reader.node_by_id(LEAF_ID).unwrap();
+            assert_eq!(Some(rect(100.0, 10.0)), node.character_bounds(0));
+            assert_eq!(Some(rect(110.0, 15.0)), node.character_bounds(1));
+            assert_eq!(Some(rect(125.0, 5.0)), node.character_bounds(2));
+            assert_eq!(None, node.character_bounds(3));
+        }
+    }
+
+    #[test]
+    fn character_bounds_right_to_left() {
+        let tree = text_box_with_direction(Some(TextDirection::RightToLeft));
+        let reader = tree.read();
+        let node = reader.node_by_id(LEAF_ID).unwrap();
+        let rect = |left, width| Rect {
+            left,
+            top: 20.0,
+            width,
+            height: 10.0,
+        };
+        assert_eq!(Some(rect(120.0, 10.0)), node.character_bounds(0));
+        assert_eq!(Some(rect(105.0, 15.0)), node.character_bounds(1));
+        assert_eq!(Some(rect(100.0, 5.0)), node.character_bounds(2));
+        assert_eq!(None, node.character_bounds(3));
+    }
+
+    #[test]
+    fn drag_and_drop() {
+        const ITEM_1_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        const ITEM_2_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        const ITEM_3_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+        let item = |id, grabbed| Node {
+            grabbed,
+            ..Node::new(id, Role::ListItem)
+        };
+        let tree = crate::tree::Tree::new(
+            TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    children: Box::new([ITEM_1_ID, ITEM_2_ID, ITEM_3_ID]),
+                    drop_effects: DropEffect::Move | DropEffect::Copy,
+                    ..Node::new(ROOT_ID, Role::List)
+                })
+                .nodes(vec![
+                    item(ITEM_1_ID, Some(true)),
+                    item(ITEM_2_ID, Some(false)),
+                    item(ITEM_3_ID, None),
+                ])
+                .build(),
+        );
+        let reader = tree.read();
+        let root = reader.root();
+        assert_eq!(
+            vec![DropEffect::Copy, DropEffect::Move],
+            root.drop_effects()
+        );
+        assert!(!root.is_draggable());
+
+        let item_1 = reader.node_by_id(ITEM_1_ID).unwrap();
+        assert!(item_1.is_draggable());
+        assert!(item_1.is_grabbed());
+        assert_eq!(
+            vec![ITEM_1_ID],
+            item_1
+                .grabbed_items()
+                .iter()
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        );
+        assert!(item_1.drop_effects().is_empty());
+
+        let item_2 = reader.node_by_id(ITEM_2_ID).unwrap();
+        assert!(item_2.is_draggable());
+        assert!(!item_2.is_grabbed());
+        assert!(item_2.grabbed_items().is_empty());
+
+        let item_3 = reader.node_by_id(ITEM_3_ID).unwrap();
+        assert!(!item_3.is_draggable());
+        assert!(!item_3.is_grabbed());
+    }
+
+    #[test]
+    fn caret_position() {
+        const FIELD_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+        const CARET_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+        let tree = crate::tree::Tree::new(
+            TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    bounds: Some(RelativeBounds {
+                        offset_container: None,
+                        rect: Rect {
+                            left: 10.0,
+                            top: 20.0,
+                            width: 200.0,
+                            height: 30.0,
+                        },
+                        transform: None,
+                    }),
+                    children: Box::new([CARET_ID]),
+                    ..Node::new(FIELD_ID, Role::TextField)
+                })
+                .node(Node {
+                    bounds: Some(RelativeBounds {
+                        offset_container: Some(FIELD_ID),
+                        rect: Rect {
+                            left: 42.0,
+                            top: 5.0,
+                            width: 1.0,
+                            height: 20.0,
+                        },
+                        transform: None,
+                    }),
+                    ..Node::new(CARET_ID, Role::Caret)
+                })
+                .build(),
+        );
+        let reader = tree.read();
+        let field = reader.node_by_id(FIELD_ID).unwrap();
+        let caret = reader.node_by_id(CARET_ID).unwrap();
+        assert!(field.is_text_field());
+        assert_eq!(Some(CARET_ID), field.caret().map(|node| node.id()));
+        assert_eq!(
+            Some(FIELD_ID),
+            caret.containing_text_field().map(|node| node.id())
+        );
+        assert_eq!(
+            Some(Rect {
+                left: 52.0,
+                top: 25.0,
+                width: 1.0,
+                height: 20.0,
+            }),
+            field.caret_position()
+        );
+        assert_eq!(None, caret.caret_position());
+    }
+
+    #[test]
+    fn text_runs() {
+        const FIELD_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        const BOX_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        const NESTED_BOX_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+        let tree = crate::tree::Tree::new(
+            TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    children: Box::new([LEAF_ID, FIELD_ID]),
+                    ..Node::new(ROOT_ID, Role::RootWebArea)
+                })
+                .nodes(vec![
+                    Node {
+                        children: Box::new([BOX_ID]),
+                        ..Node::new(LEAF_ID, Role::Paragraph)
+                    },
+                    Node {
+                        name: Some("Hello".into()),
+                        ..Node::new(BOX_ID, Role::InlineTextBox)
+                    },
+                    Node {
+                        value: Some("world".into()),
+                        children: Box::new([NESTED_BOX_ID]),
+                        ..Node::new(FIELD_ID, Role::TextField)
+                    },
+                    Node {
+                        name: Some("world".into()),
+                        ..Node::new(NESTED_BOX_ID, Role::InlineTextBox)
+                    },
+                ])
+                .build(),
+        );
+        let reader = tree.read();
+        let ids = |id| {
+            reader
+                .node_by_id(id)
+                .unwrap()
+                .text_runs()
+                .into_iter()
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        };
+        // The nested text field's text isn't part of the document's.
+        assert_eq!(vec![BOX_ID], ids(ROOT_ID));
+        assert_eq!(vec![NESTED_BOX_ID], ids(FIELD_ID));
+        assert_eq!(vec![BOX_ID], ids(BOX_ID));
+        assert_eq!("world", reader.node_by_id(FIELD_ID).unwrap().text());
+    }
+
+    #[test]
+    fn scroll_percent() {
+        let scroller = |scroll_y, scroll_y_min, scroll_y_max| {
+            crate::tree::Tree::new(leaf_update(Node {
+                scroll_y: Some(scroll_y),
+                scroll_y_min,
+                scroll_y_max: Some(scroll_y_max),
+                ..Node::new(LEAF_ID, Role::ScrollView)
+            }))
+        };
+        let percent_y = |tree: Arc<crate::tree::Tree>| {
+            tree.read().node_by_id(LEAF_ID).unwrap().scroll_percent_y()
+        };
+        assert_eq!(None, percent_y(scroller(0.0, Some(0.0), 0.0)));
+        assert_eq!(None, percent_y(scroller(10.0, Some(10.0), 5.0)));
+        assert_eq!(Some(0.0), percent_y(scroller(0.0, None, 200.0)));
+        assert_eq!(Some(25.0), percent_y(scroller(50.0, None, 200.0)));
+        assert_eq!(Some(50.0), percent_y(scroller(150.0, Some(100.0), 200.0)));
+        assert_eq!(Some(100.0), percent_y(scroller(200.0, Some(0.0), 200.0)));
+        // Overscroll is clamped to the range.
+        assert_eq!(Some(100.0), percent_y(scroller(250.0, Some(0.0), 200.0)));
+        assert_eq!(Some(0.0), percent_y(scroller(-20.0, Some(0.0), 200.0)));
+
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::ScrollView)));
+        let reader = tree.read();
+        let node = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(None, node.scroll_percent_x());
+        assert_eq!(None, node.scroll_percent_y());
+    }
+
+    #[test]
+    fn live_region() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            live_status: Some("assertive".into()),
+            live_relevant: Some("removals".into()),
+            container_live_status: Some("polite".into()),
+            container_live_relevant: Some("all".into()),
+            ..Node::new(LEAF_ID, Role::Alert)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(LivePoliteness::Assertive, leaf.live_status());
+        assert_eq!(vec![LiveRelevant::Removals], leaf.live_relevant());
+
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            container_live_status: Some("polite".into()),
+            ..Node::new(LEAF_ID, Role::StaticText)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(LivePoliteness::Polite, leaf.live_status());
+        assert_eq!(
+            vec![LiveRelevant::Additions, LiveRelevant::Text],
+            leaf.live_relevant()
+        );
+
+        assert_eq!(LivePoliteness::Off, reader.root().live_status());
+    }
+
+    #[test]
+    fn key_shortcuts() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            key_shortcuts: Some(" Control+S  Meta+S\tShift+Alt+F12 ".into()),
+            ..Node::new(LEAF_ID, Role::MenuItem)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(
+            vec!["Control+S", "Meta+S", "Shift+Alt+F12"],
+            leaf.key_shortcuts()
+        );
+        assert_eq!(Some("Control+S"), leaf.accelerator());
+        assert!(reader.root().key_shortcuts().is_empty());
+        assert_eq!(None, reader.root().accelerator());
+    }
+
+    #[test]
+    fn abbreviation_expansion() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            name: Some("HTML".into()),
+            tooltip: Some("HyperText Markup Language".into()),
+            description: Some("A markup language".into()),
+            ..Node::new(LEAF_ID, Role::Abbr)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert!(leaf.is_abbreviation());
+        assert_eq!(
+            Some("HyperText Markup Language"),
+            leaf.abbreviation_expansion()
+        );
+
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            description: Some("Cascading Style Sheets".into()),
+            ..Node::new(LEAF_ID, Role::Abbr)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(
+            Some("Cascading Style Sheets"),
+            leaf.abbreviation_expansion()
+        );
+
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            tooltip: Some("Not an abbreviation".into()),
+            ..Node::new(LEAF_ID, Role::Emphasis)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert!(!leaf.is_abbreviation());
+        assert_eq!(None, leaf.abbreviation_expansion());
+    }
+
+    #[test]
+    fn auto_complete() {
+        for value in ["inline", "list", "both"] {
+            let tree = crate::tree::Tree::new(leaf_update(Node {
+                auto_complete: Some(value.into()),
+                ..Node::new(LEAF_ID, Role::SearchBox)
+            }));
+            assert_eq!(
+                Some(value),
+                tree.read().node_by_id(LEAF_ID).unwrap().auto_complete()
+            );
+        }
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::SearchBox)));
+        assert_eq!(
+            None,
+            tree.read().node_by_id(LEAF_ID).unwrap().auto_complete()
+        );
+    }
+
+    #[test]
+    fn window_and_application() {
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::Application)));
+        let reader = tree.read();
+        let root = reader.root();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert!(root.is_window());
+        assert!(!root.is_application());
+        assert!(!leaf.is_window());
+        assert!(leaf.is_application());
+    }
+
+    #[test]
+    fn all_children_including_indirect() {
+        const TABLE_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+        const ROW_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        const CELL_1_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        const CELL_2_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+        const COLUMN_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(6) });
+        const HEADER_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(7) });
+        const MISSING_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(99) });
+        let tree = crate::tree::Tree::new(
+            TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    children: Box::new([TABLE_ID]),
+                    ..Node::new(ROOT_ID, Role::Window)
+                })
+                .nodes(vec![
+                    Node {
+                        children: Box::new([ROW_ID, COLUMN_ID]),
+                        ..Node::new(TABLE_ID, Role::Table)
+                    },
+                    Node {
+                        children: Box::new([CELL_1_ID, CELL_2_ID]),
+                        ..Node::new(ROW_ID, Role::Row)
+                    },
+                    Node::new(CELL_1_ID, Role::Cell),
+                    Node::new(CELL_2_ID, Role::Cell),
+                    Node {
+                        children: Box::new([HEADER_ID]),
+                        indirect_children: Box::new([CELL_2_ID, MISSING_ID, HEADER_ID, CELL_1_ID]),
+                        ..Node::new(COLUMN_ID, Role::Column)
+                    },
+                    Node::new(HEADER_ID, Role::ColumnHeader),
+                ])
+                .build(),
+        );
+        let reader = tree.read();
+        let ids = |id| {
+            reader
+                .node_by_id(id)
+                .unwrap()
+                .all_children_including_indirect()
+                .iter()
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        };
+        assert_eq!(vec![HEADER_ID, CELL_2_ID, CELL_1_ID], ids(COLUMN_ID));
+        assert_eq!(vec![CELL_1_ID, CELL_2_ID], ids(ROW_ID));
+        assert!(ids(CELL_1_ID).is_empty());
+    }
+
+    #[test]
+    fn class_name_and_html_tag() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            class_name: Some("primary-button".into()),
+            html_tag: Some("button".into()),
+            ..Node::new(LEAF_ID, Role::Button)
+        }));
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(Some("primary-button"), leaf.class_name());
+        assert_eq!(Some("button"), leaf.html_tag());
+        assert_eq!(None, reader.root().class_name());
+        assert_eq!(None, reader.root().html_tag());
+    }
+
+    const CAPTION_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+    const CAPTION_TEXT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+    const CAPTIONED_CONTENT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+
+    fn captioned_tree(
+        container: Node,
+        caption_role: Role,
+        caption_text: &str,
+    ) -> std::sync::Arc<crate::tree::Tree> {
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([LEAF_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                Node {
+                    children: Box::new([CAPTION_ID, CAPTIONED_CONTENT_ID]),
+                    ..container
+                },
+                Node {
+                    children: Box::new([CAPTION_TEXT_ID]),
+                    ..Node::new(CAPTION_ID, caption_role)
+                },
+                Node {
+                    name: Some(caption_text.into()),
+                    ..Node::new(CAPTION_TEXT_ID, Role::StaticText)
+                },
+                Node::new(CAPTIONED_CONTENT_ID, Role::Image),
+            ])
+            .build();
+        crate::tree::Tree::new(update)
+    }
+
+    #[test]
+    fn table_caption() {
+        let tree = captioned_tree(
+            Node::new(LEAF_ID, Role::Table),
+            Role::Caption,
+            "Quarterly results",
+        );
+        {
+            let reader = tree.read();
+            let table = reader.node_by_id(LEAF_ID).unwrap();
+            assert_eq!(CAPTION_ID, table.caption().unwrap().id());
+            assert_eq!(Some("Quarterly results".into()), table.computed_name());
+            assert!(reader.root().caption().is_none());
+        }
+
+        // An explicit name takes precedence over the caption.
+        tree.update(TreeUpdate {
+            nodes: vec![Node {
+                name: Some("Results".into()),
+                children: Box::new([CAPTION_ID, CAPTIONED_CONTENT_ID]),
+                ..Node::new(LEAF_ID, Role::Table)
+            }],
+            ..Default::default()
+        });
+        let reader = tree.read();
+        let table = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(Some("Results".into()), table.computed_name());
+    }
+
+    #[test]
+    fn figure_caption() {
+        let tree = captioned_tree(
+            Node::new(LEAF_ID, Role::Figure),
+            Role::FigureCaption,
+            "A lighthouse",
+        );
+        let reader = tree.read();
+        let figure = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(CAPTION_ID, figure.caption().unwrap().id());
+        assert_eq!(Some("A lighthouse".into()), figure.computed_name());
+
+        // A figure caption doesn't caption a table.
+        let tree = captioned_tree(
+            Node::new(LEAF_ID, Role::Table),
+            Role::FigureCaption,
+            "A lighthouse",
+        );
+        let reader = tree.read();
+        let table = reader.node_by_id(LEAF_ID).unwrap();
+        assert!(table.caption().is_none());
+        assert_eq!(None, table.computed_name());
+    }
+
+    #[test]
+    fn fieldset_legend() {
+        let tree = captioned_tree(
+            Node::new(LEAF_ID, Role::Group),
+            Role::Legend,
+            "Shipping address",
+        );
+        let reader = tree.read();
+        let group = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(CAPTION_ID, group.caption().unwrap().id());
+        assert_eq!(Some("Shipping address".into()), group.computed_name());
+
+        // Naming from some other source ignores the legend.
+        let tree = captioned_tree(
+            Node {
+                name_from: Some(NameFrom::Attribute),
+                ..Node::new(LEAF_ID, Role::Group)
+            },
+            Role::Legend,
+            "Shipping address",
+        );
+        let reader = tree.read();
+        let group = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(None, group.computed_name());
+    }
+
+    #[test]
+    fn graphics_document() {
+        for (role, expected) in [
+            (Role::SvgRoot, true),
+            (Role::GraphicsDocument, true),
+            (Role::GraphicsObject, false),
+            (Role::GraphicsSymbol, false),
+            (Role::Image, false),
+        ] {
+            let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, role)));
+            let reader = tree.read();
+            let node = reader.node_by_id(LEAF_ID).unwrap();
+            assert_eq!(expected, node.is_graphics_document(), "{:?}", role);
+        }
+    }
+
+    #[test]
+    fn aria_current() {
+        for (aria_current, expected) in [
+            (None, None),
+            (Some(AriaCurrent::False), None),
+            (Some(AriaCurrent::Page), Some(AriaCurrent::Page)),
+            (Some(AriaCurrent::Step), Some(AriaCurrent::Step)),
+        ] {
+            let tree = crate::tree::Tree::new(leaf_update(Node {
+                aria_current,
+                ..Node::new(LEAF_ID, Role::Link)
+            }));
+            let reader = tree.read();
+            assert_eq!(expected, reader.node_by_id(LEAF_ID).unwrap().aria_current());
+        }
+    }
+
+    #[test]
+    fn effectively_enabled() {
+        const BUTTON_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        let tree = |disabled| {
+            let mut update = leaf_update(Node {
+                children: Box::new([BUTTON_ID]),
+                disabled,
+                ..Node::new(LEAF_ID, Role::Group)
+            });
+            update.nodes.push(Node::new(BUTTON_ID, Role::Button));
+            crate::tree::Tree::new(update)
+        };
+
+        let tree = tree(true);
+        let reader = tree.read();
+        let button = reader.node_by_id(BUTTON_ID).unwrap();
+        assert!(!button.is_disabled());
+        assert!(!button.is_effectively_enabled());
+        assert!(!reader.node_by_id(LEAF_ID).unwrap().is_effectively_enabled());
+        assert!(reader.root().is_effectively_enabled());
+        drop(reader);
+
+        tree.update(TreeUpdate {
+            nodes: vec![Node {
+                children: Box::new([BUTTON_ID]),
+                ..Node::new(LEAF_ID, Role::Group)
+            }],
+            ..Default::default()
+        });
+        let reader = tree.read();
+        assert!(reader
+            .node_by_id(BUTTON_ID)
+            .unwrap()
+            .is_effectively_enabled());
+    }
+
+    #[test]
+    fn resolved_name() {
+        const LABEL_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        const TEXT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        let resolved_name = |leaf: Node| {
+            let update = TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    children: Box::new([LABEL_ID, LEAF_ID]),
+                    ..Node::new(ROOT_ID, Role::Window)
+                })
+                .node(Node {
+                    name: Some("Shipping".into()),
+                    ..Node::new(LABEL_ID, Role::LabelText)
+                })
+                .node(Node {
+                    children: Box::new([TEXT_ID]),
+                    ..leaf
+                })
+                .node(Node {
+                    name: Some("Submit".into()),
+                    ..Node::new(TEXT_ID, Role::StaticText)
+                })
+                .build();
+            let tree = crate::tree::Tree::new(update);
+            let reader = tree.read();
+            reader.node_by_id(LEAF_ID).unwrap().resolved_name()
+        };
+        let named = |name: &str, name_from| Some((name.to_string(), name_from));
+
+        // Labels win over the contents, and over the name itself.
+        assert_eq!(
+            named("Shipping", NameFrom::RelatedElement),
+            resolved_name(Node {
+                labelled_by: Box::new([LABEL_ID]),
+                name: Some("Go".into()),
+                ..Node::new(LEAF_ID, Role::Button)
+            })
+        );
+        assert_eq!(
+            named("Go", NameFrom::Attribute),
+            resolved_name(Node {
+                name: Some("Go".into()),
+                ..Node::new(LEAF_ID, Role::Button)
+            })
+        );
+        assert_eq!(
+            named("Go", NameFrom::Title),
+            resolved_name(Node {
+                name: Some("Go".into()),
+                name_from: Some(NameFrom::Title),
+                ..Node::new(LEAF_ID, Role::Button)
+            })
+        );
+        assert_eq!(
+            named("Send", NameFrom::Value),
+            resolved_name(Node {
+                value: Some("Send".into()),
+                ..Node::new(LEAF_ID, Role::Button)
+            })
+        );
+        assert_eq!(
+            named("Submit", NameFrom::Contents),
+            resolved_name(Node {
+                tooltip: Some("Submits the form".into()),
+                ..Node::new(LEAF_ID, Role::Button)
+            })
+        );
+        // A text field isn't named from its contents.
+        assert_eq!(
+            named("Search the site", NameFrom::Title),
+            resolved_name(Node {
+                tooltip: Some("Search the site".into()),
+                placeholder: Some("Search".into()),
+                ..Node::new(LEAF_ID, Role::TextField)
+            })
+        );
+        assert_eq!(
+            named("Search", NameFrom::Placeholder),
+            resolved_name(Node {
+                placeholder: Some("Search".into()),
+                ..Node::new(LEAF_ID, Role::TextField)
+            })
+        );
+        assert_eq!(None, resolved_name(Node::new(LEAF_ID, Role::TextField)));
+
+        let tree = captioned_tree(Node::new(LEAF_ID, Role::Table), Role::Caption, "Prices");
+        let reader = tree.read();
+        let table = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(named("Prices", NameFrom::Caption), table.resolved_name());
+        let tree = captioned_tree(Node::new(LEAF_ID, Role::Group), Role::Legend, "Address");
+        let reader = tree.read();
+        let group = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(
+            named("Address", NameFrom::RelatedElement),
+            group.resolved_name()
+        );
+    }
+
+    #[test]
+    fn editable_text() {
+        let is_editable_text = |node: Node| {
+            let tree = crate::tree::Tree::new(leaf_update(node));
+            let reader = tree.read();
+            let result = reader.node_by_id(LEAF_ID).unwrap().is_editable_text();
+            result
+        };
+        assert!(is_editable_text(Node {
+            editable: true,
+            nonatomic_text_field_root: true,
+            ..Node::new(LEAF_ID, Role::GenericContainer)
+        }));
+        assert!(!is_editable_text(Node {
+            nonatomic_text_field_root: true,
+            ..Node::new(LEAF_ID, Role::TextField)
+        }));
+        assert!(is_editable_text(Node::new(LEAF_ID, Role::TextField)));
+        assert!(!is_editable_text(Node {
+            read_only: true,
+            ..Node::new(LEAF_ID, Role::TextField)
+        }));
+        assert!(!is_editable_text(Node {
+            editable: true,
+            disabled: true,
+            ..Node::new(LEAF_ID, Role::TextField)
+        }));
+        assert!(!is_editable_text(Node::new(LEAF_ID, Role::StaticText)));
+        assert!(is_editable_text(Node {
+            editable: true,
+            ..Node::new(LEAF_ID, Role::StaticText)
+        }));
+    }
+
+    #[test]
+    fn input_type() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            input_type: Some("email".into()),
+            ..Node::new(LEAF_ID, Role::TextField)
+        }));
+        assert_eq!(
+            Some("email"),
+            tree.read().node_by_id(LEAF_ID).unwrap().input_type()
+        );
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::TextField)));
+        assert_eq!(None, tree.read().node_by_id(LEAF_ID).unwrap().input_type());
+    }
+
+    #[test]
+    fn color_value() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            color_value: Some(0x3366_99FF),
+            ..Node::new(LEAF_ID, Role::ColorWell)
+        }));
+        assert_eq!(
+            Some(Color {
+                red: 0x33,
+                green: 0x66,
+                blue: 0x99,
+                alpha: 0xFF,
+            }),
+            tree.read().node_by_id(LEAF_ID).unwrap().color_value()
+        );
+        let tree = crate::tree::Tree::new(leaf_update(Node::new(LEAF_ID, Role::ColorWell)));
+        assert!(tree
+            .read()
+            .node_by_id(LEAF_ID)
+            .unwrap()
+            .color_value()
+            .is_none());
+    }
+
+    #[test]
+    fn computed_words_match_explicit_words() {
+        const TEXT: &str = "Well,  that's   it... for now!";
+        let words = vec![0..4, 7..13, 16..18, 22..25, 26..29];
+        let explicit = crate::tree::Tree::new(leaf_update(Node {
+            name: Some(TEXT.into()),
+            words: words.clone().into(),
+            ..Node::new(LEAF_ID, Role::InlineTextBox)
+        }));
+        let computed = crate::tree::Tree::new(leaf_update(Node {
+            name: Some(TEXT.into()),
+            ..Node::new(LEAF_ID, Role::InlineTextBox)
+        }));
+        assert_eq!(
+            words,
+            explicit.read().node_by_id(LEAF_ID).unwrap().word_ranges()
+        );
+        assert_eq!(
+            words,
+            computed.read().node_by_id(LEAF_ID).unwrap().word_ranges()
+        );
+
+        // Explicit words take precedence, even if they differ.
+        let explicit = crate::tree::Tree::new(leaf_update(Node {
+            name: Some(TEXT.into()),
+            words: Box::new([0..13, 16..29]),
+            ..Node::new(LEAF_ID, Role::InlineTextBox)
+        }));
+        assert_eq!(
+            vec![0..13, 16..29],
+            explicit.read().node_by_id(LEAF_ID).unwrap().word_ranges()
+        );
+        assert_eq!(
+            vec![0..22, 22..30],
+            computed
+                .read()
+                .node_by_id(LEAF_ID)
+                .unwrap()
+                .sentence_ranges()
+        );
+    }
+
+    #[test]
+    fn effective_actions_of_check_box() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            actions: Action::ShowContextMenu.into(),
+            checked_state: Some(CheckedState::False),
+            focusable: true,
+            ..Node::new(LEAF_ID, Role::CheckBox)
+        }));
+        assert_eq!(
+            vec![Action::Default, Action::Focus, Action::ShowContextMenu],
+            tree.read().node_by_id(LEAF_ID).unwrap().effective_actions()
+        );
+    }
+
+    struct RecordingActionHandler(Arc<Mutex<Vec<ActionRequest>>>);
+
+    impl ActionHandler for RecordingActionHandler {
+        fn do_action(&self, request: ActionRequest) {
+            self.0.lock().unwrap().push(request);
+        }
+    }
+
+    fn range_step_requests(slider: Node) -> Vec<ActionRequest> {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let tree = crate::tree::Tree::with_action_handler(
+            leaf_update(slider),
+            Box::new(RecordingActionHandler(requests.clone())),
+        );
+        let reader = tree.read();
+        let slider = reader.node_by_id(LEAF_ID).unwrap();
+        slider.increment(RangeStep::Small);
+        slider.decrement(RangeStep::Small);
+        slider.increment(RangeStep::Large);
+        slider.decrement(RangeStep::Large);
+        let requests = requests.lock().unwrap().clone();
+        requests
+    }
+
+    fn leaf_request(action: Action, data: Option<ActionData>) -> ActionRequest {
+        ActionRequest {
+            action,
+            target: LEAF_ID,
+            data,
+        }
+    }
+
+    #[test]
+    fn do_default_action() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let tree = crate::tree::Tree::with_action_handler(
+            leaf_update(Node::new(LEAF_ID, Role::Button)),
+            Box::new(RecordingActionHandler(requests.clone())),
+        );
+        tree.read().node_by_id(LEAF_ID).unwrap().do_default_action();
+        assert_eq!(
+            vec![leaf_request(Action::Default, None)],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn flows_to() {
+        const NEXT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        const DANGLING_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(99) });
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([LEAF_ID, NEXT_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .node(Node {
+                flow_to: Box::new([DANGLING_ID, NEXT_ID, ROOT_ID]),
+                controls: Box::new([DANGLING_ID]),
+                ..Node::new(LEAF_ID, Role::Paragraph)
+            })
+            .node(Node::new(NEXT_ID, Role::Paragraph))
+            .build();
+        let tree = crate::tree::Tree::new(update);
+        let reader = tree.read();
+        let leaf = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(
+            vec![NEXT_ID, ROOT_ID],
+            leaf.flows_to().map(|node| node.id()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(NEXT_ID), leaf.flows_to().next().map(|node| node.id()));
+        assert_eq!(
+            Some(ROOT_ID),
+            leaf.flows_to().next_back().map(|node| node.id())
+        );
+        assert_eq!(0, leaf.controls().count());
+        assert_eq!(0, reader.root().flows_to().count());
+    }
+
+    #[test]
+    fn tooltip() {
+        const TOOLTIP_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let tree = crate::tree::Tree::with_action_handler(
+            TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    children: Box::new([LEAF_ID, TOOLTIP_ID]),
+                    ..Node::new(ROOT_ID, Role::Window)
+                })
+                .nodes(vec![
+                    Node {
+                        described_by: Box::new([ROOT_ID, TOOLTIP_ID]),
+                        ..Node::new(LEAF_ID, Role::Button)
+                    },
+                    Node {
+                        invisible: true,
+                        ..Node::new(TOOLTIP_ID, Role::Tooltip)
+                    },
+                ])
+                .build(),
+            Box::new(RecordingActionHandler(requests.clone())),
+        );
+        let reader = tree.read();
+        let button = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(
+            Some(TOOLTIP_ID),
+            button.tooltip_node().map(|node| node.id())
+        );
+        assert!(reader.root().tooltip_node().is_none());
+        button.show_tooltip();
+        button.hide_tooltip();
+        assert_eq!(
+            vec![
+                leaf_request(Action::ShowTooltip, None),
+                leaf_request(Action::HideTooltip, None),
+            ],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn text_selection_and_scrolling_requests() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let tree = crate::tree::Tree::with_action_handler(
+            leaf_update(Node {
+                value: Some("Hello".into()),
+                ..Node::new(LEAF_ID, Role::TextField)
+            }),
+            Box::new(RecordingActionHandler(requests.clone())),
+        );
+        let selection = TextSelection {
+            anchor_node: LEAF_ID,
+            anchor_offset: 1,
+            focus_node: LEAF_ID,
+            focus_offset: 4,
+        };
+        let reader = tree.read();
+        let field = reader.node_by_id(LEAF_ID).unwrap();
+        field.set_text_selection(selection);
+        field.scroll_into_view();
+        assert_eq!(
+            vec![
+                leaf_request(
+                    Action::SetTextSelection,
+                    Some(ActionData::SetTextSelection(selection))
+                ),
+                leaf_request(Action::ScrollIntoView, None),
+            ],
+            *requests.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn replace_selected_text() {
+        let text_field = |value: &str, anchor_offset, focus_offset| Node {
+            editable: true,
+            value: Some(value.into()),
+            text_selection: Some(TextSelection {
+                anchor_node: LEAF_ID,
+                anchor_offset,
+                focus_node: LEAF_ID,
+                focus_offset,
+            }),
+            ..Node::new(LEAF_ID, Role::TextField)
+        };
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let tree = crate::tree::Tree::with_action_handler(
+            leaf_update(text_field("Hello world", 6, 11)),
+            Box::new(RecordingActionHandler(requests.clone())),
+        );
+        tree.read()
+            .node_by_id(LEAF_ID)
+            .unwrap()
+            .replace_selected_text("there");
+        assert_eq!(
+            vec![leaf_request(
+                Action::ReplaceSelectedText,
+                Some(ActionData::Value("there".into()))
+            )],
+            *requests.lock().unwrap()
+        );
+
+        // The application confirms the edit, leaving a collapsed selection.
+        tree.update(TreeUpdate {
+            nodes: vec![text_field("Hello there", 11, 11)],
+            ..Default::default()
+        });
+        let reader = tree.read();
+        let node = reader.node_by_id(LEAF_ID).unwrap();
+        assert_eq!(Some("Hello there"), node.data().value.as_deref());
+        assert!(node.data().text_selection.unwrap().is_collapsed());
+
+        // With a collapsed selection, there's nothing to replace, so empty
+        // text is ignored and other text is inserted at the caret.
+        node.replace_selected_text("");
+        node.replace_selected_text("!");
+        drop(reader);
+        assert_eq!(
+            vec![leaf_request(
+                Action::ReplaceSelectedText,
+                Some(ActionData::Value("!".into()))
+            )],
+            requests.lock().unwrap()[1..]
+        );
+
+        // Replacing a selection with empty text deletes it.
+        tree.update(TreeUpdate {
+            nodes: vec![text_field("Hello there!", 5, 11)],
+            ..Default::default()
+        });
+        tree.read()
+            .node_by_id(LEAF_ID)
+            .unwrap()
+            .replace_selected_text("");
+        assert_eq!(
+            leaf_request(
+                Action::ReplaceSelectedText,
+                Some(ActionData::Value("".into()))
+            ),
+            requests.lock().unwrap()[2]
+        );
+
+        // A read-only field can't be edited.
+        tree.update(TreeUpdate {
+            nodes: vec![Node {
+                read_only: true,
+                ..text_field("Hello!", 0, 5)
+            }],
+            ..Default::default()
+        });
+        tree.read()
+            .node_by_id(LEAF_ID)
+            .unwrap()
+            .replace_selected_text("Goodbye");
+        assert_eq!(3, requests.lock().unwrap().len());
+    }
+
+    #[test]
+    fn range_steps() {
+        let slider = Node {
+            value_for_range: Some(50.0),
+            min_value_for_range: Some(0.0),
+            max_value_for_range: Some(55.0),
+            step_value_for_range: Some(1.0),
+            large_step_value_for_range: Some(20.0),
+            ..Node::new(LEAF_ID, Role::Slider)
+        };
+        assert_eq!(
+            vec![
+                leaf_request(Action::Increment, None),
+                leaf_request(Action::Decrement, None),
+                leaf_request(Action::SetValue, Some(ActionData::NumericValue(55.0))),
+                leaf_request(Action::SetValue, Some(ActionData::NumericValue(30.0))),
+            ],
+            range_step_requests(slider)
+        );
+    }
+
+    #[test]
+    fn default_large_range_step() {
+        let slider = Node {
+            value_for_range: Some(50.0),
+            step_value_for_range: Some(2.0),
+            ..Node::new(LEAF_ID, Role::Slider)
+        };
+        let tree = crate::tree::Tree::new(leaf_update(slider.clone()));
+        assert_eq!(
+            Some(20.0),
+            tree.read()
+                .node_by_id(LEAF_ID)
+                .unwrap()
+                .large_step_value_for_range()
+        );
+        assert_eq!(
+            vec![
+                leaf_request(Action::Increment, None),
+                leaf_request(Action::Decrement, None),
+                leaf_request(Action::SetValue, Some(ActionData::NumericValue(70.0))),
+                leaf_request(Action::SetValue, Some(ActionData::NumericValue(30.0))),
+            ],
+            range_step_requests(slider)
+        );
+    }
+
+    #[test]
+    fn large_range_step_without_step_size() {
+        let slider = Node {
+            value_for_range: Some(50.0),
+            ..Node::new(LEAF_ID, Role::Slider)
+        };
+        assert_eq!(
+            vec![
+                leaf_request(Action::Increment, None),
+                leaf_request(Action::Decrement, None),
+                leaf_request(Action::Increment, None),
+                leaf_request(Action::Decrement, None),
+            ],
+            range_step_requests(slider)
+        );
+    }
+
+    #[test]
+    fn effective_actions_of_static_text() {
+        let tree = crate::tree::Tree::new(leaf_update(Node {
+            name: Some("static text".into()),
+            ..Node::new(LEAF_ID, Role::StaticText)
+        }));
+        assert!(tree
+            .read()
+            .node_by_id(LEAF_ID)
+            .unwrap()
+            .effective_actions()
+            .is_empty());
+    }
+
+    const BUTTON_1_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+    const STATIC_TEXT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+    const GROUP_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+    const BUTTON_2_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(6) });
+    const DISABLED_BUTTON_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(7) });
+    const BUTTON_3_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(8) });
+
+    fn focus_order_tree(button_1: Node, button_3: Node) -> std::sync::Arc<crate::tree::Tree> {
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([BUTTON_1_ID, STATIC_TEXT_ID, GROUP_ID, BUTTON_3_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                button_1,
+                Node::new(STATIC_TEXT_ID, Role::StaticText),
+                Node {
+                    children: Box::new([BUTTON_2_ID, DISABLED_BUTTON_ID]),
+                    ..Node::new(GROUP_ID, Role::Group)
+                },
+                Node {
+                    focusable: true,
+                    ..Node::new(BUTTON_2_ID, Role::Button)
+                },
+                Node {
+                    focusable: true,
+                    disabled: true,
+                    ..Node::new(DISABLED_BUTTON_ID, Role::Button)
+                },
+                button_3,
+            ])
+            .build();
+        crate::tree::Tree::new(update)
+    }
+
+    fn focusable_button(id: NodeId) -> Node {
+        Node {
+            focusable: true,
+            ..Node::new(id, Role::Button)
+        }
+    }
+
+    #[test]
+    fn implicit_focus_order() {
+        let tree = focus_order_tree(focusable_button(BUTTON_1_ID), focusable_button(BUTTON_3_ID));
+        let reader = tree.read();
+        let next = |id| {
+            reader
+                .node_by_id(id)
+                .unwrap()
+                .next_in_focus_order()
+                .map(|node| node.id())
+        };
+        let previous = |id| {
+            reader
+                .node_by_id(id)
+                .unwrap()
+                .previous_in_focus_order()
+                .map(|node| node.id())
+        };
+        assert_eq!(Some(BUTTON_1_ID), next(ROOT_ID));
+        assert_eq!(Some(BUTTON_2_ID), next(BUTTON_1_ID));
+        assert_eq!(Some(BUTTON_2_ID), next(STATIC_TEXT_ID));
+        assert_eq!(Some(BUTTON_3_ID), next(BUTTON_2_ID));
+        assert_eq!(None, next(BUTTON_3_ID));
+        assert_eq!(Some(BUTTON_2_ID), previous(BUTTON_3_ID));
+        assert_eq!(Some(BUTTON_1_ID), previous(BUTTON_2_ID));
+        assert_eq!(Some(BUTTON_1_ID), previous(STATIC_TEXT_ID));
+        assert_eq!(None, previous(BUTTON_1_ID));
+    }
+
+    #[test]
+    fn focusable_nodes() {
+        let ids = |tree: std::sync::Arc<crate::tree::Tree>| {
+            tree.read()
+                .focusable_nodes()
+                .iter()
+                .map(|node| node.id())
+                .collect::<Vec<NodeId>>()
+        };
+        let tree = focus_order_tree(focusable_button(BUTTON_1_ID), focusable_button(BUTTON_3_ID));
+        assert_eq!(vec![BUTTON_1_ID, BUTTON_2_ID, BUTTON_3_ID], ids(tree));
+
+        let tree = focus_order_tree(
+            Node {
+                next_focus: Some(BUTTON_3_ID),
+                ..focusable_button(BUTTON_1_ID)
+            },
+            focusable_button(BUTTON_3_ID),
+        );
+        assert_eq!(vec![BUTTON_1_ID, BUTTON_3_ID], ids(tree));
+
+        // An explicit cycle is only followed once.
+        let tree = focus_order_tree(
+            Node {
+                next_focus: Some(BUTTON_3_ID),
+                ..focusable_button(BUTTON_1_ID)
+            },
+            Node {
+                next_focus: Some(BUTTON_1_ID),
+                ..focusable_button(BUTTON_3_ID)
+            },
+        );
+        assert_eq!(vec![BUTTON_1_ID, BUTTON_3_ID], ids(tree));
+
+        let tree = focus_order_tree(
+            Node::new(BUTTON_1_ID, Role::Button),
+            Node::new(BUTTON_3_ID, Role::Button),
+        );
+        assert_eq!(vec![BUTTON_2_ID], ids(tree));
+    }
+
+    #[test]
+    fn explicit_focus_order() {
+        let tree = focus_order_tree(
+            Node {
+                next_focus: Some(BUTTON_3_ID),
+                ..focusable_button(BUTTON_1_ID)
+            },
+            Node {
+                previous_focus: Some(BUTTON_1_ID),
+                ..focusable_button(BUTTON_3_ID)
+            },
+        );
+        let reader = tree.read();
+        assert_eq!(
+            BUTTON_3_ID,
+            reader
+                .node_by_id(BUTTON_1_ID)
+                .unwrap()
+                .next_in_focus_order()
+                .unwrap()
+                .id()
+        );
+        assert_eq!(
+            BUTTON_1_ID,
+            reader
+                .node_by_id(BUTTON_3_ID)
+                .unwrap()
+                .previous_in_focus_order()
+                .unwrap()
+                .id()
+        );
+        // Nodes without explicit overrides still use document order.
+        assert_eq!(
+            BUTTON_3_ID,
+            reader
+                .node_by_id(BUTTON_2_ID)
+                .unwrap()
+                .next_in_focus_order()
+                .unwrap()
+                .id()
+        );
+    }
+
+    const TABLE_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+    const ROW_GROUP_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+    const ROW_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+    const CELL_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+    const ORPHAN_CELL_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(6) });
+
+    #[test]
+    fn containing_table() {
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([TABLE_ID, ORPHAN_CELL_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                Node {
+                    children: Box::new([ROW_GROUP_ID]),
+                    ..Node::new(TABLE_ID, Role::Table)
+                },
+                Node {
+                    children: Box::new([ROW_ID]),
+                    ..Node::new(ROW_GROUP_ID, Role::RowGroup)
+                },
+                Node {
+                    children: Box::new([CELL_ID]),
+                    ..Node::new(ROW_ID, Role::Row)
+                },
+                Node::new(CELL_ID, Role::Cell),
+                Node::new(ORPHAN_CELL_ID, Role::Cell),
+            ])
+            .build();
+        let tree = crate::tree::Tree::new(update);
+        let reader = tree.read();
+        assert_eq!(
+            TABLE_ID,
+            reader
+                .node_by_id(CELL_ID)
+                .unwrap()
+                .containing_table()
+                .unwrap()
+                .id()
+        );
+        assert!(reader
+            .node_by_id(ORPHAN_CELL_ID)
+            .unwrap()
+            .containing_table()
+            .is_none());
+    }
+
+    #[test]
+    fn table_cells_with_row_span() {
+        // A 3x2 table whose first cell spans the first two rows:
+        //
+        //   | 0,0 | 0,1 |
+        //   |     | 1,1 |
+        //   | 2,0 |     |
+        let cell_id = |i: u64| NodeId(NonZeroU64::new(i + 10).unwrap());
+        let cell = |i, row, column, row_span| Node {
+            table_cell_row_index: Some(row),
+            table_cell_column_index: Some(column),
+            table_cell_row_span: row_span,
+            ..Node::new(cell_id(i), Role::Cell)
+        };
+        let row = |id, children: &[NodeId]| Node {
+            children: children.into(),
+            ..Node::new(id, Role::Row)
+        };
+        const ROW_1_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+        const ROW_2_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(6) });
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([TABLE_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                Node {
+                    children: Box::new([ROW_ID, ROW_1_ID, ROW_2_ID]),
+                    table_row_count: Some(3),
+                    ..Node::new(TABLE_ID, Role::Table)
+                },
+                row(ROW_ID, &[cell_id(0), cell_id(1)]),
+                row(ROW_1_ID, &[cell_id(2)]),
+                row(ROW_2_ID, &[cell_id(3)]),
+                cell(0, 0, 0, Some(2)),
+                cell(1, 0, 1, None),
+                cell(2, 1, 1, None),
+                cell(3, 2, 0, None),
+            ])
+            .build();
+        let tree = crate::tree::Tree::new(update);
+        let reader = tree.read();
+        let grid = reader.node_by_id(TABLE_ID).unwrap().table_cells();
+        let ids: Vec<Vec<Option<NodeId>>> = grid
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.map(|cell| cell.id())).collect())
+            .collect();
+        assert_eq!(
+            vec![
+                vec![Some(cell_id(0)), Some(cell_id(1))],
+                vec![Some(cell_id(0)), Some(cell_id(2))],
+                vec![Some(cell_id(3)), None],
+            ],
+            ids
+        );
+    }
+
+    const SECTION_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+    const PARAGRAPH_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+    const TEXT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+
+    #[test]
+    fn inherited_text_attributes() {
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([SECTION_ID]),
+                language: Some("fr".into()),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                Node {
+                    children: Box::new([PARAGRAPH_ID]),
+                    font_family: Some("Georgia".into()),
+                    text_direction: Some(TextDirection::RightToLeft),
+                    ..Node::new(SECTION_ID, Role::Section)
+                },
+                Node {
+                    children: Box::new([TEXT_ID]),
+                    ..Node::new(PARAGRAPH_ID, Role::Paragraph)
+                },
+                Node {
+                    language: Some("de".into()),
+                    ..Node::new(TEXT_ID, Role::StaticText)
+                },
+            ])
+            .build();
+        let tree = crate::tree::Tree::new(update);
+        let reader = tree.read();
+        let paragraph = reader.node_by_id(PARAGRAPH_ID).unwrap();
+        assert_eq!(Some("fr"), paragraph.effective_language());
+        assert_eq!(Some("Georgia"), paragraph.effective_font_family());
+        let text = reader.node_by_id(TEXT_ID).unwrap();
+        assert_eq!(Some("de"), text.effective_language());
+        assert_eq!(Some("Georgia"), text.effective_font_family());
+        let root = reader.root();
+        assert_eq!(Some("fr"), root.effective_language());
+        assert_eq!(None, root.effective_font_family());
+        assert_eq!(TextDirection::LeftToRight, root.effective_text_direction());
+        assert_eq!(
+            TextDirection::RightToLeft,
+            reader
+                .node_by_id(SECTION_ID)
+                .unwrap()
+                .effective_text_direction()
+        );
+        assert_eq!(TextDirection::RightToLeft, text.effective_text_direction());
+    }
+
+    const TREE_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+    const FIRST_ITEM_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+    const SEPARATOR_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+    const SECOND_ITEM_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+
+    #[test]
+    fn position_in_set() {
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([TREE_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            })
+            .nodes(vec![
+                Node {
+                    children: Box::new([FIRST_ITEM_ID, SEPARATOR_ID, SECOND_ITEM_ID]),
+                    ..Node::new(TREE_ID, Role::Tree)
+                },
+                Node::new(FIRST_ITEM_ID, Role::TreeItem),
+                Node::new(SEPARATOR_ID, Role::Splitter),
+                Node {
+                    pos_in_set: Some(7),
+                    set_size: Some(9),
+                    ..Node::new(SECOND_ITEM_ID, Role::TreeItem)
+                },
+            ])
+            .build();
+        let tree = crate::tree::Tree::new(update);
+        let reader = tree.read();
+        let first_item = reader.node_by_id(FIRST_ITEM_ID).unwrap();
+        assert_eq!(Some(1), first_item.position_in_set());
+        assert_eq!(Some(2), first_item.size_of_set());
+        let second_item = reader.node_by_id(SECOND_ITEM_ID).unwrap();
+        assert_eq!(Some(7), second_item.position_in_set());
+        assert_eq!(Some(9), second_item.size_of_set());
+        let separator = reader.node_by_id(SEPARATOR_ID).unwrap();
+        assert_eq!(None, separator.position_in_set());
+        assert_eq!(None, separator.size_of_set());
+    }
 }
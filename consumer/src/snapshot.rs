@@ -0,0 +1,34 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use serde_json::Value;
+
+use crate::Node;
+
+// A node's attributes are serialized with the schema's serde
+// implementations. `serde_json::Map` keeps its keys sorted, so the output
+// doesn't depend on the order in which fields are declared. The children
+// are nested in place of their IDs.
+fn node_value(node: Node) -> Value {
+    let mut value = serde_json::to_value(node.data()).expect("node data can always be serialized");
+    let members = value
+        .as_object_mut()
+        .expect("node data serializes as a struct");
+    members.remove("children");
+    let children: Vec<Value> = node.children().map(node_value).collect();
+    if !children.is_empty() {
+        members.insert("children".into(), Value::Array(children));
+    }
+    value
+}
+
+/// Returns the pretty-printed JSON for the subtree rooted at `node`;
+/// see [`crate::TreeReader::to_snapshot_json`].
+pub(crate) fn to_json(node: Node) -> String {
+    let mut out = serde_json::to_string_pretty(&node_value(node))
+        .expect("a JSON value can always be serialized");
+    out.push('\n');
+    out
+}
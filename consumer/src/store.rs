@@ -0,0 +1,137 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use std::collections::HashMap;
+
+use accesskit_schema::NodeId;
+
+use crate::tree::{EventSink, Reader as TreeReader};
+use crate::Node;
+
+/// Opaque per-node state, such as a platform wrapper object, that a
+/// platform adapter keeps for as long as the node exists. A [`Node`] is
+/// only a view that's valid while a [`TreeReader`] is held, so state that
+/// must outlive an update, e.g. to keep an object's identity stable,
+/// is stored here by ID instead.
+///
+/// The store doesn't observe the tree by itself. Pass it to
+/// [`Tree::update_with_sink`](crate::Tree::update_with_sink), or call
+/// [`NodeStateStore::remove`] from another [`EventSink`] when a node
+/// is removed, so that the state of removed nodes is dropped.
+pub struct NodeStateStore<T> {
+    entries: HashMap<NodeId, T>,
+}
+
+impl<T> NodeStateStore<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&T> {
+        self.entries.get(&id)
+    }
+
+    /// Returns the state for the given node, first creating it with `f`
+    /// if the node doesn't have any yet.
+    pub fn get_or_insert_with(&mut self, id: NodeId, f: impl FnOnce() -> T) -> &mut T {
+        self.entries.entry(id).or_insert_with(f)
+    }
+
+    /// Removes and returns the state for the given node, if any.
+    pub fn remove(&mut self, id: NodeId) -> Option<T> {
+        self.entries.remove(&id)
+    }
+
+    /// Drops the state of every node that isn't in the tree, e.g. after
+    /// updates that were applied without passing this store as the sink.
+    pub fn retain_existing(&mut self, reader: &TreeReader) {
+        self.entries
+            .retain(|id, _| reader.node_by_id(*id).is_some());
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<T> Default for NodeStateStore<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> EventSink for NodeStateStore<T> {
+    fn on_node_removed(&mut self, node: Node) {
+        self.remove(node.id());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use accesskit_schema::{Node, NodeId, Role, StringEncoding, Tree, TreeId, TreeUpdate};
+    use std::num::NonZeroU64;
+
+    use super::NodeStateStore;
+
+    const ROOT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+    const BUTTON_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+
+    fn update(root_children: &[NodeId], button_name: &str) -> TreeUpdate {
+        let mut nodes = vec![Node {
+            children: root_children.into(),
+            ..Node::new(ROOT_ID, Role::Window)
+        }];
+        if !root_children.is_empty() {
+            nodes.push(Node {
+                name: Some(button_name.into()),
+                ..Node::new(BUTTON_ID, Role::Button)
+            });
+        }
+        TreeUpdate {
+            nodes,
+            tree: Some(Tree::new(
+                TreeId("test_tree".into()),
+                ROOT_ID,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn state_lives_as_long_as_node() {
+        let tree = crate::tree::Tree::new(update(&[BUTTON_ID], "OK"));
+        let mut store = NodeStateStore::new();
+        *store.get_or_insert_with(BUTTON_ID, || 0) += 1;
+        store.get_or_insert_with(ROOT_ID, || 10);
+
+        tree.update_with_sink(update(&[BUTTON_ID], "Cancel"), &mut store);
+        assert_eq!(Some(&1), store.get(BUTTON_ID));
+        *store.get_or_insert_with(BUTTON_ID, || 0) += 1;
+        assert_eq!(Some(&2), store.get(BUTTON_ID));
+
+        tree.update_with_sink(update(&[], ""), &mut store);
+        assert_eq!(None, store.get(BUTTON_ID));
+        assert_eq!(Some(&10), store.get(ROOT_ID));
+        assert_eq!(1, store.len());
+    }
+
+    #[test]
+    fn retain_existing_drops_removed_nodes() {
+        let tree = crate::tree::Tree::new(update(&[BUTTON_ID], "OK"));
+        let mut store = NodeStateStore::new();
+        store.get_or_insert_with(BUTTON_ID, || "button");
+        tree.update(update(&[], ""));
+        assert_eq!(Some(&"button"), store.get(BUTTON_ID));
+        store.retain_existing(&tree.read());
+        assert!(store.is_empty());
+    }
+}
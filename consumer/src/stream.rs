@@ -0,0 +1,288 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+//! An adapter that delivers the changes from each tree update
+//! to an async task, without depending on any particular runtime.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use accesskit_schema::TreeUpdate;
+use parking_lot::Mutex;
+
+use crate::tree::{ChangeRecord, Tree, UpdateStatus};
+
+struct Channel {
+    batches: VecDeque<Vec<ChangeRecord>>,
+    capacity: usize,
+    dropped_batches: usize,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// Creates a sender that applies tree updates, and a stream that
+/// receives the changes from each update as a batch.
+///
+/// The channel holds at most `capacity` batches. Sending never blocks
+/// or waits for the stream; if the channel is full, the oldest batch
+/// is dropped to make room for the new one, and the stream can find
+/// out how many were lost with [`ChangeStream::dropped_batches`].
+/// A consumer that misses a batch should usually re-read the
+/// affected parts of the tree, or the whole tree.
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+pub fn change_channel(capacity: usize) -> (ChangeSender, ChangeStream) {
+    assert!(capacity > 0);
+    let channel = Arc::new(Mutex::new(Channel {
+        batches: VecDeque::with_capacity(capacity),
+        capacity,
+        dropped_batches: 0,
+        closed: false,
+        waker: None,
+    }));
+    (
+        ChangeSender {
+            channel: channel.clone(),
+        },
+        ChangeStream { channel },
+    )
+}
+
+/// The sending half of a [`change_channel`]. When it's dropped,
+/// the stream ends once it has yielded the remaining batches.
+pub struct ChangeSender {
+    channel: Arc<Mutex<Channel>>,
+}
+
+impl ChangeSender {
+    /// Applies the update as with [`Tree::update_and_collect_changes`],
+    /// and sends the resulting changes to the stream. Nothing is sent
+    /// if there were no changes, e.g. because the update was stale.
+    pub fn update(&self, tree: &Arc<Tree>, update: TreeUpdate) -> UpdateStatus {
+        let mut changes = Vec::new();
+        let status = tree.update_and_process_changes(update, |change| {
+            changes.push(ChangeRecord::from(&change));
+        });
+        if !changes.is_empty() {
+            self.send(changes);
+        }
+        status
+    }
+
+    fn send(&self, changes: Vec<ChangeRecord>) {
+        let waker = {
+            let mut channel = self.channel.lock();
+            if channel.batches.len() == channel.capacity {
+                channel.batches.pop_front();
+                channel.dropped_batches += 1;
+            }
+            channel.batches.push_back(changes);
+            channel.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for ChangeSender {
+    fn drop(&mut self) {
+        let waker = {
+            let mut channel = self.channel.lock();
+            channel.closed = true;
+            channel.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// The receiving half of a [`change_channel`]. Its
+/// [`poll_next`](ChangeStream::poll_next) method has the same signature
+/// as that of the `Stream` trait in the `futures` crate, so it's easy to
+/// wrap in that trait, or it can be awaited directly with
+/// [`next_batch`](ChangeStream::next_batch).
+pub struct ChangeStream {
+    channel: Arc<Mutex<Channel>>,
+}
+
+impl ChangeStream {
+    /// Returns the next batch of changes, or `None` if the sender
+    /// has been dropped and there are no more batches.
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Vec<ChangeRecord>>> {
+        let mut channel = self.channel.lock();
+        if let Some(batch) = channel.batches.pop_front() {
+            Poll::Ready(Some(batch))
+        } else if channel.closed {
+            Poll::Ready(None)
+        } else {
+            channel.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// Waits for the next batch of changes, as with
+    /// [`poll_next`](ChangeStream::poll_next).
+    pub fn next_batch(&mut self) -> NextBatch<'_> {
+        NextBatch { stream: self }
+    }
+
+    /// Returns the number of batches that were dropped because the channel
+    /// was full since the last call, and resets the count.
+    pub fn dropped_batches(&self) -> usize {
+        std::mem::take(&mut self.channel.lock().dropped_batches)
+    }
+}
+
+/// The future returned by [`ChangeStream::next_batch`].
+pub struct NextBatch<'a> {
+    stream: &'a mut ChangeStream,
+}
+
+impl Future for NextBatch<'_> {
+    type Output = Option<Vec<ChangeRecord>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use accesskit_schema::{Node, NodeId, Role, StringEncoding, Tree, TreeId, TreeUpdate};
+    use std::future::Future;
+    use std::num::NonZeroU64;
+    use std::pin::Pin;
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread;
+
+    use super::change_channel;
+    use crate::tree::ChangeRecord;
+
+    const ROOT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+    const BUTTON_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+
+    // A minimal executor, so the tests don't need an async runtime.
+    struct ThreadWaker {
+        woken: Mutex<bool>,
+        cv: Condvar,
+    }
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            *self.woken.lock().unwrap() = true;
+            self.cv.notify_one();
+        }
+    }
+
+    fn block_on<F: Future>(mut future: F) -> F::Output {
+        let thread_waker = Arc::new(ThreadWaker {
+            woken: Mutex::new(false),
+            cv: Condvar::new(),
+        });
+        let waker = Waker::from(thread_waker.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut future = unsafe { Pin::new_unchecked(&mut future) };
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            let mut woken = thread_waker.woken.lock().unwrap();
+            while !*woken {
+                woken = thread_waker.cv.wait(woken).unwrap();
+            }
+            *woken = false;
+        }
+    }
+
+    fn button(name: &str) -> TreeUpdate {
+        TreeUpdate {
+            nodes: vec![Node {
+                name: Some(name.into()),
+                ..Node::new(BUTTON_ID, Role::Button)
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn test_tree() -> Arc<crate::tree::Tree> {
+        crate::tree::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([BUTTON_ID]),
+                    ..Node::new(ROOT_ID, Role::Window)
+                },
+                Node::new(BUTTON_ID, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId("test_tree".into()),
+                ROOT_ID,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn batches_from_another_thread() {
+        let tree = test_tree();
+        let (sender, mut stream) = change_channel(8);
+        let producer = {
+            let tree = tree.clone();
+            thread::spawn(move || {
+                sender.update(&tree, button("First"));
+                // An update that changes nothing sends no batch.
+                sender.update(&tree, button("First"));
+                sender.update(&tree, button("Second"));
+            })
+        };
+        let batches = block_on(async {
+            let mut batches = Vec::new();
+            while let Some(batch) = stream.next_batch().await {
+                batches.push(batch);
+            }
+            batches
+        });
+        producer.join().unwrap();
+        assert_eq!(
+            vec![
+                vec![ChangeRecord::NodeUpdated(BUTTON_ID)],
+                vec![ChangeRecord::NodeUpdated(BUTTON_ID)]
+            ],
+            batches
+        );
+        assert_eq!(0, stream.dropped_batches());
+    }
+
+    #[test]
+    fn full_channel_drops_oldest_batch() {
+        let tree = test_tree();
+        let (sender, mut stream) = change_channel(1);
+        sender.update(&tree, button("First"));
+        sender.update(&tree, button("Second"));
+        drop(sender);
+        assert_eq!(1, stream.dropped_batches());
+        assert_eq!(0, stream.dropped_batches());
+        assert_eq!(
+            Some(vec![ChangeRecord::NodeUpdated(BUTTON_ID)]),
+            block_on(stream.next_batch())
+        );
+        assert_eq!(None, block_on(stream.next_batch()));
+        assert_eq!(
+            Some("Second"),
+            tree.read().node_by_id(BUTTON_ID).unwrap().name()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+//! Fallback word and sentence segmentation, for text nodes whose provider
+//! doesn't supply [`NodeData::words`](crate::NodeData::words).
+//!
+//! This is a simplified form of the default rules in Unicode Standard
+//! Annex #29, without any dictionary-based segmentation. A word is a run
+//! of letters, digits, and marks, possibly joined by an apostrophe, period,
+//! or connector such as `_` (as in "can't", "3.14", or "snake_case").
+//! A sentence ends after `.`, `!`, or `?` (and any closing punctuation)
+//! that is followed by whitespace or the end of the text, and includes
+//! that trailing whitespace.
+
+use std::ops::Range;
+
+use accesskit_schema::StringEncoding;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Characters that join two word characters into a single word.
+fn is_word_joiner(c: char) -> bool {
+    matches!(c, '\'' | '\u{2019}' | '.' | '\u{00B7}')
+}
+
+fn is_sentence_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '\u{2026}' | '\u{3002}')
+}
+
+fn is_sentence_closer(c: char) -> bool {
+    matches!(c, '"' | '\'' | ')' | ']' | '\u{201D}' | '\u{2019}')
+}
+
+/// Converts byte offsets into `text` into code units of `encoding`.
+/// The offsets must be in ascending order and on character boundaries.
+fn convert_offsets(
+    text: &str,
+    encoding: StringEncoding,
+    ranges: Vec<Range<usize>>,
+) -> Vec<Range<usize>> {
+    match encoding {
+        StringEncoding::Utf8 => ranges,
+        StringEncoding::Utf16 => {
+            let mut converted = 0;
+            let mut last_offset = 0;
+            let mut convert = |offset: usize| {
+                converted += text[last_offset..offset].encode_utf16().count();
+                last_offset = offset;
+                converted
+            };
+            ranges
+                .into_iter()
+                .map(|range| convert(range.start)..convert(range.end))
+                .collect()
+        }
+    }
+}
+
+/// Returns the range of each word in `text`, in code units of `encoding`.
+pub(crate) fn word_ranges(text: &str, encoding: StringEncoding) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut current: Option<Range<usize>> = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        let end = offset + c.len_utf8();
+        if is_word_char(c) {
+            match &mut current {
+                Some(range) => range.end = end,
+                None => current = Some(offset..end),
+            }
+        } else if current.is_some()
+            && is_word_joiner(c)
+            && matches!(chars.peek(), Some((_, next)) if is_word_char(*next))
+        {
+            current.as_mut().unwrap().end = end;
+        } else if let Some(range) = current.take() {
+            ranges.push(range);
+        }
+    }
+    ranges.extend(current);
+    convert_offsets(text, encoding, ranges)
+}
+
+/// Returns the range of each sentence in `text`, in code units of
+/// `encoding`. Every character of the text is in exactly one sentence.
+pub(crate) fn sentence_ranges(text: &str, encoding: StringEncoding) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if !is_sentence_terminator(c) {
+            continue;
+        }
+        while let Some((_, next)) = chars.peek() {
+            if is_sentence_terminator(*next) || is_sentence_closer(*next) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        match chars.peek() {
+            Some((_, next)) if next.is_whitespace() => (),
+            Some(_) => continue,
+            None => break,
+        }
+        while let Some((_, next)) = chars.peek() {
+            if next.is_whitespace() {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        let end = chars.peek().map_or(text.len(), |(offset, _)| *offset);
+        ranges.push(start..end);
+        start = end;
+    }
+    if start < text.len() {
+        ranges.push(start..text.len());
+    }
+    convert_offsets(text, encoding, ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use accesskit_schema::StringEncoding;
+
+    use super::{sentence_ranges, word_ranges};
+
+    fn words(text: &str) -> Vec<&str> {
+        word_ranges(text, StringEncoding::Utf8)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    fn sentences(text: &str) -> Vec<&str> {
+        sentence_ranges(text, StringEncoding::Utf8)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn words_with_punctuation() {
+        assert_eq!(
+            vec!["It's", "3.14", "not", "snake_case", "right"],
+            words("It's 3.14, not snake_case... right?")
+        );
+        assert_eq!(vec!["end"], words("  end.  "));
+        assert!(words(" , ").is_empty());
+    }
+
+    #[test]
+    fn sentences_with_trailing_whitespace() {
+        assert_eq!(
+            vec!["Hello there.  ", "Is pi 3.14? ", "\"Yes!\" ", "Done"],
+            sentences("Hello there.  Is pi 3.14? \"Yes!\" Done")
+        );
+        assert!(sentences("").is_empty());
+    }
+
+    #[test]
+    fn utf16_offsets() {
+        // "é" is two bytes in UTF-8 but one code unit in UTF-16,
+        // and the emoji is four bytes but two code units.
+        let text = "café \u{1F600} ok";
+        assert_eq!(vec![0..5, 11..13], word_ranges(text, StringEncoding::Utf8));
+        assert_eq!(vec![0..4, 8..10], word_ranges(text, StringEncoding::Utf16));
+    }
+}
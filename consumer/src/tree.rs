@@ -3,12 +3,16 @@
 // the LICENSE-APACHE file) or the MIT license (found in
 // the LICENSE-MIT file), at your option.
 
-use accesskit_schema::{NodeId, TreeId, TreeUpdate};
-use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
-use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use accesskit_schema::{
+    ActionHandler, ActionRequest, NodeAttribute, NodeAttributeKind, NodeId, Rect, Role,
+    StringEncoding, TreeId, TreeUpdate,
+};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Weak};
 
-use crate::{Node, NodeData, TreeData};
+use crate::iterators::{Traversal, TraversalOrder};
+use crate::{DirtyFlags, Node, NodeData, TreeData};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub(crate) struct ParentAndIndex(pub(crate) NodeId, pub(crate) usize);
@@ -24,37 +28,125 @@ pub(crate) struct State {
     pub(crate) nodes: im::HashMap<NodeId, NodeState>,
     pub(crate) data: TreeData,
     pub(crate) focus: Option<NodeId>,
+    /// The version of the most recent update that had one.
+    pub(crate) version: Option<u64>,
+}
+
+/// Whether [`Tree::update`] applied an update.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpdateStatus {
+    Applied,
+    /// The update was ignored, because its [`TreeUpdate::version`]
+    /// wasn't greater than that of an update that was already applied.
+    Stale {
+        version: u64,
+        last_applied: u64,
+    },
 }
 
 #[derive(Default)]
 struct InternalChanges {
     added_node_ids: HashSet<NodeId>,
     updated_node_ids: HashSet<NodeId>,
+    bounds_changed_node_ids: HashSet<NodeId>,
     focus_moved: bool,
+    root_changed: bool,
     removed_node_ids: HashSet<NodeId>,
 }
 
+impl InternalChanges {
+    fn is_changed(&self, id: &NodeId) -> bool {
+        self.added_node_ids.contains(id)
+            || self.updated_node_ids.contains(id)
+            || self.bounds_changed_node_ids.contains(id)
+            || self.removed_node_ids.contains(id)
+    }
+}
+
+/// A value computed from one or more nodes, along with the IDs of those
+/// nodes, so it can be discarded when any of them changes.
+pub(crate) struct CacheEntry<T> {
+    value: T,
+    dependencies: Vec<NodeId>,
+}
+
+/// Values that are expensive to compute from the tree, such as names
+/// computed from descendants. Entries are only added when the value is
+/// first requested, and are removed as soon as any node they were
+/// computed from changes, so the cache never holds more than one entry
+/// per node for each kind of value.
+#[derive(Default)]
+pub(crate) struct Cache {
+    pub(crate) names: HashMap<NodeId, CacheEntry<Option<String>>>,
+    pub(crate) bounds: HashMap<NodeId, CacheEntry<Option<Rect>>>,
+    #[cfg(test)]
+    hits: usize,
+}
+
+impl Cache {
+    fn invalidate(&mut self, changes: &InternalChanges) {
+        let is_valid =
+            |dependencies: &[NodeId]| !dependencies.iter().any(|id| changes.is_changed(id));
+        self.names.retain(|_, entry| is_valid(&entry.dependencies));
+        self.bounds.retain(|_, entry| is_valid(&entry.dependencies));
+    }
+}
+
 impl State {
-    fn validate_global(&self) {
-        assert!(self.nodes.contains_key(&self.data.root));
-        if let Some(id) = self.focus {
-            assert!(self.nodes.contains_key(&id));
+    fn validate_global(&self) -> Result<(), TreeUpdateError> {
+        let references = std::iter::once(self.data.root)
+            .chain(self.focus)
+            .chain(self.data.root_scroller);
+        for id in references {
+            if !self.nodes.contains_key(&id) {
+                return Err(TreeUpdateError::MissingNode(id));
+            }
         }
-        if let Some(id) = self.data.root_scroller {
-            assert!(self.nodes.contains_key(&id));
+        Ok(())
+    }
+
+    fn check_version(&self, update: &TreeUpdate) -> UpdateStatus {
+        match (update.version, self.version) {
+            (Some(version), Some(last_applied)) if version <= last_applied => UpdateStatus::Stale {
+                version,
+                last_applied,
+            },
+            _ => UpdateStatus::Applied,
         }
     }
 
-    fn update(&mut self, update: TreeUpdate, mut changes: Option<&mut InternalChanges>) {
+    // On error, the state may be partially updated, so callers that
+    // need to recover apply the update to a copy.
+    fn update(
+        &mut self,
+        update: TreeUpdate,
+        mut changes: Option<&mut InternalChanges>,
+    ) -> Result<(), TreeUpdateError> {
         // TODO: handle TreeUpdate::clear
-        assert!(update.clear.is_none());
+        if update.clear.is_some() {
+            return Err(TreeUpdateError::UnexpectedClear);
+        }
+        if let Some(tree) = &update.tree {
+            if tree.id != self.data.id {
+                return Err(TreeUpdateError::WrongTree {
+                    expected: self.data.id.clone(),
+                    actual: tree.id.clone(),
+                });
+            }
+        }
+
+        if update.version.is_some() {
+            self.version = update.version;
+        }
 
         let mut orphans = HashSet::new();
 
         if let Some(tree) = update.tree {
-            assert_eq!(tree.id, self.data.id);
             if tree.root != self.data.root {
                 orphans.insert(self.data.root);
+                if let Some(changes) = &mut changes {
+                    changes.root_changed = true;
+                }
             }
             self.data = tree;
         }
@@ -62,6 +154,11 @@ impl State {
         let root = self.data.root;
         let mut pending_nodes: HashMap<NodeId, _> = HashMap::new();
         let mut pending_children = HashMap::new();
+        // The parent that claimed each child in this update, and the
+        // previous parents of children that moved, so that a node can't
+        // end up in the children of two nodes.
+        let mut claimed_children = HashMap::new();
+        let mut moved_children = Vec::new();
 
         fn add_node(
             nodes: &mut im::HashMap<NodeId, NodeState>,
@@ -80,16 +177,31 @@ impl State {
             }
         }
 
+        let mut seen_node_ids = HashSet::new();
         for node_data in update.nodes {
             let node_id = node_data.id;
+            if !seen_node_ids.insert(node_id) {
+                return Err(TreeUpdateError::DuplicateNode(node_id));
+            }
             orphans.remove(&node_id);
 
             let mut seen_child_ids = HashSet::new();
             for (child_index, child_id) in node_data.children.iter().enumerate() {
-                assert!(!seen_child_ids.contains(child_id));
+                if seen_child_ids.contains(child_id)
+                    || claimed_children
+                        .insert(*child_id, node_id)
+                        .is_some_and(|parent_id| parent_id != node_id)
+                {
+                    return Err(TreeUpdateError::MultipleParents(*child_id));
+                }
                 orphans.remove(child_id);
                 let parent_and_index = ParentAndIndex(node_id, child_index);
                 if let Some(child_state) = self.nodes.get_mut(child_id) {
+                    if let Some(ParentAndIndex(old_parent_id, _)) = child_state.parent_and_index {
+                        if old_parent_id != node_id {
+                            moved_children.push((*child_id, old_parent_id));
+                        }
+                    }
                     if child_state.parent_and_index != Some(parent_and_index) {
                         child_state.parent_and_index = Some(parent_and_index);
                     }
@@ -135,14 +247,29 @@ impl State {
             }
         }
 
-        if !pending_nodes.is_empty() {
-            for (node_id, data) in &pending_nodes {
-                println!("unattached: {:?} {:?}", node_id, data.role);
-            }
-            panic!("unattached nodes");
+        // Without the root, every other node would look unattached.
+        if !self.nodes.contains_key(&root) {
+            return Err(TreeUpdateError::MissingNode(root));
+        }
+        if let Some(node_id) = pending_nodes.keys().next() {
+            return Err(TreeUpdateError::UnattachedNode(*node_id));
+        }
+        if let Some(child_id) = pending_children.keys().next() {
+            return Err(TreeUpdateError::MissingNode(*child_id));
         }
 
-        assert_eq!(pending_children.len(), 0);
+        // Only a node that was given a parent in this update can be part
+        // of a cycle, so it's enough to walk up from each of those.
+        for child_id in claimed_children.keys() {
+            let mut visited = HashSet::new();
+            let mut id = *child_id;
+            while let Some(ParentAndIndex(parent_id, _)) = self.nodes[&id].parent_and_index {
+                if !visited.insert(id) {
+                    return Err(TreeUpdateError::Cycle(*child_id));
+                }
+                id = parent_id;
+            }
+        }
 
         if update.focus != self.focus {
             if let Some(changes) = &mut changes {
@@ -179,7 +306,62 @@ impl State {
             }
         }
 
-        self.validate_global();
+        // A child that moved to a new parent must have been removed from
+        // the children of its previous parent, unless that parent is gone.
+        for (child_id, old_parent_id) in moved_children {
+            if self
+                .nodes
+                .get(&old_parent_id)
+                .is_some_and(|parent| parent.data.children.contains(&child_id))
+            {
+                return Err(TreeUpdateError::MultipleParents(child_id));
+            }
+        }
+
+        for patch in update.patches {
+            let node_id = patch.id;
+            let node_state = self
+                .nodes
+                .get_mut(&node_id)
+                .ok_or(TreeUpdateError::MissingNode(node_id))?;
+            debug_assert!(
+                patch.duplicate_attribute_kinds().is_empty(),
+                "patch for node {:?} sets {:?} more than once",
+                node_id,
+                patch.duplicate_attribute_kinds()
+            );
+            let mut node_data = (*node_state.data).clone();
+            patch.apply(&mut node_data);
+            if *node_state.data != node_data {
+                *node_state.data = node_data;
+                if let Some(changes) = &mut changes {
+                    if !changes.added_node_ids.contains(&node_id) {
+                        changes.updated_node_ids.insert(node_id);
+                    }
+                }
+            }
+        }
+
+        // Unlike patches, bounds updates modify the existing node data
+        // in place rather than rebuilding it.
+        for (node_id, bounds) in update.bounds_updates {
+            let node_state = self
+                .nodes
+                .get_mut(&node_id)
+                .ok_or(TreeUpdateError::MissingNode(node_id))?;
+            if node_state.data.bounds != bounds {
+                node_state.data.bounds = bounds;
+                if let Some(changes) = &mut changes {
+                    if !changes.added_node_ids.contains(&node_id)
+                        && !changes.updated_node_ids.contains(&node_id)
+                    {
+                        changes.bounds_changed_node_ids.insert(node_id);
+                    }
+                }
+            }
+        }
+
+        self.validate_global()
     }
 
     fn serialize(&self) -> TreeUpdate {
@@ -198,10 +380,11 @@ impl State {
         assert_eq!(nodes.len(), self.nodes.len());
 
         TreeUpdate {
-            clear: None,
             nodes,
             tree: Some(self.data.clone()),
             focus: self.focus,
+            version: self.version,
+            ..Default::default()
         }
     }
 }
@@ -212,6 +395,51 @@ pub struct Reader<'a> {
 }
 
 impl Reader<'_> {
+    // Only the current state of the tree may be cached; a reader for
+    // the old state, as in `Tree::update_and_process_changes`,
+    // must compute everything from scratch.
+    fn is_current(&self) -> bool {
+        std::ptr::eq(&*self.state, self.tree.state.data_ptr())
+    }
+
+    /// Returns the cached value for the given node if there is one, or else
+    /// computes it. `compute` returns the value along with the IDs of
+    /// all of the nodes that it was computed from.
+    pub(crate) fn cached<T: Clone>(
+        &self,
+        map: fn(&mut Cache) -> &mut HashMap<NodeId, CacheEntry<T>>,
+        id: NodeId,
+        compute: impl FnOnce() -> (T, Vec<NodeId>),
+    ) -> T {
+        if !self.is_current() {
+            return compute().0;
+        }
+        {
+            let mut cache = self.tree.cache.lock();
+            if let Some(entry) = map(&mut cache).get(&id) {
+                let value = entry.value.clone();
+                #[cfg(test)]
+                {
+                    cache.hits += 1;
+                }
+                return value;
+            }
+        }
+        // The cache isn't locked while computing the value. Since we hold
+        // a read lock on the state, the tree can't be updated, so nothing
+        // can invalidate the value before we store it.
+        let (value, dependencies) = compute();
+        let entry = CacheEntry {
+            value: value.clone(),
+            dependencies,
+        };
+        map(&mut self.tree.cache.lock()).insert(id, entry);
+        value
+    }
+
+    /// Looks up a node by ID in constant time. The map from IDs to nodes
+    /// is maintained as updates are applied, so this never requires
+    /// a traversal of the tree.
     pub fn node_by_id(&self, id: NodeId) -> Option<Node<'_>> {
         self.state.nodes.get(&id).map(|node_state| Node {
             tree_reader: self,
@@ -223,60 +451,630 @@ impl Reader<'_> {
         self.node_by_id(self.state.data.root).unwrap()
     }
 
+    /// Returns an iterator over every node in the tree, starting from
+    /// the root, in the given order. Each node is visited exactly once.
+    pub fn traverse(&self, order: TraversalOrder) -> Traversal<'_> {
+        Traversal::new(self.root(), order)
+    }
+
     pub fn id(&self) -> &TreeId {
         &self.state.data.id
     }
 
+    /// Returns the encoding of the string offsets in the tree, such as
+    /// those in [`NodeData::text_selection`]. See
+    /// [`TreeData::source_string_encoding`].
+    pub fn string_encoding(&self) -> StringEncoding {
+        self.state.data.source_string_encoding
+    }
+
+    /// Returns an immutable copy of the tree as it is now, which can be
+    /// read from any thread while the live tree continues to be updated.
+    /// This is cheap, because the copy shares its nodes with the live
+    /// tree until they're replaced by an update.
+    pub fn snapshot(&self) -> Snapshot {
+        let tree = Tree {
+            state: RwLock::new(self.state.clone()),
+            child_trees: RwLock::new(self.tree.child_trees.read().clone()),
+            host: RwLock::new(Host::default()),
+            action_handler: None,
+            cache: Mutex::new(Cache::default()),
+        };
+        Snapshot {
+            tree: Arc::new(tree),
+        }
+    }
+
     pub fn focus(&self) -> Option<Node<'_>> {
         self.state.focus.map(|id| self.node_by_id(id).unwrap())
     }
+
+    /// Returns the [focus-navigable](Node::is_focus_navigable) nodes in the
+    /// order that sequential focus navigation visits them, starting from
+    /// the first one in document order and following
+    /// [`Node::next_in_focus_order`]. Because that honors
+    /// [`NodeData::next_focus`], a node that the explicit order skips over
+    /// isn't included. The list ends when the order reaches a node that's
+    /// already in it, so an explicit cycle doesn't repeat.
+    pub fn focusable_nodes(&self) -> Vec<Node<'_>> {
+        let root = self.root();
+        let mut current = if root.is_focus_navigable() {
+            Some(root)
+        } else {
+            root.next_in_focus_order()
+        };
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        while let Some(node) = current {
+            if !visited.insert(node.id()) {
+                break;
+            }
+            if node.is_focus_navigable() {
+                result.push(node);
+            }
+            current = node.next_in_focus_order();
+        }
+        result
+    }
+
+    /// Returns the deepest node that's an ancestor of both of the given
+    /// nodes, where each node counts as an ancestor of itself, so if one
+    /// node is an ancestor of the other, that node is returned. Returns
+    /// `None` if either node isn't in the tree.
+    pub fn common_ancestor(&self, a: NodeId, b: NodeId) -> Option<Node<'_>> {
+        let mut a = self.node_by_id(a)?;
+        let mut b = self.node_by_id(b)?;
+        let depth = |node: Node| std::iter::successors(node.parent(), |node| node.parent()).count();
+        let mut a_depth = depth(a);
+        let mut b_depth = depth(b);
+        while a_depth > b_depth {
+            a = a.parent()?;
+            a_depth -= 1;
+        }
+        while b_depth > a_depth {
+            b = b.parent()?;
+            b_depth -= 1;
+        }
+        while a.id() != b.id() {
+            a = a.parent()?;
+            b = b.parent()?;
+        }
+        Some(a)
+    }
+
+    /// Returns the unignored, visible nodes whose bounds intersect
+    /// the viewport, which is in the same coordinate space as
+    /// [`Node::bounds`]. Nodes are visited in document order. A node whose
+    /// bounds are entirely outside the viewport isn't returned; if it also
+    /// [clips its children](NodeData::clips_children), its whole subtree
+    /// is skipped without being visited. Otherwise, descendants of
+    /// a clipping node are only returned if they intersect both
+    /// the viewport and the clipping node's bounds.
+    pub fn visible_nodes(&self, viewport: Rect) -> impl Iterator<Item = Node<'_>> + '_ {
+        let mut stack = vec![(self.root(), viewport)];
+        std::iter::from_fn(move || {
+            while let Some((node, clip)) = stack.pop() {
+                if node.is_invisible() {
+                    continue;
+                }
+                let bounds = node.bounds();
+                let visible_bounds = bounds.and_then(|bounds| bounds.intersection(&clip));
+                let child_clip = if node.data().clips_children {
+                    match visible_bounds {
+                        Some(visible_bounds) => Some(visible_bounds),
+                        None if bounds.is_some() => None,
+                        None => Some(clip),
+                    }
+                } else {
+                    Some(clip)
+                };
+                if let Some(child_clip) = child_clip {
+                    stack.extend(node.children().rev().map(|child| (child, child_clip)));
+                }
+                if visible_bounds.is_some() && !node.is_ignored() {
+                    return Some(node);
+                }
+            }
+            None
+        })
+    }
+
+    /// Returns an update that initializes a tree containing just the
+    /// subtree rooted at the given node, with parents before children,
+    /// or `None` if the node isn't in the tree. This lets a provider
+    /// send part of a large tree to another process when it's needed.
+    ///
+    /// A node that hosts a [child tree](Node::child_tree_id) is included,
+    /// but its children aren't, since the child tree is sent separately.
+    /// Bounds that are relative to an offset container outside the subtree
+    /// are converted to an equivalent transform, so they keep the same
+    /// [global bounds](Node::bounds). The focus and root scroller are only
+    /// kept if they're in the subtree. Other references, like relations,
+    /// are copied as they are, even if they point outside the subtree.
+    pub fn subtree_update(&self, root: NodeId) -> Option<TreeUpdate> {
+        let root = self.node_by_id(root)?;
+        let mut included = HashSet::new();
+        let mut nodes = Vec::new();
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            included.insert(node.id());
+            let mut data = node.data().clone();
+            if node.child_tree_id().is_some() {
+                data.children = Default::default();
+            } else {
+                stack.extend(node.children().rev());
+            }
+            nodes.push((node, data));
+        }
+        let nodes = nodes
+            .into_iter()
+            .map(|(node, mut data)| {
+                let outside_container = data
+                    .bounds
+                    .as_ref()
+                    .and_then(|bounds| bounds.offset_container)
+                    .is_some_and(|id| !included.contains(&id));
+                if outside_container {
+                    data.bounds = node.standalone_bounds();
+                }
+                data
+            })
+            .collect();
+        let tree = &self.state.data;
+        Some(TreeUpdate {
+            nodes,
+            tree: Some(TreeData {
+                root: root.id(),
+                root_scroller: tree.root_scroller.filter(|id| included.contains(id)),
+                ..tree.clone()
+            }),
+            focus: self.state.focus.filter(|id| included.contains(id)),
+            version: self.state.version,
+            ..Default::default()
+        })
+    }
+
+    /// Returns a pretty-printed JSON dump of the tree, starting at the root,
+    /// with each node's children nested inside it. Each node has the
+    /// attributes that are set, including its children, sorted by name.
+    /// Values use the same encoding as the schema's serde
+    /// implementations, so attributes with default values are omitted.
+    /// The output depends only on the contents of the tree, so it's
+    /// suitable for snapshot testing.
+    #[cfg(feature = "serde")]
+    pub fn to_snapshot_json(&self) -> String {
+        crate::snapshot::to_json(self.root())
+    }
+}
+
+/// A reason why a [`TreeUpdate`] can't be used to initialize or update
+/// a tree, as reported by [`Tree::try_new`] and [`Tree::try_update`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeUpdateError {
+    /// [`TreeUpdate::tree`] isn't set, so the tree has no ID or root.
+    MissingTree,
+    /// [`TreeUpdate::tree`] has a different ID than the tree being updated.
+    WrongTree { expected: TreeId, actual: TreeId },
+    /// [`TreeUpdate::clear`] is set, which isn't supported yet.
+    UnexpectedClear,
+    /// The same node appears more than once in [`TreeUpdate::nodes`].
+    DuplicateNode(NodeId),
+    /// The root, a child, the focus, or the target of a patch
+    /// isn't in [`TreeUpdate::nodes`].
+    MissingNode(NodeId),
+    /// A node is the child of more than one node, or of the same
+    /// node more than once.
+    MultipleParents(NodeId),
+    /// A node isn't reachable from the root.
+    UnattachedNode(NodeId),
+    /// A node is its own ancestor.
+    Cycle(NodeId),
+}
+
+impl std::fmt::Display for TreeUpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingTree => write!(f, "the update has no tree data"),
+            Self::WrongTree { expected, actual } => write!(
+                f,
+                "the update is for tree {:?}, not tree {:?}",
+                actual, expected
+            ),
+            Self::UnexpectedClear => write!(f, "clearing a node isn't supported"),
+            Self::DuplicateNode(id) => write!(f, "node {:?} appears more than once", id),
+            Self::MissingNode(id) => write!(f, "node {:?} is referenced but missing", id),
+            Self::MultipleParents(id) => write!(f, "node {:?} has more than one parent", id),
+            Self::UnattachedNode(id) => write!(f, "node {:?} isn't reachable from the root", id),
+            Self::Cycle(id) => write!(f, "node {:?} is its own ancestor", id),
+        }
+    }
 }
 
+impl std::error::Error for TreeUpdateError {}
+
 pub enum Change<'a> {
     NodeAdded(Node<'a>),
     NodeUpdated {
         old_node: Node<'a>,
         new_node: Node<'a>,
+        /// Which categories of the node's data changed.
+        dirty: DirtyFlags,
     },
     FocusMoved {
         old_node: Option<Node<'a>>,
         new_node: Option<Node<'a>>,
     },
     NodeRemoved(Node<'a>),
+    /// Only the [bounds](accesskit_schema::Node::bounds) of the node
+    /// changed, as the result of [`TreeUpdate::bounds_updates`]. A node
+    /// whose other attributes also changed is reported as
+    /// [`Change::NodeUpdated`] instead.
+    BoundsChanged {
+        old_node: Node<'a>,
+        new_node: Node<'a>,
+    },
+    /// The [active descendant](accesskit_schema::Node::active_descendant)
+    /// of `container` changed, e.g. because the user moved to another
+    /// option in a combo box or IME candidate list. Either side is `None`
+    /// if there was no active descendant or it isn't in the tree.
+    ActiveDescendantChanged {
+        container: Node<'a>,
+        old: Option<Node<'a>>,
+        new: Option<Node<'a>>,
+    },
+    /// The tree's root was replaced by a different node, which usually
+    /// means that the whole UI has been rebuilt.
+    RootChanged {
+        old_node: Node<'a>,
+        new_node: Node<'a>,
+    },
+}
+
+/// An owned record of a [`Change`] that identifies the affected nodes
+/// by ID, so it can be retained and replayed after the update
+/// has been processed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeRecord {
+    NodeAdded(NodeId),
+    NodeUpdated(NodeId),
+    FocusMoved {
+        old_node: Option<NodeId>,
+        new_node: Option<NodeId>,
+    },
+    NodeRemoved(NodeId),
+    BoundsChanged(NodeId),
+    ActiveDescendantChanged {
+        container: NodeId,
+        old: Option<NodeId>,
+        new: Option<NodeId>,
+    },
+    RootChanged {
+        old_node: NodeId,
+        new_node: NodeId,
+    },
+}
+
+impl From<&Change<'_>> for ChangeRecord {
+    fn from(change: &Change) -> Self {
+        match change {
+            Change::NodeAdded(node) => Self::NodeAdded(node.id()),
+            Change::NodeUpdated { new_node, .. } => Self::NodeUpdated(new_node.id()),
+            Change::FocusMoved { old_node, new_node } => Self::FocusMoved {
+                old_node: old_node.map(|node| node.id()),
+                new_node: new_node.map(|node| node.id()),
+            },
+            Change::NodeRemoved(node) => Self::NodeRemoved(node.id()),
+            Change::BoundsChanged { new_node, .. } => Self::BoundsChanged(new_node.id()),
+            Change::ActiveDescendantChanged {
+                container,
+                old,
+                new,
+            } => Self::ActiveDescendantChanged {
+                container: container.id(),
+                old: old.map(|node| node.id()),
+                new: new.map(|node| node.id()),
+            },
+            Change::RootChanged { old_node, new_node } => Self::RootChanged {
+                old_node: old_node.id(),
+                new_node: new_node.id(),
+            },
+        }
+    }
+}
+
+/// Receives the changes made by an update, one method per kind of
+/// [`Change`], in the order described by
+/// [`Tree::update_and_process_changes`]. Every method does nothing
+/// by default, so a sink only needs to implement the changes it cares
+/// about. A platform adapter can implement this to raise platform events,
+/// and a test can implement it to record the changes.
+pub trait EventSink {
+    fn on_node_added(&mut self, _node: Node) {}
+
+    /// `dirty` is the same as in [`Change::NodeUpdated`], so a sink can
+    /// skip comparing data that didn't change.
+    fn on_node_updated(&mut self, _old_node: Node, _new_node: Node, _dirty: DirtyFlags) {}
+
+    fn on_focus_moved(&mut self, _old_node: Option<Node>, _new_node: Option<Node>) {}
+
+    fn on_node_removed(&mut self, _node: Node) {}
+
+    fn on_bounds_changed(&mut self, _old_node: Node, _new_node: Node) {}
+
+    fn on_active_descendant_changed(
+        &mut self,
+        _container: Node,
+        _old: Option<Node>,
+        _new: Option<Node>,
+    ) {
+    }
+
+    fn on_root_changed(&mut self, _old_node: Node, _new_node: Node) {}
+}
+
+// Where a tree is registered as a child tree. The hosting node is
+// found when the tree is registered and kept up to date as the host
+// tree is updated, so that looking it up doesn't require a traversal.
+#[derive(Default)]
+struct Host {
+    tree: Weak<Tree>,
+    node_id: Option<NodeId>,
 }
 
 pub struct Tree {
     state: RwLock<State>,
+    child_trees: RwLock<HashMap<TreeId, Arc<Tree>>>,
+    host: RwLock<Host>,
+    action_handler: Option<Box<dyn ActionHandler + Send + Sync>>,
+    cache: Mutex<Cache>,
 }
 
 impl Tree {
-    pub fn new(mut initial_state: TreeUpdate) -> Arc<Self> {
-        assert!(initial_state.clear.is_none());
+    /// Creates a tree that ignores action requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the update isn't a complete tree; see [`Tree::try_new`].
+    pub fn new(initial_state: TreeUpdate) -> Arc<Self> {
+        Self::new_internal(initial_state, None)
+            .unwrap_or_else(|error| panic!("invalid initial state: {}", error))
+    }
+
+    /// Creates a tree that forwards action requests, e.g. those made
+    /// through [`Tree::do_action`], to the given handler.
+    pub fn with_action_handler(
+        initial_state: TreeUpdate,
+        action_handler: Box<dyn ActionHandler + Send + Sync>,
+    ) -> Arc<Self> {
+        Self::new_internal(initial_state, Some(action_handler))
+            .unwrap_or_else(|error| panic!("invalid initial state: {}", error))
+    }
 
+    /// Creates a tree that ignores action requests, as with [`Tree::new`],
+    /// but returns an error rather than panicking if the update isn't
+    /// a complete tree. This is also a lightweight way to inspect
+    /// the result of a single update in tests.
+    pub fn try_new(initial_state: TreeUpdate) -> Result<Arc<Self>, TreeUpdateError> {
+        Self::new_internal(initial_state, None)
+    }
+
+    /// Builds a tree from a single complete update. This is the same as
+    /// [`Tree::try_new`].
+    pub fn from_update(update: TreeUpdate) -> Result<Arc<Self>, TreeUpdateError> {
+        Self::try_new(update)
+    }
+
+    fn new_internal(
+        mut initial_state: TreeUpdate,
+        action_handler: Option<Box<dyn ActionHandler + Send + Sync>>,
+    ) -> Result<Arc<Self>, TreeUpdateError> {
+        let data = initial_state
+            .tree
+            .take()
+            .ok_or(TreeUpdateError::MissingTree)?;
         let mut state = State {
             nodes: im::HashMap::new(),
-            data: initial_state.tree.take().unwrap(),
+            data,
             focus: None,
+            version: None,
         };
-        state.update(initial_state, None);
-        Arc::new(Self {
+        state.update(initial_state, None)?;
+        Ok(Arc::new(Self {
             state: RwLock::new(state),
-        })
+            child_trees: RwLock::new(HashMap::new()),
+            host: RwLock::new(Host::default()),
+            action_handler,
+            cache: Mutex::new(Cache::default()),
+        }))
+    }
+
+    pub fn do_action(&self, request: ActionRequest) {
+        if let Some(action_handler) = &self.action_handler {
+            action_handler.do_action(request);
+        }
+    }
+
+    /// Registers a tree that is embedded in this one, so that it can be
+    /// reached from any node whose [`NodeData::child_tree`] matches
+    /// the embedded tree's ID. Registering a tree with the same ID as
+    /// a previously registered tree replaces the previous one.
+    pub fn register_child_tree(self: &Arc<Self>, child_tree: Arc<Tree>) {
+        let id = child_tree.read().id().clone();
+        // Holding the state lock keeps an update from changing the hosting
+        // node between finding it here and inserting the child tree.
+        let state = self.state.read();
+        let node_id = state
+            .nodes
+            .iter()
+            .find(|(_, node_state)| node_state.data.child_tree.as_ref() == Some(&id))
+            .map(|(node_id, _)| *node_id);
+        *child_tree.host.write() = Host {
+            tree: Arc::downgrade(self),
+            node_id,
+        };
+        self.child_trees.write().insert(id, child_tree);
+    }
+
+    pub fn unregister_child_tree(&self, id: &TreeId) -> Option<Arc<Tree>> {
+        let child_tree = self.child_trees.write().remove(id)?;
+        *child_tree.host.write() = Host::default();
+        Some(child_tree)
+    }
+
+    pub fn child_tree(&self, id: &TreeId) -> Option<Arc<Tree>> {
+        self.child_trees.read().get(id).cloned()
+    }
+
+    /// Returns the tree in which this tree is
+    /// [registered as a child tree](Tree::register_child_tree), along with
+    /// the ID of the node that hosts it. Returns `None` if this tree
+    /// isn't registered, or if no node in the host tree currently refers
+    /// to this tree's ID. A tree is hosted by one node at a time; if
+    /// several nodes refer to it, the one that most recently started
+    /// doing so is returned.
+    pub fn host(&self) -> Option<(Arc<Tree>, NodeId)> {
+        let host = self.host.read();
+        Some((host.tree.upgrade()?, host.node_id?))
+    }
+
+    // Keeps the hosting node of each registered child tree up to date
+    // when an update changes which node refers to it. This is called
+    // while the state lock is held for writing.
+    fn update_child_tree_hosts(&self, old: &State, new: &State, changes: &InternalChanges) {
+        let child_tree_of = |state: &State, id: &NodeId| {
+            state
+                .nodes
+                .get(id)
+                .and_then(|node_state| node_state.data.child_tree.clone())
+        };
+        let mut added = Vec::new();
+        let child_trees = self.child_trees.read();
+        for id in changes
+            .added_node_ids
+            .iter()
+            .chain(changes.updated_node_ids.iter())
+            .chain(changes.removed_node_ids.iter())
+        {
+            let old_child_tree = child_tree_of(old, id);
+            let new_child_tree = child_tree_of(new, id);
+            if old_child_tree == new_child_tree {
+                continue;
+            }
+            if let Some(child_tree) = old_child_tree.and_then(|tree_id| child_trees.get(&tree_id)) {
+                let mut host = child_tree.host.write();
+                if host.node_id == Some(*id) {
+                    host.node_id = None;
+                }
+            }
+            if let Some(child_tree) = new_child_tree.and_then(|tree_id| child_trees.get(&tree_id)) {
+                added.push((child_tree, *id));
+            }
+        }
+        // Nodes that now refer to a child tree take precedence over
+        // those that stopped referring to it, whatever the order in
+        // which they were changed.
+        for (child_tree, id) in added {
+            child_tree.host.write().node_id = Some(id);
+        }
+    }
+
+    /// Returns the tree containing the node that actually has focus,
+    /// along with that node's ID. When the focus is on a node that hosts
+    /// a [registered child tree](Tree::register_child_tree), the focus
+    /// within the child tree is followed, across any number of nested
+    /// trees. If the child tree isn't registered, or has no focus,
+    /// the focused node in this tree is returned.
+    pub fn deep_focus(self: &Arc<Self>) -> Option<(Arc<Tree>, NodeId)> {
+        let (focus_id, child_tree) = {
+            let reader = self.read();
+            let focus = reader.focus()?;
+            (focus.id(), focus.child_tree())
+        };
+        child_tree
+            .and_then(|child_tree| child_tree.deep_focus())
+            .or_else(|| Some((self.clone(), focus_id)))
+    }
+
+    /// Applies the update, unless it's older than one that was already
+    /// applied, according to [`TreeUpdate::version`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the update is invalid; see [`Tree::try_update`].
+    pub fn update(&self, update: TreeUpdate) -> UpdateStatus {
+        self.try_update(update)
+            .unwrap_or_else(|error| panic!("invalid tree update: {}", error))
     }
 
-    pub fn update(&self, update: TreeUpdate) {
+    /// Applies the update as with [`Tree::update`], but returns an error
+    /// rather than panicking if the update is invalid, in which case
+    /// the tree is left unchanged.
+    pub fn try_update(&self, update: TreeUpdate) -> Result<UpdateStatus, TreeUpdateError> {
+        let mut changes = InternalChanges::default();
         let mut state = self.state.write();
-        state.update(update, None);
+        let status = state.check_version(&update);
+        if status == UpdateStatus::Applied {
+            let mut new_state = state.clone();
+            new_state.update(update, Some(&mut changes))?;
+            self.update_child_tree_hosts(&state, &new_state, &changes);
+            *state = new_state;
+            self.cache.lock().invalidate(&changes);
+        }
+        Ok(status)
+    }
+
+    /// Applies the update as with [`Tree::update`], and calls `f`
+    /// for each resulting change. A stale update has no changes.
+    ///
+    /// The changes are reported in this order, so that by the time a
+    /// platform adapter announces that focus moved, it has already
+    /// reported any new name, value or state of the newly focused node:
+    ///
+    /// 1. [`Change::NodeAdded`] for each added node.
+    /// 2. [`Change::NodeUpdated`] for each updated node, then
+    ///    [`Change::BoundsChanged`] and
+    ///    [`Change::ActiveDescendantChanged`].
+    /// 3. If focus moved, [`Change::NodeUpdated`] for the previously and
+    ///    newly focused nodes, unless they were already reported, and then
+    ///    [`Change::FocusMoved`].
+    /// 4. [`Change::NodeRemoved`] for each removed node.
+    /// 5. [`Change::RootChanged`], if the root was replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the update is invalid; see
+    /// [`Tree::try_update_and_process_changes`].
+    pub fn update_and_process_changes<F>(self: &Arc<Tree>, update: TreeUpdate, f: F) -> UpdateStatus
+    where
+        for<'a> F: FnMut(Change<'a>),
+    {
+        self.try_update_and_process_changes(update, f)
+            .unwrap_or_else(|error| panic!("invalid tree update: {}", error))
     }
 
-    pub fn update_and_process_changes<F>(self: &Arc<Tree>, update: TreeUpdate, mut f: F)
+    /// Applies the update as with [`Tree::update_and_process_changes`],
+    /// but returns an error rather than panicking if the update is
+    /// invalid, in which case the tree is left unchanged and `f`
+    /// isn't called.
+    pub fn try_update_and_process_changes<F>(
+        self: &Arc<Tree>,
+        update: TreeUpdate,
+        mut f: F,
+    ) -> Result<UpdateStatus, TreeUpdateError>
     where
         for<'a> F: FnMut(Change<'a>),
     {
         let mut changes = InternalChanges::default();
         let mut state = self.state.write();
-        let old_state = state.clone();
-        state.update(update, Some(&mut changes));
+        let status = state.check_version(&update);
+        if status != UpdateStatus::Applied {
+            return Ok(status);
+        }
+        let mut new_state = state.clone();
+        new_state.update(update, Some(&mut changes))?;
+        self.update_child_tree_hosts(&state, &new_state, &changes);
+        let old_state = std::mem::replace(&mut *state, new_state);
+        self.cache.lock().invalidate(&changes);
         let state = RwLockWriteGuard::downgrade(state);
         let reader = Reader { tree: self, state };
         // Ideally we shouldn't have to wrap the old state in an `RwLock`.
@@ -298,7 +1096,30 @@ impl Tree {
         for id in &changes.updated_node_ids {
             let old_node = old_reader.node_by_id(*id).unwrap();
             let new_node = reader.node_by_id(*id).unwrap();
-            f(Change::NodeUpdated { old_node, new_node });
+            let dirty = DirtyFlags::between(old_node.data(), new_node.data());
+            f(Change::NodeUpdated {
+                old_node,
+                new_node,
+                dirty,
+            });
+        }
+        for id in &changes.bounds_changed_node_ids {
+            let old_node = old_reader.node_by_id(*id).unwrap();
+            let new_node = reader.node_by_id(*id).unwrap();
+            f(Change::BoundsChanged { old_node, new_node });
+        }
+        for id in &changes.updated_node_ids {
+            let old_node = old_reader.node_by_id(*id).unwrap();
+            let new_node = reader.node_by_id(*id).unwrap();
+            let old_id = old_node.data().active_descendant;
+            let new_id = new_node.data().active_descendant;
+            if old_id != new_id {
+                f(Change::ActiveDescendantChanged {
+                    container: new_node,
+                    old: old_id.and_then(|id| old_reader.node_by_id(id)),
+                    new: new_id.and_then(|id| reader.node_by_id(id)),
+                });
+            }
         }
         if changes.focus_moved {
             let old_node = old_reader.focus();
@@ -311,6 +1132,10 @@ impl Tree {
                         f(Change::NodeUpdated {
                             old_node,
                             new_node: old_node_new_version,
+                            dirty: DirtyFlags {
+                                state: true,
+                                ..DirtyFlags::default()
+                            },
                         });
                     }
                 }
@@ -324,6 +1149,10 @@ impl Tree {
                         f(Change::NodeUpdated {
                             old_node: new_node_old_version,
                             new_node,
+                            dirty: DirtyFlags {
+                                state: true,
+                                ..DirtyFlags::default()
+                            },
                         });
                     }
                 }
@@ -334,9 +1163,81 @@ impl Tree {
             let node = old_reader.node_by_id(*id).unwrap();
             f(Change::NodeRemoved(node));
         }
+        if changes.root_changed {
+            f(Change::RootChanged {
+                old_node: old_reader.root(),
+                new_node: reader.root(),
+            });
+        }
+        Ok(UpdateStatus::Applied)
+    }
+
+    /// Applies the update as with [`Tree::update_and_process_changes`],
+    /// passing each change to the corresponding method of `sink`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the update is invalid; see [`Tree::try_update_with_sink`].
+    pub fn update_with_sink(
+        self: &Arc<Tree>,
+        update: TreeUpdate,
+        sink: &mut impl EventSink,
+    ) -> UpdateStatus {
+        self.try_update_with_sink(update, sink)
+            .unwrap_or_else(|error| panic!("invalid tree update: {}", error))
+    }
+
+    /// Applies the update as with [`Tree::update_with_sink`], but returns
+    /// an error rather than panicking if the update is invalid, in which
+    /// case the tree is left unchanged and `sink` isn't called.
+    pub fn try_update_with_sink(
+        self: &Arc<Tree>,
+        update: TreeUpdate,
+        sink: &mut impl EventSink,
+    ) -> Result<UpdateStatus, TreeUpdateError> {
+        self.try_update_and_process_changes(update, |change| match change {
+            Change::NodeAdded(node) => sink.on_node_added(node),
+            Change::NodeUpdated {
+                old_node,
+                new_node,
+                dirty,
+            } => sink.on_node_updated(old_node, new_node, dirty),
+            Change::FocusMoved { old_node, new_node } => sink.on_focus_moved(old_node, new_node),
+            Change::NodeRemoved(node) => sink.on_node_removed(node),
+            Change::BoundsChanged { old_node, new_node } => {
+                sink.on_bounds_changed(old_node, new_node)
+            }
+            Change::ActiveDescendantChanged {
+                container,
+                old,
+                new,
+            } => sink.on_active_descendant_changed(container, old, new),
+            Change::RootChanged { old_node, new_node } => sink.on_root_changed(old_node, new_node),
+        })
+    }
+
+    /// Applies the update and returns a record of every change,
+    /// in the same order in which [`Tree::update_and_process_changes`]
+    /// would report them.
+    pub fn update_and_collect_changes(self: &Arc<Tree>, update: TreeUpdate) -> Vec<ChangeRecord> {
+        let mut changes = Vec::new();
+        self.update_and_process_changes(update, |change| {
+            changes.push(ChangeRecord::from(&change));
+        });
+        changes
+    }
+
+    #[cfg(test)]
+    pub(crate) fn cache_hits(&self) -> usize {
+        self.cache.lock().hits
     }
 
-    // Intended for debugging.
+    /// Returns the current state of the tree as a single update,
+    /// including the tree data, the focus, and the version of the last
+    /// versioned update. The nodes are in depth-first order starting
+    /// with the root, so the update can initialize another tree with
+    /// [`Tree::new`], e.g. in another process, with the same structure
+    /// and attributes as this one.
     pub fn serialize(&self) -> TreeUpdate {
         let state = self.state.read();
         state.serialize()
@@ -352,10 +1253,122 @@ impl Tree {
     }
 }
 
+/// An immutable copy of a tree, as returned by [`Reader::snapshot`].
+/// It has the same accessors as the live tree, through [`Snapshot::read`],
+/// but since the copy is never updated, reading it never waits for
+/// an update. Action requests made through its nodes are ignored.
+/// Child trees are shared with the live tree rather than copied.
+#[derive(Clone)]
+pub struct Snapshot {
+    tree: Arc<Tree>,
+}
+
+impl Snapshot {
+    pub fn read(&self) -> Reader<'_> {
+        self.tree.read()
+    }
+}
+
+/// One difference between two trees, as reported by [`diff_summary`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeDiff {
+    Added(NodeId),
+    Removed(NodeId),
+    RoleChanged {
+        id: NodeId,
+        old: Role,
+        new: Role,
+    },
+    ChildrenChanged {
+        id: NodeId,
+        old: Box<[NodeId]>,
+        new: Box<[NodeId]>,
+    },
+    /// An attribute was set, cleared, or changed. The old or new value
+    /// is `None` if the attribute wasn't set in that tree.
+    AttributeChanged {
+        id: NodeId,
+        kind: NodeAttributeKind,
+        old: Option<NodeAttribute>,
+        new: Option<NodeAttribute>,
+    },
+}
+
+/// Compares two trees node by node, for debugging or for explaining
+/// a failed snapshot test. Unlike a [`TreeUpdate`], which is meant to be
+/// as small as possible, this lists every individual difference.
+/// The result is sorted by node ID; the differences for a single node
+/// are listed in the order role, children, then attributes sorted by name.
+pub fn diff_summary(old: &Tree, new: &Tree) -> Vec<NodeDiff> {
+    let old_state = old.state.read();
+    let new_state = new.state.read();
+    let mut ids = old_state
+        .nodes
+        .keys()
+        .chain(new_state.nodes.keys())
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    ids.sort_by_key(|id| id.0);
+    let mut diffs = Vec::new();
+    for id in ids {
+        let (old_node, new_node) = match (old_state.nodes.get(&id), new_state.nodes.get(&id)) {
+            (Some(old_node), Some(new_node)) => (&old_node.data, &new_node.data),
+            (Some(_), None) => {
+                diffs.push(NodeDiff::Removed(id));
+                continue;
+            }
+            (None, Some(_)) => {
+                diffs.push(NodeDiff::Added(id));
+                continue;
+            }
+            (None, None) => unreachable!(),
+        };
+        if old_node.role != new_node.role {
+            diffs.push(NodeDiff::RoleChanged {
+                id,
+                old: old_node.role,
+                new: new_node.role,
+            });
+        }
+        if old_node.children != new_node.children {
+            diffs.push(NodeDiff::ChildrenChanged {
+                id,
+                old: old_node.children.clone(),
+                new: new_node.children.clone(),
+            });
+        }
+        let mut attributes = BTreeMap::new();
+        for attribute in old_node.attributes() {
+            let name = attribute.kind().name();
+            attributes.entry(name).or_insert((None, None)).0 = Some(attribute);
+        }
+        for attribute in new_node.attributes() {
+            let name = attribute.kind().name();
+            attributes.entry(name).or_insert((None, None)).1 = Some(attribute);
+        }
+        for (old, new) in attributes.into_values() {
+            if old != new {
+                let kind = old.as_ref().or(new.as_ref()).unwrap().kind();
+                diffs.push(NodeDiff::AttributeChanged { id, kind, old, new });
+            }
+        }
+    }
+    diffs
+}
+
 #[cfg(test)]
 mod tests {
-    use accesskit_schema::{Node, NodeId, Role, StringEncoding, Tree, TreeId, TreeUpdate};
+    use accesskit_schema::{
+        Node, NodeAttribute, NodeAttributeKind, NodeId, NodePatch, Rect, RelativeBounds, Role,
+        StringEncoding, Tree, TreeId, TreeUpdate,
+    };
     use std::num::NonZeroU64;
+    use std::sync::Arc;
+
+    use super::{diff_summary, ChangeRecord, EventSink, NodeDiff, TreeUpdateError, UpdateStatus};
+    use crate::TraversalOrder;
 
     const TREE_ID: &str = "test_tree";
     const NODE_ID_1: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
@@ -363,43 +1376,508 @@ mod tests {
     const NODE_ID_3: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
 
     #[test]
-    fn init_tree_with_root_node() {
-        let update = TreeUpdate {
-            clear: None,
-            nodes: vec![Node::new(NODE_ID_1, Role::Window)],
+    fn navigate_into_child_tree() {
+        const CHILD_TREE_ID: &str = "child_tree";
+        let host_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    child_tree: Some(TreeId(CHILD_TREE_ID.into())),
+                    ..Node::new(NODE_ID_2, Role::WebView)
+                },
+            ],
             tree: Some(Tree::new(
                 TreeId(TREE_ID.into()),
                 NODE_ID_1,
                 StringEncoding::Utf8,
             )),
-            focus: None,
+            ..Default::default()
         };
-        let tree = super::Tree::new(update);
-        assert_eq!(&TreeId(TREE_ID.into()), tree.read().id());
-        assert_eq!(NODE_ID_1, tree.read().root().id());
-        assert_eq!(Role::Window, tree.read().root().role());
-        assert!(tree.read().root().parent().is_none());
+        let child_update = TreeUpdate {
+            nodes: vec![Node::new(NODE_ID_1, Role::RootWebArea)],
+            tree: Some(Tree::new(
+                TreeId(CHILD_TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let host_tree = super::Tree::new(host_update);
+        let child_tree = super::Tree::new(child_update);
+        {
+            let reader = host_tree.read();
+            let host_node = reader.node_by_id(NODE_ID_2).unwrap();
+            assert_eq!(Some(CHILD_TREE_ID), host_node.child_tree_id());
+            assert!(host_node.child_tree_root().is_none());
+            assert!(reader.root().child_tree_id().is_none());
+            assert!(child_tree.read().root().host_node().is_none());
+        }
+
+        host_tree.register_child_tree(child_tree);
+        let (embedded_tree, embedded_root_id) = {
+            let reader = host_tree.read();
+            let host_node = reader.node_by_id(NODE_ID_2).unwrap();
+            // The host node's own children are unaffected.
+            assert_eq!(0, host_node.children().count());
+            host_node.child_tree_root().unwrap()
+        };
+        {
+            let reader = embedded_tree.read();
+            let embedded_root = reader.node_by_id(embedded_root_id).unwrap();
+            assert_eq!(&TreeId(CHILD_TREE_ID.into()), reader.id());
+            assert_eq!(Role::RootWebArea, embedded_root.role());
+            assert!(embedded_root.parent().is_none());
+
+            let (tree, host_id) = embedded_root.host_node().unwrap();
+            assert!(Arc::ptr_eq(&host_tree, &tree));
+            assert_eq!(NODE_ID_2, host_id);
+        }
+
+        assert!(host_tree
+            .unregister_child_tree(&TreeId(CHILD_TREE_ID.into()))
+            .is_some());
+        assert!(host_tree
+            .read()
+            .node_by_id(NODE_ID_2)
+            .unwrap()
+            .child_tree_root()
+            .is_none());
+        assert!(embedded_tree.read().root().host_node().is_none());
     }
 
     #[test]
-    fn root_node_has_children() {
-        let update = TreeUpdate {
-            clear: None,
+    fn host_follows_host_updates() {
+        const CHILD_TREE_ID: &str = "child_tree";
+        let host_tree = super::Tree::new(TreeUpdate {
             nodes: vec![
                 Node {
                     children: Box::new([NODE_ID_2, NODE_ID_3]),
                     ..Node::new(NODE_ID_1, Role::Window)
                 },
-                Node::new(NODE_ID_2, Role::Button),
-                Node::new(NODE_ID_3, Role::Button),
+                Node {
+                    child_tree: Some(TreeId(CHILD_TREE_ID.into())),
+                    ..Node::new(NODE_ID_2, Role::WebView)
+                },
+                Node::new(NODE_ID_3, Role::WebView),
             ],
             tree: Some(Tree::new(
                 TreeId(TREE_ID.into()),
                 NODE_ID_1,
                 StringEncoding::Utf8,
             )),
-            focus: None,
-        };
+            ..Default::default()
+        });
+        let child_tree = super::Tree::new(TreeUpdate {
+            nodes: vec![Node::new(NODE_ID_1, Role::RootWebArea)],
+            tree: Some(Tree::new(
+                TreeId(CHILD_TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+        host_tree.register_child_tree(child_tree.clone());
+        assert_eq!(NODE_ID_2, child_tree.host().unwrap().1);
+
+        // Move the child tree to the other web view.
+        host_tree.update(TreeUpdate {
+            nodes: vec![
+                Node::new(NODE_ID_2, Role::WebView),
+                Node {
+                    child_tree: Some(TreeId(CHILD_TREE_ID.into())),
+                    ..Node::new(NODE_ID_3, Role::WebView)
+                },
+            ],
+            ..Default::default()
+        });
+        let (tree, host_id) = child_tree.host().unwrap();
+        assert!(Arc::ptr_eq(&host_tree, &tree));
+        assert_eq!(NODE_ID_3, host_id);
+
+        // Removing the hosting node leaves the child tree without a host.
+        host_tree.update_and_process_changes(
+            TreeUpdate {
+                nodes: vec![Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                }],
+                ..Default::default()
+            },
+            |_| {},
+        );
+        assert!(child_tree.host().is_none());
+        assert!(host_tree
+            .child_tree(&TreeId(CHILD_TREE_ID.into()))
+            .is_some());
+    }
+
+    #[test]
+    fn deep_focus_follows_child_tree() {
+        const CHILD_TREE_ID: &str = "child_tree";
+        let host_tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    child_tree: Some(TreeId(CHILD_TREE_ID.into())),
+                    focusable: true,
+                    ..Node::new(NODE_ID_2, Role::WebView)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_2),
+            ..Default::default()
+        });
+        let child_tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::RootWebArea)
+                },
+                Node {
+                    focusable: true,
+                    ..Node::new(NODE_ID_3, Role::Button)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(CHILD_TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_3),
+            ..Default::default()
+        });
+
+        let (tree, id) = host_tree.deep_focus().unwrap();
+        assert!(Arc::ptr_eq(&host_tree, &tree));
+        assert_eq!(NODE_ID_2, id);
+
+        host_tree.register_child_tree(child_tree.clone());
+        let (tree, id) = host_tree.deep_focus().unwrap();
+        assert!(Arc::ptr_eq(&child_tree, &tree));
+        assert_eq!(NODE_ID_3, id);
+
+        // A child tree without focus leaves the focus on the host node.
+        child_tree.update(TreeUpdate::default());
+        let (tree, id) = host_tree.deep_focus().unwrap();
+        assert!(Arc::ptr_eq(&host_tree, &tree));
+        assert_eq!(NODE_ID_2, id);
+    }
+
+    #[test]
+    fn subtree_update() {
+        use crate::tests::*;
+        let tree = test_tree();
+        let reader = tree.read();
+        assert!(reader
+            .subtree_update(NodeId(NonZeroU64::new(100).unwrap()))
+            .is_none());
+
+        let update = reader.subtree_update(PARAGRAPH_3_IGNORED_ID).unwrap();
+        let ids: Vec<NodeId> = update.nodes.iter().map(|node| node.id).collect();
+        assert_eq!(
+            vec![
+                PARAGRAPH_3_IGNORED_ID,
+                EMPTY_CONTAINER_3_0_IGNORED_ID,
+                LINK_3_1_IGNORED_ID,
+                STATIC_TEXT_3_1_0_ID,
+                BUTTON_3_2_ID,
+                EMPTY_CONTAINER_3_3_IGNORED_ID
+            ],
+            ids
+        );
+        let subtree = super::Tree::try_new(update).unwrap();
+        let subtree_reader = subtree.read();
+        assert_eq!(PARAGRAPH_3_IGNORED_ID, subtree_reader.root().id());
+        assert_eq!(
+            ids.len(),
+            subtree_reader.traverse(TraversalOrder::PreOrder).count()
+        );
+        for id in ids {
+            assert_eq!(
+                reader.node_by_id(id).unwrap().data(),
+                subtree_reader.node_by_id(id).unwrap().data()
+            );
+        }
+
+        // The static text is positioned relative to its parent,
+        // which isn't in the subtree.
+        let update = reader.subtree_update(STATIC_TEXT_1_0_ID).unwrap();
+        assert_eq!(
+            None,
+            update.nodes[0].bounds.as_ref().unwrap().offset_container
+        );
+        let subtree = super::Tree::try_new(update).unwrap();
+        assert_eq!(
+            reader.node_by_id(STATIC_TEXT_1_0_ID).unwrap().bounds(),
+            subtree.read().root().bounds()
+        );
+    }
+
+    #[test]
+    fn common_ancestor() {
+        use crate::tests::*;
+        let tree = test_tree();
+        let reader = tree.read();
+        let common_ancestor = |a, b| reader.common_ancestor(a, b).map(|node| node.id());
+        // Siblings and cousins
+        assert_eq!(
+            Some(PARAGRAPH_3_IGNORED_ID),
+            common_ancestor(LINK_3_1_IGNORED_ID, BUTTON_3_2_ID)
+        );
+        assert_eq!(
+            Some(PARAGRAPH_3_IGNORED_ID),
+            common_ancestor(STATIC_TEXT_3_1_0_ID, EMPTY_CONTAINER_3_3_IGNORED_ID)
+        );
+        assert_eq!(
+            Some(ROOT_ID),
+            common_ancestor(STATIC_TEXT_0_0_IGNORED_ID, STATIC_TEXT_3_1_0_ID)
+        );
+        // Ancestor and descendant, in either order
+        assert_eq!(
+            Some(PARAGRAPH_3_IGNORED_ID),
+            common_ancestor(PARAGRAPH_3_IGNORED_ID, STATIC_TEXT_3_1_0_ID)
+        );
+        assert_eq!(
+            Some(PARAGRAPH_3_IGNORED_ID),
+            common_ancestor(STATIC_TEXT_3_1_0_ID, PARAGRAPH_3_IGNORED_ID)
+        );
+        assert_eq!(
+            Some(BUTTON_3_2_ID),
+            common_ancestor(BUTTON_3_2_ID, BUTTON_3_2_ID)
+        );
+        // The root
+        assert_eq!(Some(ROOT_ID), common_ancestor(ROOT_ID, STATIC_TEXT_2_0_ID));
+        assert_eq!(Some(ROOT_ID), common_ancestor(ROOT_ID, ROOT_ID));
+        // Missing nodes
+        let missing = NodeId(NonZeroU64::new(100).unwrap());
+        assert_eq!(None, common_ancestor(missing, ROOT_ID));
+        assert_eq!(None, common_ancestor(BUTTON_3_2_ID, missing));
+    }
+
+    #[test]
+    fn init_tree_with_root_node() {
+        let update = TreeUpdate {
+            nodes: vec![Node::new(NODE_ID_1, Role::Window)],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let tree = super::Tree::new(update);
+        assert_eq!(&TreeId(TREE_ID.into()), tree.read().id());
+        assert_eq!(NODE_ID_1, tree.read().root().id());
+        assert_eq!(Role::Window, tree.read().root().role());
+        assert!(tree.read().root().parent().is_none());
+    }
+
+    #[test]
+    fn from_valid_update() {
+        let tree = super::Tree::from_update(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_2),
+            ..Default::default()
+        })
+        .unwrap();
+        let reader = tree.read();
+        assert_eq!(NODE_ID_1, reader.root().id());
+        assert_eq!(NODE_ID_2, reader.focus().unwrap().id());
+    }
+
+    #[test]
+    fn from_incomplete_update() {
+        let update = |nodes, tree| TreeUpdate {
+            nodes,
+            tree,
+            ..Default::default()
+        };
+        let tree = || {
+            Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            ))
+        };
+        let parent = |children: &[NodeId]| Node {
+            children: children.into(),
+            ..Node::new(NODE_ID_1, Role::Window)
+        };
+        let child = || Node::new(NODE_ID_2, Role::Button);
+        for (update, expected) in [
+            (
+                update(vec![parent(&[])], None),
+                TreeUpdateError::MissingTree,
+            ),
+            (
+                update(vec![child()], tree()),
+                TreeUpdateError::MissingNode(NODE_ID_1),
+            ),
+            (
+                update(vec![parent(&[NODE_ID_2])], tree()),
+                TreeUpdateError::MissingNode(NODE_ID_2),
+            ),
+            (
+                update(vec![parent(&[]), child()], tree()),
+                TreeUpdateError::UnattachedNode(NODE_ID_2),
+            ),
+            (
+                update(vec![parent(&[NODE_ID_2, NODE_ID_2]), child()], tree()),
+                TreeUpdateError::MultipleParents(NODE_ID_2),
+            ),
+            (
+                update(vec![parent(&[NODE_ID_2]), child(), child()], tree()),
+                TreeUpdateError::DuplicateNode(NODE_ID_2),
+            ),
+            (
+                TreeUpdate {
+                    bounds_updates: vec![(NODE_ID_3, None)],
+                    ..update(vec![parent(&[NODE_ID_2]), child()], tree())
+                },
+                TreeUpdateError::MissingNode(NODE_ID_3),
+            ),
+        ] {
+            assert_eq!(Some(expected), super::Tree::try_new(update).err());
+        }
+    }
+
+    #[test]
+    fn invalid_update() {
+        const NODE_ID_4: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        let update = |nodes| TreeUpdate {
+            nodes,
+            ..Default::default()
+        };
+        let node = |id, children: &[NodeId]| Node {
+            children: children.into(),
+            ..Node::new(id, Role::Window)
+        };
+        let tree = super::Tree::try_new(update(vec![
+            node(NODE_ID_1, &[NODE_ID_2, NODE_ID_3]),
+            node(NODE_ID_2, &[]),
+            node(NODE_ID_3, &[]),
+        ]))
+        .err();
+        assert_eq!(Some(TreeUpdateError::MissingTree), tree);
+        let tree = super::Tree::try_new(TreeUpdate {
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..update(vec![
+                node(NODE_ID_1, &[NODE_ID_2, NODE_ID_3]),
+                node(NODE_ID_2, &[]),
+                node(NODE_ID_3, &[]),
+            ])
+        })
+        .unwrap();
+        for (update, expected) in [
+            (
+                TreeUpdate {
+                    tree: Some(Tree::new(
+                        TreeId("other_tree".into()),
+                        NODE_ID_1,
+                        StringEncoding::Utf8,
+                    )),
+                    ..update(vec![])
+                },
+                TreeUpdateError::WrongTree {
+                    expected: TreeId(TREE_ID.into()),
+                    actual: TreeId("other_tree".into()),
+                },
+            ),
+            (
+                TreeUpdate {
+                    clear: Some(NODE_ID_2),
+                    ..update(vec![node(NODE_ID_2, &[])])
+                },
+                TreeUpdateError::UnexpectedClear,
+            ),
+            (
+                update(vec![node(NODE_ID_3, &[NODE_ID_2])]),
+                TreeUpdateError::MultipleParents(NODE_ID_2),
+            ),
+            (
+                update(vec![node(NODE_ID_2, &[NODE_ID_1])]),
+                TreeUpdateError::Cycle(NODE_ID_1),
+            ),
+            (
+                update(vec![node(NODE_ID_1, &[NODE_ID_2, NODE_ID_3, NODE_ID_4])]),
+                TreeUpdateError::MissingNode(NODE_ID_4),
+            ),
+            (
+                TreeUpdate {
+                    focus: Some(NODE_ID_4),
+                    ..update(vec![])
+                },
+                TreeUpdateError::MissingNode(NODE_ID_4),
+            ),
+            (
+                TreeUpdate {
+                    patches: vec![NodePatch::new(NODE_ID_4)],
+                    ..update(vec![])
+                },
+                TreeUpdateError::MissingNode(NODE_ID_4),
+            ),
+            (
+                update(vec![node(NODE_ID_4, &[])]),
+                TreeUpdateError::UnattachedNode(NODE_ID_4),
+            ),
+        ] {
+            assert_eq!(Err(expected), tree.try_update(update));
+            let reader = tree.read();
+            let root = reader.root();
+            let children = root.children().map(|child| child.id());
+            assert_eq!(NODE_ID_1, root.id());
+            assert_eq!(vec![NODE_ID_2, NODE_ID_3], children.collect::<Vec<_>>());
+            assert!(reader.node_by_id(NODE_ID_4).is_none());
+        }
+        assert_eq!(
+            Ok(UpdateStatus::Applied),
+            tree.try_update(update(vec![node(NODE_ID_3, &[])]))
+        );
+    }
+
+    #[test]
+    fn root_node_has_children() {
+        let update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+                Node::new(NODE_ID_3, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
         let tree = super::Tree::new(update);
         let reader = tree.read();
         assert_eq!(
@@ -417,19 +1895,17 @@ mod tests {
     fn add_child_to_root_node() {
         let root_node = Node::new(NODE_ID_1, Role::Window);
         let first_update = TreeUpdate {
-            clear: None,
             nodes: vec![root_node.clone()],
             tree: Some(Tree::new(
                 TreeId(TREE_ID.into()),
                 NODE_ID_1,
                 StringEncoding::Utf8,
             )),
-            focus: None,
+            ..Default::default()
         };
         let tree = super::Tree::new(first_update);
         assert_eq!(0, tree.read().root().children().count());
         let second_update = TreeUpdate {
-            clear: None,
             nodes: vec![
                 Node {
                     children: Box::new([NODE_ID_2]),
@@ -437,13 +1913,15 @@ mod tests {
                 },
                 Node::new(NODE_ID_2, Role::RootWebArea),
             ],
-            tree: None,
-            focus: None,
+            ..Default::default()
         };
         let mut got_updated_root_node = false;
         let mut got_new_child_node = false;
         tree.update_and_process_changes(second_update, |change| {
-            if let super::Change::NodeUpdated { old_node, new_node } = &change {
+            if let super::Change::NodeUpdated {
+                old_node, new_node, ..
+            } = &change
+            {
                 if new_node.id() == NODE_ID_1
                     && old_node.data().children == Box::new([])
                     && new_node.data().children == Box::new([NODE_ID_2])
@@ -475,7 +1953,6 @@ mod tests {
     fn remove_child_from_root_node() {
         let root_node = Node::new(NODE_ID_1, Role::Window);
         let first_update = TreeUpdate {
-            clear: None,
             nodes: vec![
                 Node {
                     children: Box::new([NODE_ID_2]),
@@ -488,20 +1965,21 @@ mod tests {
                 NODE_ID_1,
                 StringEncoding::Utf8,
             )),
-            focus: None,
+            ..Default::default()
         };
         let tree = super::Tree::new(first_update);
         assert_eq!(1, tree.read().root().children().count());
         let second_update = TreeUpdate {
-            clear: None,
             nodes: vec![root_node],
-            tree: None,
-            focus: None,
+            ..Default::default()
         };
         let mut got_updated_root_node = false;
         let mut got_removed_child_node = false;
         tree.update_and_process_changes(second_update, |change| {
-            if let super::Change::NodeUpdated { old_node, new_node } = &change {
+            if let super::Change::NodeUpdated {
+                old_node, new_node, ..
+            } = &change
+            {
                 if new_node.id() == NODE_ID_1
                     && old_node.data().children == Box::new([NODE_ID_2])
                     && new_node.data().children == Box::new([])
@@ -524,10 +2002,66 @@ mod tests {
         assert!(tree.read().node_by_id(NODE_ID_2).is_none());
     }
 
+    #[test]
+    fn node_by_id_across_updates() {
+        let root_node = Node::new(NODE_ID_1, Role::Window);
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..root_node.clone()
+                },
+                Node::new(NODE_ID_2, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+        {
+            let reader = tree.read();
+            assert_eq!(NODE_ID_1, reader.node_by_id(NODE_ID_1).unwrap().id());
+            assert_eq!(Role::Button, reader.node_by_id(NODE_ID_2).unwrap().role());
+            assert!(reader.node_by_id(NODE_ID_3).is_none());
+        }
+
+        // Replace the button with a check box.
+        tree.update_and_process_changes(
+            TreeUpdate {
+                nodes: vec![
+                    Node {
+                        children: Box::new([NODE_ID_3]),
+                        ..root_node.clone()
+                    },
+                    Node::new(NODE_ID_3, Role::CheckBox),
+                ],
+                ..Default::default()
+            },
+            |_| {},
+        );
+        {
+            let reader = tree.read();
+            assert!(reader.node_by_id(NODE_ID_2).is_none());
+            let check_box = reader.node_by_id(NODE_ID_3).unwrap();
+            assert_eq!(Role::CheckBox, check_box.role());
+            assert_eq!(NODE_ID_1, check_box.parent().unwrap().id());
+        }
+
+        tree.update(TreeUpdate {
+            nodes: vec![root_node],
+            ..Default::default()
+        });
+        let reader = tree.read();
+        assert!(reader.node_by_id(NODE_ID_1).is_some());
+        assert!(reader.node_by_id(NODE_ID_2).is_none());
+        assert!(reader.node_by_id(NODE_ID_3).is_none());
+    }
+
     #[test]
     fn move_focus_between_siblings() {
         let first_update = TreeUpdate {
-            clear: None,
             nodes: vec![
                 Node {
                     children: Box::new([NODE_ID_2, NODE_ID_3]),
@@ -542,20 +2076,22 @@ mod tests {
                 StringEncoding::Utf8,
             )),
             focus: Some(NODE_ID_2),
+            ..Default::default()
         };
         let tree = super::Tree::new(first_update);
         assert!(tree.read().node_by_id(NODE_ID_2).unwrap().is_focused());
         let second_update = TreeUpdate {
-            clear: None,
-            nodes: vec![],
-            tree: None,
             focus: Some(NODE_ID_3),
+            ..Default::default()
         };
         let mut got_old_focus_node_update = false;
         let mut got_new_focus_node_update = false;
         let mut got_focus_change = false;
         tree.update_and_process_changes(second_update, |change| {
-            if let super::Change::NodeUpdated { old_node, new_node } = change {
+            if let super::Change::NodeUpdated {
+                old_node, new_node, ..
+            } = change
+            {
                 if old_node.id() == NODE_ID_2
                     && new_node.id() == NODE_ID_2
                     && old_node.is_focused()
@@ -596,7 +2132,6 @@ mod tests {
     fn update_node() {
         let child_node = Node::new(NODE_ID_2, Role::Button);
         let first_update = TreeUpdate {
-            clear: None,
             nodes: vec![
                 Node {
                     children: Box::new([NODE_ID_2]),
@@ -612,7 +2147,7 @@ mod tests {
                 NODE_ID_1,
                 StringEncoding::Utf8,
             )),
-            focus: None,
+            ..Default::default()
         };
         let tree = super::Tree::new(first_update);
         assert_eq!(
@@ -620,17 +2155,18 @@ mod tests {
             tree.read().node_by_id(NODE_ID_2).unwrap().name()
         );
         let second_update = TreeUpdate {
-            clear: None,
             nodes: vec![Node {
                 name: Some("bar".into()),
                 ..child_node
             }],
-            tree: None,
-            focus: None,
+            ..Default::default()
         };
         let mut got_updated_child_node = false;
         tree.update_and_process_changes(second_update, |change| {
-            if let super::Change::NodeUpdated { old_node, new_node } = &change {
+            if let super::Change::NodeUpdated {
+                old_node, new_node, ..
+            } = &change
+            {
                 if new_node.id() == NODE_ID_2
                     && old_node.name() == Some("foo")
                     && new_node.name() == Some("bar")
@@ -648,32 +2184,1120 @@ mod tests {
         );
     }
 
-    // Verify that if an update consists entirely of node data and tree data
-    // that's the same as before, no changes are reported. This would be useful
-    // for a provider that constructs a fresh tree every time, such as
-    // an immediate-mode GUI.
     #[test]
-    fn no_change_update() {
-        let update = TreeUpdate {
-            clear: None,
+    fn serialized_tree_initializes_equal_tree() {
+        let tree = super::Tree::new(TreeUpdate {
             nodes: vec![
                 Node {
-                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    children: Box::new([NODE_ID_3]),
+                    ..Node::new(NODE_ID_2, Role::ScrollView)
+                },
+                Node {
+                    name: Some("OK".into()),
+                    ..Node::new(NODE_ID_3, Role::Button)
+                },
+            ],
+            tree: Some(Tree {
+                root_scroller: Some(NODE_ID_2),
+                ..Tree::new(TreeId(TREE_ID.into()), NODE_ID_1, StringEncoding::Utf8)
+            }),
+            focus: Some(NODE_ID_3),
+            version: Some(7),
+            ..Default::default()
+        });
+        let exported = tree.serialize();
+        let imported = super::Tree::try_new(exported.clone()).unwrap();
+        assert!(diff_summary(&tree, &imported).is_empty());
+        assert!(exported == imported.serialize());
+        let reader = imported.read();
+        assert_eq!(NODE_ID_3, reader.focus().unwrap().id());
+        assert!(reader.node_by_id(NODE_ID_2).unwrap().is_root_scroller());
+    }
+
+    #[test]
+    fn stale_update_is_rejected() {
+        let named_button = |name: &str, version| TreeUpdate {
+            nodes: vec![Node {
+                name: Some(name.into()),
+                ..Node::new(NODE_ID_2, Role::Button)
+            }],
+            version,
+            ..Default::default()
+        };
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
                     ..Node::new(NODE_ID_1, Role::Window)
                 },
                 Node::new(NODE_ID_2, Role::Button),
-                Node::new(NODE_ID_3, Role::Button),
             ],
             tree: Some(Tree::new(
                 TreeId(TREE_ID.into()),
                 NODE_ID_1,
                 StringEncoding::Utf8,
             )),
-            focus: Some(NODE_ID_2),
-        };
-        let tree = super::Tree::new(update.clone());
-        tree.update_and_process_changes(update, |_| {
-            panic!("expected no changes");
+            version: Some(1),
+            ..Default::default()
         });
+        let name = || {
+            tree.read()
+                .node_by_id(NODE_ID_2)
+                .unwrap()
+                .name()
+                .map(String::from)
+        };
+
+        assert_eq!(
+            UpdateStatus::Applied,
+            tree.update(named_button("third", Some(3)))
+        );
+        assert_eq!(Some("third".into()), name());
+
+        // Version 2 arrives late, and the same version can't be applied twice.
+        for version in [2, 3] {
+            assert_eq!(
+                UpdateStatus::Stale {
+                    version,
+                    last_applied: 3
+                },
+                tree.update_and_process_changes(named_button("stale", Some(version)), |_| {
+                    panic!("expected no changes");
+                })
+            );
+            assert_eq!(Some("third".into()), name());
+        }
+
+        // Updates without a version are always applied, and don't reset
+        // the last applied version.
+        assert_eq!(
+            UpdateStatus::Applied,
+            tree.update(named_button("unversioned", None))
+        );
+        assert_eq!(Some("unversioned".into()), name());
+        assert_eq!(Some(3), tree.serialize().version);
+        assert_eq!(
+            UpdateStatus::Applied,
+            tree.update(named_button("fourth", Some(4)))
+        );
+        assert_eq!(Some("fourth".into()), name());
+    }
+
+    #[test]
+    fn collect_changes() {
+        const NODE_ID_4: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        let first_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+                Node::new(NODE_ID_3, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let tree = super::Tree::new(first_update);
+        let second_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_4]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("renamed".into()),
+                    ..Node::new(NODE_ID_2, Role::Button)
+                },
+                Node::new(NODE_ID_4, Role::Button),
+            ],
+            focus: Some(NODE_ID_4),
+            ..Default::default()
+        };
+        let changes = tree.update_and_collect_changes(second_update);
+        assert_eq!(5, changes.len());
+        assert_eq!(ChangeRecord::NodeAdded(NODE_ID_4), changes[0]);
+        let updated = &changes[1..3];
+        assert!(updated.contains(&ChangeRecord::NodeUpdated(NODE_ID_1)));
+        assert!(updated.contains(&ChangeRecord::NodeUpdated(NODE_ID_2)));
+        assert_eq!(
+            ChangeRecord::FocusMoved {
+                old_node: None,
+                new_node: Some(NODE_ID_4),
+            },
+            changes[3]
+        );
+        assert_eq!(ChangeRecord::NodeRemoved(NODE_ID_3), changes[4]);
+    }
+
+    #[test]
+    fn active_descendant_changes() {
+        let combo_box = |active_descendant: Option<NodeId>| Node {
+            children: Box::new([NODE_ID_2, NODE_ID_3]),
+            active_descendant,
+            ..Node::new(NODE_ID_1, Role::ComboBoxGrouping)
+        };
+        let update = |active_descendant: Option<NodeId>| TreeUpdate {
+            nodes: vec![combo_box(active_descendant)],
+            ..Default::default()
+        };
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                combo_box(None),
+                Node::new(NODE_ID_2, Role::ListBoxOption),
+                Node::new(NODE_ID_3, Role::ListBoxOption),
+            ],
+            tree: Some(Tree::new(
+                TreeId("test_tree".into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+
+        let changes = tree.update_and_collect_changes(update(Some(NODE_ID_2)));
+        assert_eq!(
+            vec![
+                ChangeRecord::NodeUpdated(NODE_ID_1),
+                ChangeRecord::ActiveDescendantChanged {
+                    container: NODE_ID_1,
+                    old: None,
+                    new: Some(NODE_ID_2),
+                },
+            ],
+            changes
+        );
+
+        let changes = tree.update_and_collect_changes(update(Some(NODE_ID_3)));
+        assert_eq!(
+            Some(&ChangeRecord::ActiveDescendantChanged {
+                container: NODE_ID_1,
+                old: Some(NODE_ID_2),
+                new: Some(NODE_ID_3),
+            }),
+            changes.last()
+        );
+
+        let changes = tree.update_and_collect_changes(update(None));
+        assert_eq!(
+            Some(&ChangeRecord::ActiveDescendantChanged {
+                container: NODE_ID_1,
+                old: Some(NODE_ID_3),
+                new: None,
+            }),
+            changes.last()
+        );
+
+        // An update that leaves the active descendant alone
+        // doesn't report a change to it.
+        let changes = tree.update_and_collect_changes(update(None));
+        assert!(!changes
+            .iter()
+            .any(|change| matches!(change, ChangeRecord::ActiveDescendantChanged { .. })));
+    }
+
+    #[test]
+    fn focus_moves_after_updates() {
+        let slider = |id, value| Node {
+            value_for_range: Some(value),
+            focusable: true,
+            ..Node::new(id, Role::Slider)
+        };
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                slider(NODE_ID_2, 0.0),
+                slider(NODE_ID_3, 0.0),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_2),
+            ..Default::default()
+        });
+        let changes = tree.update_and_collect_changes(TreeUpdate {
+            nodes: vec![slider(NODE_ID_3, 50.0)],
+            focus: Some(NODE_ID_3),
+            ..Default::default()
+        });
+        assert_eq!(
+            vec![
+                ChangeRecord::NodeUpdated(NODE_ID_3),
+                ChangeRecord::NodeUpdated(NODE_ID_2),
+                ChangeRecord::FocusMoved {
+                    old_node: Some(NODE_ID_2),
+                    new_node: Some(NODE_ID_3),
+                },
+            ],
+            changes
+        );
+    }
+
+    #[test]
+    fn dirty_flags() {
+        let bounds = |top| RelativeBounds {
+            offset_container: None,
+            rect: Rect {
+                left: 0.0,
+                top,
+                width: 100.0,
+                height: 20.0,
+            },
+            transform: None,
+        };
+        let slider = |value, top| Node {
+            name: Some("Volume".into()),
+            value_for_range: Some(value),
+            bounds: Some(bounds(top)),
+            ..Node::new(NODE_ID_2, Role::Slider)
+        };
+        let update = |node| TreeUpdate {
+            nodes: vec![node],
+            ..Default::default()
+        };
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                slider(50.0, 0.0),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+        let dirty_flags = |update| {
+            let mut flags = Vec::new();
+            tree.update_and_process_changes(update, |change| {
+                if let super::Change::NodeUpdated { dirty, .. } = change {
+                    flags.push(dirty);
+                }
+            });
+            flags
+        };
+
+        assert_eq!(
+            vec![super::DirtyFlags {
+                value: true,
+                ..Default::default()
+            }],
+            dirty_flags(update(slider(60.0, 0.0)))
+        );
+        assert_eq!(
+            vec![super::DirtyFlags {
+                bounds: true,
+                ..Default::default()
+            }],
+            dirty_flags(update(slider(60.0, 20.0)))
+        );
+        let flags = dirty_flags(update(Node {
+            labelled_by: Box::new([NODE_ID_1]),
+            ..Node::new(NODE_ID_2, Role::Button)
+        }));
+        assert_eq!(
+            vec![super::DirtyFlags {
+                name: true,
+                value: true,
+                bounds: true,
+                relationships: true,
+                other: true,
+                ..Default::default()
+            }],
+            flags
+        );
+        assert!(super::DirtyFlags::default().is_empty());
+        assert!(!flags[0].is_empty());
+    }
+
+    #[test]
+    fn bounds_updates() {
+        let bounds = |top| RelativeBounds {
+            offset_container: None,
+            rect: Rect {
+                left: 0.0,
+                top,
+                width: 100.0,
+                height: 20.0,
+            },
+            transform: None,
+        };
+        let update = |bounds_updates| TreeUpdate {
+            bounds_updates,
+            ..Default::default()
+        };
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("First".into()),
+                    bounds: Some(bounds(0.0)),
+                    ..Node::new(NODE_ID_2, Role::Button)
+                },
+                Node {
+                    name: Some("Second".into()),
+                    bounds: Some(bounds(20.0)),
+                    ..Node::new(NODE_ID_3, Role::Button)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+        let before = tree.serialize();
+
+        let changes = tree.update_and_collect_changes(update(vec![
+            (NODE_ID_2, Some(bounds(-50.0))),
+            (NODE_ID_3, Some(bounds(20.0))),
+        ]));
+        assert_eq!(vec![ChangeRecord::BoundsChanged(NODE_ID_2)], changes);
+        let after = tree.serialize();
+        assert!(after.eq_ignoring_bounds(&before));
+        assert_eq!(Some(bounds(-50.0)), after.nodes[1].bounds);
+        assert_eq!(Some(bounds(20.0)), after.nodes[2].bounds);
+
+        let changes = tree.update_and_collect_changes(update(vec![(NODE_ID_3, None)]));
+        assert_eq!(vec![ChangeRecord::BoundsChanged(NODE_ID_3)], changes);
+        assert_eq!(
+            None,
+            tree.read().node_by_id(NODE_ID_3).unwrap().data().bounds
+        );
+
+        let missing = NodeId(NonZeroU64::new(4).unwrap());
+        assert_eq!(
+            Err(TreeUpdateError::MissingNode(missing)),
+            tree.try_update(update(vec![(missing, None)]))
+        );
+    }
+
+    #[derive(Default)]
+    struct RecordingSink(Vec<String>);
+
+    impl EventSink for RecordingSink {
+        fn on_node_added(&mut self, node: crate::Node) {
+            self.0.push(format!("added {:?}", node.role()));
+        }
+
+        fn on_node_updated(
+            &mut self,
+            old_node: crate::Node,
+            new_node: crate::Node,
+            dirty: crate::DirtyFlags,
+        ) {
+            self.0.push(format!(
+                "updated {:?} -> {:?}, children changed: {}",
+                old_node.name(),
+                new_node.name(),
+                dirty.children
+            ));
+        }
+
+        fn on_focus_moved(&mut self, old_node: Option<crate::Node>, new_node: Option<crate::Node>) {
+            self.0.push(format!(
+                "focus {:?} -> {:?}",
+                old_node.map(|node| node.role()),
+                new_node.map(|node| node.role())
+            ));
+        }
+
+        fn on_node_removed(&mut self, node: crate::Node) {
+            self.0.push(format!("removed {:?}", node.role()));
+        }
+    }
+
+    #[test]
+    fn event_sink_receives_changes_in_order() {
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("old".into()),
+                    ..Node::new(NODE_ID_2, Role::Button)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_2),
+            ..Default::default()
+        });
+        // Replace the button with a check box, and focus the check box.
+        let mut sink = RecordingSink::default();
+        let status = tree.update_with_sink(
+            TreeUpdate {
+                nodes: vec![
+                    Node {
+                        children: Box::new([NODE_ID_3]),
+                        ..Node::new(NODE_ID_1, Role::Window)
+                    },
+                    Node::new(NODE_ID_3, Role::CheckBox),
+                ],
+                focus: Some(NODE_ID_3),
+                ..Default::default()
+            },
+            &mut sink,
+        );
+        assert_eq!(UpdateStatus::Applied, status);
+        assert_eq!(
+            vec![
+                "added CheckBox",
+                "updated None -> None, children changed: true",
+                "focus Some(Button) -> Some(CheckBox)",
+                "removed Button",
+            ],
+            sink.0
+        );
+    }
+
+    #[test]
+    fn invalid_update_reports_no_changes() {
+        let tree = super::Tree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+        let invalid_update = || TreeUpdate {
+            nodes: vec![Node {
+                children: Box::new([NODE_ID_3]),
+                ..Node::new(NODE_ID_1, Role::Window)
+            }],
+            ..Default::default()
+        };
+        let mut change_count = 0;
+        assert_eq!(
+            Err(TreeUpdateError::MissingNode(NODE_ID_3)),
+            tree.try_update_and_process_changes(invalid_update(), |_| change_count += 1)
+        );
+        assert_eq!(0, change_count);
+        let mut sink = RecordingSink::default();
+        assert_eq!(
+            Err(TreeUpdateError::MissingNode(NODE_ID_3)),
+            tree.try_update_with_sink(invalid_update(), &mut sink)
+        );
+        assert!(sink.0.is_empty());
+        let reader = tree.read();
+        let children = reader.root().children().map(|child| child.id());
+        assert_eq!(vec![NODE_ID_2], children.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn patch_matches_full_update() {
+        let first_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("Loading".into()),
+                    value_for_range: Some(10.0),
+                    ..Node::new(NODE_ID_2, Role::ProgressIndicator)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let full_tree = super::Tree::new(first_update.clone());
+        let full_changes = full_tree.update_and_collect_changes(TreeUpdate {
+            nodes: vec![Node {
+                value_for_range: Some(50.0),
+                busy: true,
+                ..Node::new(NODE_ID_2, Role::ProgressIndicator)
+            }],
+            ..Default::default()
+        });
+        let patched_tree = super::Tree::new(first_update);
+        let patched_changes = patched_tree.update_and_collect_changes(TreeUpdate {
+            patches: vec![NodePatch {
+                id: NODE_ID_2,
+                set: vec![NodeAttribute::ValueForRange(50.0), NodeAttribute::Busy],
+                remove: vec![NodeAttributeKind::Name],
+                unknown_attributes: vec![],
+            }],
+            ..Default::default()
+        });
+        assert_eq!(vec![ChangeRecord::NodeUpdated(NODE_ID_2)], patched_changes);
+        assert_eq!(full_changes, patched_changes);
+        assert_eq!(full_tree.serialize().nodes, patched_tree.serialize().nodes);
+    }
+
+    #[test]
+    fn merged_update_matches_sequential_updates() {
+        const NODE_ID_4: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+        let initial_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            version: Some(1),
+            ..Default::default()
+        };
+        // Adds two nodes, renames the existing button with a patch,
+        // and focuses one of the new nodes.
+        let first_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    children: Box::new([NODE_ID_4]),
+                    ..Node::new(NODE_ID_3, Role::Group)
+                },
+                Node {
+                    name: Some("New".into()),
+                    ..Node::new(NODE_ID_4, Role::Button)
+                },
+            ],
+            focus: Some(NODE_ID_4),
+            patches: vec![NodePatch {
+                set: vec![NodeAttribute::Name("Patched".into())],
+                ..NodePatch::new(NODE_ID_2)
+            }],
+            version: Some(2),
+            ..Default::default()
+        };
+        // Removes the new nodes again, replaces the existing button,
+        // and moves the focus to it.
+        let second_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("Replaced".into()),
+                    ..Node::new(NODE_ID_2, Role::Button)
+                },
+            ],
+            focus: Some(NODE_ID_2),
+            version: Some(3),
+            ..Default::default()
+        };
+
+        let sequential_tree = super::Tree::new(initial_update.clone());
+        sequential_tree.update(first_update.clone());
+        sequential_tree.update(second_update.clone());
+
+        let mut merged_update = first_update;
+        assert!(merged_update.merge(second_update).is_ok());
+        assert_eq!(
+            vec![NODE_ID_1, NODE_ID_2],
+            merged_update
+                .nodes
+                .iter()
+                .map(|node| node.id)
+                .collect::<Vec<_>>()
+        );
+        assert!(merged_update.patches.is_empty());
+        assert_eq!(Some(3), merged_update.version);
+        let merged_tree = super::Tree::new(initial_update);
+        assert_eq!(UpdateStatus::Applied, merged_tree.update(merged_update));
+
+        assert!(diff_summary(&sequential_tree, &merged_tree).is_empty());
+        assert!(sequential_tree.serialize() == merged_tree.serialize());
+    }
+
+    #[test]
+    fn merge_keeps_patches_for_nodes_not_updated_again() {
+        let initial_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+                Node::new(NODE_ID_3, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let first_update = TreeUpdate {
+            patches: vec![NodePatch {
+                set: vec![NodeAttribute::Name("First".into())],
+                ..NodePatch::new(NODE_ID_2)
+            }],
+            ..Default::default()
+        };
+        let second_update = TreeUpdate {
+            patches: vec![
+                NodePatch {
+                    set: vec![NodeAttribute::Name("Second".into())],
+                    ..NodePatch::new(NODE_ID_2)
+                },
+                NodePatch {
+                    remove: vec![NodeAttributeKind::Name],
+                    set: vec![NodeAttribute::Disabled],
+                    ..NodePatch::new(NODE_ID_3)
+                },
+            ],
+            ..Default::default()
+        };
+
+        let sequential_tree = super::Tree::new(initial_update.clone());
+        sequential_tree.update(first_update.clone());
+        sequential_tree.update(second_update.clone());
+
+        let mut merged_update = first_update;
+        assert!(merged_update.merge(second_update).is_ok());
+        assert_eq!(3, merged_update.patches.len());
+        let merged_tree = super::Tree::new(initial_update);
+        merged_tree.update(merged_update);
+
+        assert!(sequential_tree.serialize() == merged_tree.serialize());
+        let reader = merged_tree.read();
+        assert_eq!(Some("Second"), reader.node_by_id(NODE_ID_2).unwrap().name());
+    }
+
+    #[test]
+    fn replace_root() {
+        let first_update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let tree = super::Tree::new(first_update);
+        let second_update = TreeUpdate {
+            nodes: vec![Node::new(NODE_ID_3, Role::Window)],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_3,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let changes = tree.update_and_collect_changes(second_update);
+        let root_changes: Vec<_> = changes
+            .iter()
+            .filter(|change| matches!(change, ChangeRecord::RootChanged { .. }))
+            .collect();
+        assert_eq!(
+            vec![&ChangeRecord::RootChanged {
+                old_node: NODE_ID_1,
+                new_node: NODE_ID_3,
+            }],
+            root_changes
+        );
+        assert!(changes.contains(&ChangeRecord::NodeAdded(NODE_ID_3)));
+        assert!(changes.contains(&ChangeRecord::NodeRemoved(NODE_ID_1)));
+        assert!(changes.contains(&ChangeRecord::NodeRemoved(NODE_ID_2)));
+        assert_eq!(NODE_ID_3, tree.read().root().id());
+    }
+
+    #[test]
+    fn visible_nodes_in_scrolled_list() {
+        const LIST_ID: NodeId = NODE_ID_2;
+        const ITEM_COUNT: u64 = 100;
+        const ITEM_HEIGHT: f32 = 20.0;
+        let item_id = |i: u64| NodeId(NonZeroU64::new(i + 3).unwrap());
+        let bounds = |offset_container, top, height| RelativeBounds {
+            offset_container,
+            rect: Rect {
+                left: 0.0,
+                top,
+                width: 100.0,
+                height,
+            },
+            transform: None,
+        };
+        let mut nodes = vec![
+            Node {
+                children: Box::new([LIST_ID]),
+                ..Node::new(NODE_ID_1, Role::Window)
+            },
+            Node {
+                bounds: Some(bounds(None, 0.0, 100.0)),
+                children: (0..ITEM_COUNT).map(item_id).collect(),
+                clips_children: true,
+                scroll_y: Some(500.0),
+                ..Node::new(LIST_ID, Role::List)
+            },
+        ];
+        nodes.extend((0..ITEM_COUNT).map(|i| Node {
+            bounds: Some(bounds(Some(LIST_ID), i as f32 * ITEM_HEIGHT, ITEM_HEIGHT)),
+            ..Node::new(item_id(i), Role::ListItem)
+        }));
+        let tree = super::Tree::new(TreeUpdate {
+            nodes,
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        });
+        let reader = tree.read();
+        let viewport = Rect {
+            left: 0.0,
+            top: 0.0,
+            width: 800.0,
+            height: 600.0,
+        };
+        // The list is scrolled by 500 pixels, so items 25 through 29 are
+        // within its bounds. Item 30 only touches the bottom edge.
+        let mut expected = vec![LIST_ID];
+        expected.extend((25..30).map(item_id));
+        assert_eq!(
+            expected,
+            reader
+                .visible_nodes(viewport)
+                .map(|node| node.id())
+                .collect::<Vec<_>>()
+        );
+
+        // A viewport that only covers the top of the list.
+        let viewport = Rect {
+            height: 30.0,
+            ..viewport
+        };
+        assert_eq!(
+            vec![LIST_ID, item_id(25), item_id(26)],
+            reader
+                .visible_nodes(viewport)
+                .map(|node| node.id())
+                .collect::<Vec<_>>()
+        );
+
+        // A viewport that doesn't cover the list at all.
+        let viewport = Rect {
+            top: 200.0,
+            ..viewport
+        };
+        assert_eq!(0, reader.visible_nodes(viewport).count());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshot_json() {
+        use accesskit_schema::CheckedState;
+
+        let update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    name: Some("Dialog \"1\"".into()),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("OK".into()),
+                    focusable: true,
+                    ..Node::new(NODE_ID_2, Role::Button)
+                },
+                Node {
+                    value_for_range: Some(0.5),
+                    checked_state: Some(CheckedState::Mixed),
+                    ..Node::new(NODE_ID_3, Role::CheckBox)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let tree = super::Tree::new(update);
+        let expected = r#"{
+  "children": [
+    {
+      "focusable": true,
+      "id": 2,
+      "name": "OK",
+      "role": "button"
+    },
+    {
+      "checkedState": "mixed",
+      "id": 3,
+      "role": "checkBox",
+      "valueForRange": 0.5
+    }
+  ],
+  "id": 1,
+  "name": "Dialog \"1\"",
+  "role": "window"
+}
+"#;
+        assert_eq!(expected, tree.read().to_snapshot_json());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn snapshot_json_is_deterministic() {
+        let nodes = vec![
+            Node {
+                children: Box::new([NODE_ID_2, NODE_ID_3]),
+                ..Node::new(NODE_ID_1, Role::Window)
+            },
+            Node {
+                name: Some("Name".into()),
+                description: Some("Description".into()),
+                selected: Some(true),
+                ..Node::new(NODE_ID_2, Role::ListItem)
+            },
+            Node {
+                labelled_by: Box::new([NODE_ID_2]),
+                ..Node::new(NODE_ID_3, Role::TextField)
+            },
+        ];
+        let tree_from = |nodes| {
+            super::Tree::new(TreeUpdate {
+                nodes,
+                tree: Some(Tree::new(
+                    TreeId(TREE_ID.into()),
+                    NODE_ID_1,
+                    StringEncoding::Utf8,
+                )),
+                ..Default::default()
+            })
+        };
+        let first = tree_from(nodes.clone());
+        let second = tree_from(nodes.into_iter().rev().collect());
+        assert_eq!(
+            first.read().to_snapshot_json(),
+            second.read().to_snapshot_json()
+        );
+        assert_eq!(
+            first.read().to_snapshot_json(),
+            first.read().to_snapshot_json()
+        );
+    }
+
+    #[test]
+    fn snapshot_json_ignores_patch_order() {
+        let attributes = vec![
+            NodeAttribute::Name("Name".into()),
+            NodeAttribute::Busy,
+            NodeAttribute::ValueForRange(50.0),
+        ];
+        let patched_tree = |set: Vec<NodeAttribute>| {
+            super::Tree::new(TreeUpdate {
+                nodes: vec![
+                    Node {
+                        children: Box::new([NODE_ID_2]),
+                        ..Node::new(NODE_ID_1, Role::Window)
+                    },
+                    Node::new(NODE_ID_2, Role::ProgressIndicator),
+                ],
+                tree: Some(Tree::new(
+                    TreeId(TREE_ID.into()),
+                    NODE_ID_1,
+                    StringEncoding::Utf8,
+                )),
+                patches: vec![NodePatch {
+                    set,
+                    ..NodePatch::new(NODE_ID_2)
+                }],
+                ..Default::default()
+            })
+        };
+        let forward = patched_tree(attributes.clone());
+        let reverse = patched_tree(attributes.into_iter().rev().collect());
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            forward.read().to_snapshot_json(),
+            reverse.read().to_snapshot_json()
+        );
+        assert!(diff_summary(&forward, &reverse).is_empty());
+    }
+
+    // Verify that if an update consists entirely of node data and tree data
+    // that's the same as before, no changes are reported. This would be useful
+    // for a provider that constructs a fresh tree every time, such as
+    // an immediate-mode GUI.
+    #[test]
+    fn no_change_update() {
+        let update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2, NODE_ID_3]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node::new(NODE_ID_2, Role::Button),
+                Node::new(NODE_ID_3, Role::Button),
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_2),
+            ..Default::default()
+        };
+        let tree = super::Tree::new(update.clone());
+        tree.update_and_process_changes(update, |_| {
+            panic!("expected no changes");
+        });
+    }
+
+    #[test]
+    fn snapshot() {
+        let update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([NODE_ID_2]),
+                    ..Node::new(NODE_ID_1, Role::Window)
+                },
+                Node {
+                    name: Some("OK".into()),
+                    ..Node::new(NODE_ID_2, Role::Button)
+                },
+            ],
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            focus: Some(NODE_ID_2),
+            ..Default::default()
+        };
+        let tree = super::Tree::new(update);
+        let snapshot = tree.read().snapshot();
+        tree.update(TreeUpdate {
+            nodes: vec![Node {
+                name: Some("Cancel".into()),
+                ..Node::new(NODE_ID_2, Role::Button)
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            Some("Cancel"),
+            tree.read().node_by_id(NODE_ID_2).unwrap().name()
+        );
+
+        // Hold the live tree's lock for writing, so the threads can only
+        // finish if reading the snapshot doesn't need it.
+        let state = tree.state.write();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let snapshot = snapshot.clone();
+                std::thread::spawn(move || {
+                    let reader = snapshot.read();
+                    let button = reader.root().children().next().unwrap();
+                    assert_eq!(NODE_ID_2, button.id());
+                    assert_eq!(Some("OK"), button.name());
+                    assert_eq!(Some(NODE_ID_2), reader.focus().map(|node| node.id()));
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        drop(state);
+    }
+
+    #[test]
+    fn diff_summary_lists_each_difference() {
+        let tree_update = |nodes| TreeUpdate {
+            nodes,
+            tree: Some(Tree::new(
+                TreeId(TREE_ID.into()),
+                NODE_ID_1,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        };
+        let old = super::Tree::new(tree_update(vec![
+            Node {
+                children: Box::new([NODE_ID_2]),
+                ..Node::new(NODE_ID_1, Role::Window)
+            },
+            Node {
+                name: Some("OK".into()),
+                ..Node::new(NODE_ID_2, Role::Button)
+            },
+        ]));
+        let new = super::Tree::new(tree_update(vec![
+            Node {
+                children: Box::new([NODE_ID_2, NODE_ID_3]),
+                ..Node::new(NODE_ID_1, Role::Window)
+            },
+            Node {
+                name: Some("Cancel".into()),
+                ..Node::new(NODE_ID_2, Role::Button)
+            },
+            Node::new(NODE_ID_3, Role::Button),
+        ]));
+        assert_eq!(
+            vec![
+                NodeDiff::ChildrenChanged {
+                    id: NODE_ID_1,
+                    old: Box::new([NODE_ID_2]),
+                    new: Box::new([NODE_ID_2, NODE_ID_3]),
+                },
+                NodeDiff::AttributeChanged {
+                    id: NODE_ID_2,
+                    kind: NodeAttributeKind::Name,
+                    old: Some(NodeAttribute::Name("OK".into())),
+                    new: Some(NodeAttribute::Name("Cancel".into())),
+                },
+                NodeDiff::Added(NODE_ID_3),
+            ],
+            diff_summary(&old, &new)
+        );
+        assert!(diff_summary(&new, &new).is_empty());
     }
 }
@@ -83,14 +83,13 @@ fn get_initial_state() -> TreeUpdate {
     let button_1 = make_button(BUTTON_1_ID, "Button 1");
     let button_2 = make_button(BUTTON_2_ID, "Button 2");
     TreeUpdate {
-        clear: None,
         nodes: vec![root, button_1, button_2],
         tree: Some(Tree::new(
             TreeId("test".into()),
             WINDOW_ID,
             StringEncoding::Utf8,
         )),
-        focus: None,
+        ..Default::default()
     }
 }
 
@@ -106,10 +105,8 @@ unsafe fn get_window_state(window: HWND) -> *const WindowState {
 fn update_focus(window: HWND, is_window_focused: bool) {
     let window_state = unsafe { &*get_window_state(window) };
     let update = TreeUpdate {
-        clear: None,
-        nodes: vec![],
-        tree: None,
         focus: is_window_focused.then(|| window_state.focus.get()),
+        ..Default::default()
     };
     window_state.manager.update(update);
 }
@@ -187,10 +184,9 @@ extern "system" fn wndproc(window: HWND, message: u32, wparam: WPARAM, lparam: L
                     make_button(BUTTON_2_ID, "You pressed button 2")
                 };
                 let update = TreeUpdate {
-                    clear: None,
                     nodes: vec![node],
-                    tree: None,
                     focus: Some(focus),
+                    ..Default::default()
                 };
                 window_state.manager.update(update);
                 LRESULT(0)
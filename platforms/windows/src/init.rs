@@ -0,0 +1,34 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use accesskit_schema::TreeUpdate;
+
+/// Provides the initial state of the tree for a [`Manager`](crate::Manager).
+/// The manager doesn't build the tree until it's first needed, typically
+/// when an assistive technology first sends `WM_GETOBJECT`, so an
+/// implementation can defer the work of describing the UI until then.
+pub trait InitTree: Send {
+    fn init_tree(self: Box<Self>) -> TreeUpdate;
+}
+
+impl InitTree for TreeUpdate {
+    fn init_tree(self: Box<Self>) -> TreeUpdate {
+        *self
+    }
+}
+
+/// Wraps a closure that builds the initial tree, e.g.
+/// `Manager::new(hwnd, FnInitTree(|| build_tree()))`.
+/// The closure is called at most once.
+pub struct FnInitTree<F>(pub F);
+
+impl<F> InitTree for FnInitTree<F>
+where
+    F: FnOnce() -> TreeUpdate + Send,
+{
+    fn init_tree(self: Box<Self>) -> TreeUpdate {
+        (self.0)()
+    }
+}
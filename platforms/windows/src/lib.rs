@@ -4,8 +4,15 @@
 // the LICENSE-MIT file), at your option.
 
 mod node;
+mod text;
 mod util;
 
+mod init;
+pub use init::{FnInitTree, InitTree};
+
+mod localizer;
+pub use localizer::Localizer;
+
 mod manager;
 pub use manager::Manager;
 
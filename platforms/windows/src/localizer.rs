@@ -0,0 +1,40 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use accesskit_schema::{CheckedState, DefaultActionVerb, Role};
+
+/// Provides localized strings for properties that the application
+/// didn't describe itself. Descriptions provided by the application,
+/// such as [`accesskit_schema::Node::role_description`], always take
+/// precedence. Each method returns `None` by default, which leaves
+/// the property to UIA's own defaults.
+pub trait Localizer: Send + Sync {
+    /// Used for `UIA_LocalizedControlTypePropertyId` when the node has
+    /// no role description. If this returns `None`, UIA derives
+    /// the localized control type from the control type, except for
+    /// [`Role::Meter`], which is described in English as "meter"
+    /// to distinguish it from a progress bar.
+    fn role_description(&self, _role: Role) -> Option<String> {
+        None
+    }
+
+    /// Used for `UIA_ItemStatusPropertyId` when the node has a checked
+    /// state but no checked state description. If this returns `None`,
+    /// the item status is empty.
+    fn checked_state_description(&self, _state: CheckedState) -> Option<String> {
+        None
+    }
+
+    /// Used for `UIA_LegacyIAccessibleDefaultActionPropertyId`. If this
+    /// returns `None`, the English name from
+    /// [`accesskit_schema::default_action_verb_string`] is used.
+    fn default_action_verb(&self, _verb: DefaultActionVerb) -> Option<String> {
+        None
+    }
+}
+
+pub(crate) struct DefaultLocalizer;
+
+impl Localizer for DefaultLocalizer {}
@@ -3,21 +3,81 @@
 // the LICENSE-APACHE file) or the MIT license (found in
 // the LICENSE-MIT file), at your option.
 
-use std::sync::Arc;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
 
-use accesskit_consumer::{Tree, TreeChange};
-use accesskit_schema::TreeUpdate;
+use accesskit_consumer::{DirtyFlags, EventSink, Node, Tree, TreeUpdateError, UpdateStatus};
+use accesskit_schema::{ActionHandler, TreeUpdate};
 use windows::Win32::{Foundation::*, UI::Accessibility::*};
 
-use crate::node::{PlatformNode, ResolvedPlatformNode};
+use crate::{
+    init::InitTree,
+    localizer::{DefaultLocalizer, Localizer},
+    node::{PlatformNode, ResolvedPlatformNode},
+    util::ScaleFactor,
+};
+
+// The tree isn't built until it's first needed.
+enum TreeState {
+    Pending(
+        Box<dyn InitTree>,
+        Option<Box<dyn ActionHandler + Send + Sync>>,
+    ),
+    Initialized(Arc<Tree>),
+}
 
 pub struct Manager {
     hwnd: HWND,
-    tree: Arc<Tree>,
+    tree: Mutex<Option<TreeState>>,
+    queued_updates: Mutex<VecDeque<TreeUpdate>>,
+    localizer: Arc<dyn Localizer>,
+    scale_factor: Arc<ScaleFactor>,
 }
 
 impl Manager {
-    pub fn new(hwnd: HWND, initial_state: TreeUpdate) -> Self {
+    /// Creates a manager whose tree is initialized from `initial_state`,
+    /// which may be a [`TreeUpdate`] or an [`InitTree`] implementation
+    /// that builds one when the tree is first needed. Action requests
+    /// from assistive technologies are ignored; use
+    /// [`Manager::with_action_handler`] to handle them.
+    pub fn new(hwnd: HWND, initial_state: impl InitTree + 'static) -> Self {
+        Self::new_internal(hwnd, initial_state, None, Box::new(DefaultLocalizer))
+    }
+
+    /// Creates a manager that passes action requests from assistive
+    /// technologies to `action_handler`.
+    pub fn with_action_handler(
+        hwnd: HWND,
+        initial_state: impl InitTree + 'static,
+        action_handler: Box<dyn ActionHandler + Send + Sync>,
+    ) -> Self {
+        Self::new_internal(
+            hwnd,
+            initial_state,
+            Some(action_handler),
+            Box::new(DefaultLocalizer),
+        )
+    }
+
+    /// Creates a manager that uses the given localizer for descriptions
+    /// that the application doesn't provide in the tree.
+    pub fn with_localizer(
+        hwnd: HWND,
+        initial_state: impl InitTree + 'static,
+        action_handler: Box<dyn ActionHandler + Send + Sync>,
+        localizer: Box<dyn Localizer>,
+    ) -> Self {
+        Self::new_internal(hwnd, initial_state, Some(action_handler), localizer)
+    }
+
+    fn new_internal(
+        hwnd: HWND,
+        initial_state: impl InitTree + 'static,
+        action_handler: Option<Box<dyn ActionHandler + Send + Sync>>,
+        localizer: Box<dyn Localizer>,
+    ) -> Self {
         // It's unfortunate that we have to force UIA to initialize early;
         // it would be more optimal to let UIA lazily initialize itself
         // when we receive the first `WM_GETOBJECT`. But if we don't do this,
@@ -28,37 +88,120 @@ impl Manager {
 
         Self {
             hwnd,
-            tree: Tree::new(initial_state),
+            tree: Mutex::new(Some(TreeState::Pending(
+                Box::new(initial_state),
+                action_handler,
+            ))),
+            queued_updates: Mutex::new(VecDeque::new()),
+            localizer: localizer.into(),
+            scale_factor: Arc::new(ScaleFactor::new(1.0)),
+        }
+    }
+
+    /// Sets the factor by which the bounds in the tree, which are
+    /// in logical pixels, are multiplied to get the physical pixels that
+    /// UIA reports, e.g. 1.5 for a window at 144 DPI. The default is 1.
+    /// An application that's aware of per-monitor DPI should call this
+    /// again when it receives `WM_DPICHANGED`. Like
+    /// [`Manager::queue_update`], this may be called from any thread.
+    pub fn set_scale_factor(&self, factor: f32) {
+        self.scale_factor.set(factor);
+    }
+
+    /// Returns the factor set by [`Manager::set_scale_factor`].
+    pub fn scale_factor(&self) -> f32 {
+        self.scale_factor.get()
+    }
+
+    /// Queues an update to be applied by [`Manager::process_queued`].
+    /// Unlike [`Manager::update`], this may be called from any thread,
+    /// e.g. a render thread that produces tree updates.
+    ///
+    /// This doesn't wake up the UI thread. The caller is responsible for
+    /// arranging for `process_queued` to be called on the UI thread,
+    /// typically by posting a message to the window.
+    pub fn queue_update(&self, update: TreeUpdate) {
+        self.queued_updates.lock().unwrap().push_back(update);
+    }
+
+    /// Applies all queued updates, in the order in which they were queued,
+    /// and raises the corresponding UIA events. Like [`Manager::update`],
+    /// this must be called on the thread that owns the window.
+    pub fn process_queued(&self) {
+        let updates = std::mem::take(&mut *self.queued_updates.lock().unwrap());
+        for update in updates {
+            self.update(update);
         }
     }
 
-    pub fn update(&self, update: TreeUpdate) {
-        self.tree.update_and_process_changes(update, |change| {
-            match change {
-                TreeChange::FocusMoved {
-                    old_node: _,
-                    new_node: Some(new_node),
-                } => {
-                    let platform_node = PlatformNode::new(&new_node, self.hwnd);
-                    let el: IRawElementProviderSimple = platform_node.into();
-                    unsafe { UiaRaiseAutomationEvent(el, UIA_AutomationFocusChangedEventId) }
-                        .unwrap();
-                }
-                TreeChange::NodeUpdated { old_node, new_node } => {
-                    let old_node = ResolvedPlatformNode::new(old_node, self.hwnd);
-                    let new_node = ResolvedPlatformNode::new(new_node, self.hwnd);
-                    new_node.raise_property_changes(&old_node);
-                }
-                // TODO: handle other events (#20)
-                _ => (),
-            };
-        });
+    /// Returns the tree, first building it with the [`InitTree`]
+    /// that was passed to the constructor if this is the first call.
+    pub(crate) fn get_or_create_tree(&self) -> Arc<Tree> {
+        let mut state = self.tree.lock().unwrap();
+        let tree = match state.take().unwrap() {
+            TreeState::Pending(init, Some(action_handler)) => {
+                Tree::with_action_handler(init.init_tree(), action_handler)
+            }
+            TreeState::Pending(init, None) => Tree::new(init.init_tree()),
+            TreeState::Initialized(tree) => tree,
+        };
+        *state = Some(TreeState::Initialized(tree.clone()));
+        tree
+    }
+
+    /// Returns true if the tree has been built, i.e. an assistive
+    /// technology has requested it, or an update has been applied.
+    /// An application whose initial state is an [`InitTree`] that
+    /// builds the tree on demand can use this to skip building
+    /// updates until they're needed.
+    pub fn is_tree_initialized(&self) -> bool {
+        matches!(*self.tree.lock().unwrap(), Some(TreeState::Initialized(_)))
+    }
+
+    /// Returns the current state of the tree as an update that can
+    /// initialize another manager, e.g. one in a separate process.
+    /// See [`Tree::serialize`].
+    pub fn export_tree(&self) -> TreeUpdate {
+        self.get_or_create_tree().serialize()
+    }
+
+    /// Applies the update and raises the corresponding UIA events.
+    /// This must be called on the thread that owns the window.
+    /// A stale update, as described in [`TreeUpdate::version`],
+    /// is ignored, and no events are raised. If the tree hasn't been
+    /// built yet, it's built first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the update is invalid; see [`Manager::try_update`].
+    pub fn update(&self, update: TreeUpdate) -> UpdateStatus {
+        self.try_update(update)
+            .unwrap_or_else(|error| panic!("invalid tree update: {}", error))
+    }
+
+    /// Applies the update as with [`Manager::update`], but returns an
+    /// error rather than panicking if the update is invalid, in which
+    /// case the tree is left unchanged and no events are raised.
+    pub fn try_update(&self, update: TreeUpdate) -> Result<UpdateStatus, TreeUpdateError> {
+        let mut sink = UiaEventSink {
+            hwnd: self.hwnd,
+            localizer: &self.localizer,
+            scale_factor: &self.scale_factor,
+        };
+        self.get_or_create_tree()
+            .try_update_with_sink(update, &mut sink)
     }
 
     fn root_platform_node(&self) -> PlatformNode {
-        let reader = self.tree.read();
+        let tree = self.get_or_create_tree();
+        let reader = tree.read();
         let node = reader.root();
-        PlatformNode::new(&node, self.hwnd)
+        PlatformNode::new(
+            &node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        )
     }
 
     pub fn handle_wm_getobject(&self, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
@@ -67,6 +210,79 @@ impl Manager {
     }
 }
 
+// Raises the UIA events that correspond to each change in the tree.
+// The consumer reports changes in the order documented on
+// `Tree::update_and_process_changes`, so property changes on a newly
+// focused node are raised before the focus event, and Narrator doesn't
+// announce stale values.
+struct UiaEventSink<'a> {
+    hwnd: HWND,
+    localizer: &'a Arc<dyn Localizer>,
+    scale_factor: &'a Arc<ScaleFactor>,
+}
+
+impl EventSink for UiaEventSink<'_> {
+    fn on_node_added(&mut self, node: Node) {
+        let node = ResolvedPlatformNode::new(
+            node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        );
+        node.raise_tooltip_changes(None);
+    }
+
+    fn on_node_updated(&mut self, old_node: Node, new_node: Node, dirty: DirtyFlags) {
+        let old_node = ResolvedPlatformNode::new(
+            old_node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        );
+        let new_node = ResolvedPlatformNode::new(
+            new_node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        );
+        new_node.raise_property_changes(&old_node);
+        // The text, the selection and the caret's position are the only
+        // things that raise text events, and a tooltip only appears or
+        // disappears when its state changes.
+        if dirty.name || dirty.value || dirty.state || dirty.bounds {
+            new_node.raise_text_changes(&old_node);
+        }
+        if dirty.state {
+            new_node.raise_tooltip_changes(Some(&old_node));
+        }
+    }
+
+    fn on_focus_moved(&mut self, _old_node: Option<Node>, new_node: Option<Node>) {
+        if let Some(new_node) = new_node {
+            let platform_node = PlatformNode::new(
+                &new_node,
+                self.hwnd,
+                self.localizer.clone(),
+                self.scale_factor.clone(),
+            );
+            let el: IRawElementProviderSimple = platform_node.into();
+            unsafe { UiaRaiseAutomationEvent(el, UIA_AutomationFocusChangedEventId) }.unwrap();
+        }
+    }
+
+    fn on_root_changed(&mut self, _old_node: Node, new_node: Node) {
+        let new_node = ResolvedPlatformNode::new(
+            new_node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        );
+        new_node.raise_children_invalidated();
+    }
+
+    // TODO: handle other events (#20)
+}
+
 fn force_init_uia() {
     // `UiaLookupId` is a cheap way of forcing UIA to initialize itself.
     unsafe {
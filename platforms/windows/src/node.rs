@@ -5,20 +5,37 @@
 
 #![allow(non_upper_case_globals)]
 
-use accesskit_consumer::{Node, WeakNode};
-use accesskit_schema::{NodeIdContent, Role};
+use accesskit_consumer::{Node, TraversalOrder, WeakNode};
+use accesskit_schema::{
+    default_action_verb_string, AriaCurrent, CheckedState, DropEffect, HasPopup, InvalidState,
+    MarkerType, NodeIdContent, Orientation, Rect, Role, SortDirection,
+};
 use arrayvec::ArrayVec;
+use std::sync::Arc;
 use windows as Windows;
 use windows::{
     core::*,
-    Win32::{Foundation::*, Graphics::Gdi::*, System::Com::*, UI::Accessibility::*},
+    Win32::{
+        Foundation::*, Graphics::Gdi::*, System::Com::*, UI::Accessibility::*,
+        UI::WindowsAndMessaging::*,
+    },
+};
+
+use crate::{
+    localizer::Localizer,
+    text::{text_range, TextContent},
+    util::*,
 };
 
-use crate::util::*;
+// The value of `UIA_ScrollPatternNoScroll`, for a node that can't scroll
+// in a given direction.
+const SCROLL_PATTERN_NO_SCROLL: f64 = -1.0;
 
 pub(crate) struct ResolvedPlatformNode<'a> {
-    node: Node<'a>,
+    pub(crate) node: Node<'a>,
     hwnd: HWND,
+    localizer: Arc<dyn Localizer>,
+    scale_factor: Arc<ScaleFactor>,
 }
 
 macro_rules! properties {
@@ -28,7 +45,7 @@ macro_rules! properties {
                 $($id => {
                     self.$m().into()
                 })*
-                _ => VariantFactory::empty()
+                _ => self.relation_property_value(property_id)
             }
         }
         pub(crate) fn raise_property_changes(&self, old: &ResolvedPlatformNode) {
@@ -44,29 +61,243 @@ macro_rules! properties {
 }
 
 impl ResolvedPlatformNode<'_> {
-    pub(crate) fn new(node: Node, hwnd: HWND) -> ResolvedPlatformNode {
-        ResolvedPlatformNode { node, hwnd }
+    pub(crate) fn new(
+        node: Node,
+        hwnd: HWND,
+        localizer: Arc<dyn Localizer>,
+        scale_factor: Arc<ScaleFactor>,
+    ) -> ResolvedPlatformNode {
+        ResolvedPlatformNode {
+            node,
+            hwnd,
+            localizer,
+            scale_factor,
+        }
     }
 
-    fn relative<'a>(&self, node: Node<'a>) -> ResolvedPlatformNode<'a> {
-        ResolvedPlatformNode::new(node, self.hwnd)
+    pub(crate) fn relative<'a>(&self, node: Node<'a>) -> ResolvedPlatformNode<'a> {
+        ResolvedPlatformNode::new(
+            node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        )
     }
 
-    fn downgrade(&self) -> PlatformNode {
-        PlatformNode::new(&self.node, self.hwnd)
+    pub(crate) fn downgrade(&self) -> PlatformNode {
+        PlatformNode::new(
+            &self.node,
+            self.hwnd,
+            self.localizer.clone(),
+            self.scale_factor.clone(),
+        )
     }
 
     fn provider_options(&self) -> ProviderOptions {
         ProviderOptions_ServerSideProvider
     }
 
-    fn is_pattern_supported(&self, _pattern_id: i32) -> bool {
-        // TODO: add patterns
-        false
+    fn is_pattern_supported(&self, pattern_id: i32) -> bool {
+        match pattern_id {
+            UIA_RangeValuePatternId => self.is_range_value_pattern_supported(),
+            UIA_TextPatternId | UIA_TextPattern2Id => self.is_text_pattern_supported(),
+            UIA_DragPatternId => self.node.is_draggable(),
+            UIA_DropTargetPatternId => !self.node.drop_effects().is_empty(),
+            UIA_AnnotationPatternId => self.annotation_type_id().is_some(),
+            UIA_LegacyIAccessiblePatternId => true,
+            _ => false,
+        }
+    }
+
+    // An indeterminate progress bar has no value to report, so like
+    // Chromium, we omit the pattern rather than reporting a bogus value.
+    fn is_range_value_pattern_supported(&self) -> bool {
+        !self.node.is_indeterminate() && self.node.data().value_for_range.is_some()
+    }
+
+    // Like Chromium, text fields and documents expose their text through
+    // the text pattern.
+    fn is_text_pattern_supported(&self) -> bool {
+        self.node.is_text_field() || self.node.is_document_root()
+    }
+
+    fn document_range(&self) -> ITextRangeProvider {
+        let content = TextContent::new(self.node);
+        text_range(self, 0..content.len())
+    }
+
+    fn text_selection(&self) -> Vec<ITextRangeProvider> {
+        let content = TextContent::new(self.node);
+        content
+            .selection(&self.node)
+            .map(|range| text_range(self, range))
+            .into_iter()
+            .collect()
+    }
+
+    // Returns an empty range at the start of the text run that contains
+    // the given point in screen coordinates, or at the start of the text
+    // if no run does.
+    fn text_range_from_point(&self, point: UiaPoint) -> ITextRangeProvider {
+        let content = TextContent::new(self.node);
+        let position = content
+            .run_starts()
+            .find(|(_, node)| {
+                let rect = self.relative(*node).bounding_rectangle();
+                node.bounds().is_some()
+                    && (rect.left..(rect.left + rect.width)).contains(&point.x)
+                    && (rect.top..(rect.top + rect.height)).contains(&point.y)
+            })
+            .map_or(0, |(start, _)| start);
+        text_range(self, position..position)
+    }
+
+    // The caret is placed at the text under the middle of its left edge.
+    // Without a caret node, it's at the end of the selection, if any.
+    fn caret_range(&self) -> Option<ITextRangeProvider> {
+        if let Some(rect) = self.node.caret_position() {
+            let rect = self.screen_rect(rect);
+            let point = UiaPoint {
+                x: rect.left,
+                y: rect.top + rect.height / 2.0,
+            };
+            return Some(self.text_range_from_point(point));
+        }
+        let content = TextContent::new(self.node);
+        let selection = content.selection(&self.node)?;
+        Some(text_range(self, selection.end..selection.end))
+    }
+
+    // The caret is active when the text field or document, or something
+    // within it, has keyboard focus.
+    fn is_caret_active(&self) -> bool {
+        self.node
+            .tree_reader
+            .focus()
+            .map_or(false, |focus| focus.is_descendant_of(&self.node))
+    }
+
+    fn range_value(&self) -> f64 {
+        self.node.data().value_for_range.unwrap_or(0.0).into()
+    }
+
+    // A disabled node can't be changed by the user either.
+    fn is_read_only(&self) -> bool {
+        self.node.data().read_only || self.node.is_disabled()
+    }
+
+    fn min_range_value(&self) -> f64 {
+        self.node.data().min_value_for_range.unwrap_or(0.0).into()
+    }
+
+    fn max_range_value(&self) -> f64 {
+        self.node.data().max_value_for_range.unwrap_or(0.0).into()
+    }
+
+    fn small_change(&self) -> f64 {
+        self.node.step_value_for_range().unwrap_or(0.0).into()
+    }
+
+    fn large_change(&self) -> f64 {
+        self.node.large_step_value_for_range().unwrap_or(0.0).into()
+    }
+
+    fn set_range_value(&self, value: f64) {
+        self.node.set_numeric_value(value);
+    }
+
+    // A node that can be grabbed, but isn't, reports that it isn't
+    // grabbed, rather than omitting the property.
+    fn is_grabbed(&self) -> bool {
+        self.node.is_grabbed()
+    }
+
+    fn grabbed_items(&self) -> Vec<IUnknown> {
+        self.node
+            .grabbed_items()
+            .into_iter()
+            .map(|node| {
+                let el: IRawElementProviderSimple = self.relative(node).downgrade().into();
+                el.into()
+            })
+            .collect()
+    }
+
+    // Following Core-AAM, details that are annotations, such as comments,
+    // are exposed as annotation objects, while other details are exposed
+    // along with the nodes that describe this one.
+    fn described_by(&self) -> Vec<IUnknown> {
+        let details = self
+            .node
+            .details()
+            .filter(|node| annotation_type(node).is_none());
+        self.elements(self.node.described_by().chain(details))
+    }
+
+    fn annotation_objects(&self) -> Vec<IUnknown> {
+        self.elements(
+            self.node
+                .details()
+                .filter(|node| annotation_type(node).is_some()),
+        )
+    }
+
+    fn elements<'b>(&self, nodes: impl Iterator<Item = Node<'b>>) -> Vec<IUnknown> {
+        nodes
+            .map(|node| {
+                let el: IRawElementProviderSimple = self.relative(node).downgrade().into();
+                el.into()
+            })
+            .collect()
+    }
+
+    fn drop_effects(&self) -> Vec<&'static str> {
+        self.node
+            .drop_effects()
+            .into_iter()
+            .map(drop_effect_name)
+            .collect()
+    }
+
+    // UIA only has room for one effect here, so like the order of
+    // `DropEffect`, this is arbitrary when there's more than one.
+    fn drop_effect(&self) -> &'static str {
+        self.drop_effects().first().copied().unwrap_or("none")
+    }
+
+    fn annotation_type_id(&self) -> Option<i32> {
+        annotation_type(&self.node)
+    }
+
+    // A comment annotates the content that refers to it with
+    // `aria-details`. Other annotations cover their own content.
+    fn annotation_target(&self) -> IRawElementProviderSimple {
+        let target = if self.node.role() == Role::Comment {
+            let id = self.node.id();
+            self.node
+                .tree_reader
+                .traverse(TraversalOrder::PreOrder)
+                .find(|node| node.data().details.contains(&id))
+        } else {
+            None
+        };
+        self.relative(target.unwrap_or(self.node))
+            .downgrade()
+            .into()
     }
 
     fn control_type(&self) -> i32 {
         let role = self.node.role();
+        // Like Chromium, expose a numeric text field as a spinner,
+        // and a range input as a slider. Other input types, such as
+        // email or password, are still edits.
+        if role == Role::TextField {
+            match self.node.input_type() {
+                Some("number") => return UIA_SpinnerControlTypeId,
+                Some("range") => return UIA_SliderControlTypeId,
+                _ => (),
+            }
+        }
         // TODO: Handle special cases. (#14)
         match role {
             Role::Unknown => UIA_CustomControlTypeId,
@@ -208,7 +439,7 @@ impl ResolvedPlatformNode<'_> {
             Role::Status => UIA_StatusBarControlTypeId,
             Role::Strong => UIA_TextControlTypeId,
             Role::Suggestion => UIA_GroupControlTypeId,
-            Role::SvgRoot => UIA_ImageControlTypeId,
+            Role::SvgRoot => UIA_DocumentControlTypeId,
             Role::Tab => UIA_TabItemControlTypeId,
             Role::TabList => UIA_TabControlTypeId,
             Role::TabPanel => UIA_PaneControlTypeId,
@@ -223,16 +454,15 @@ impl ResolvedPlatformNode<'_> {
             Role::TreeGrid => UIA_DataGridControlTypeId,
             Role::Video => UIA_GroupControlTypeId,
             Role::WebView => UIA_DocumentControlTypeId,
-            Role::Window => {
-                // TODO: determine whether to use Window or Pane.
-                // It may be good to use Pane for nested windows,
-                // as Chromium does. (#14)
-                UIA_WindowControlTypeId
-            }
+            // Like Chromium, only the top-level window is exposed as a window;
+            // the host window provides the window pattern for it. A nested
+            // window, e.g. a modal dialog drawn by the application, is a pane.
+            Role::Window if self.node.is_root() => UIA_WindowControlTypeId,
+            Role::Window => UIA_PaneControlTypeId,
             Role::PdfActionableHighlight => UIA_CustomControlTypeId,
             Role::PdfRoot => UIA_DocumentControlTypeId,
             Role::GraphicsDocument => UIA_DocumentControlTypeId,
-            Role::GraphicsObject => UIA_PaneControlTypeId,
+            Role::GraphicsObject => UIA_GroupControlTypeId,
             Role::GraphicsSymbol => UIA_ImageControlTypeId,
             Role::DocAbstract => UIA_GroupControlTypeId,
             Role::DocAcknowledgements => UIA_GroupControlTypeId,
@@ -287,18 +517,277 @@ impl ResolvedPlatformNode<'_> {
         !self.node.is_invisible_or_ignored()
     }
 
+    // Like UIA's own controls, a node in a disabled container is disabled.
     fn is_enabled(&self) -> bool {
-        !self.node.is_disabled()
+        self.node.is_effectively_enabled()
     }
 
+    // A disabled or invisible node can't actually take focus,
+    // even if the provider says it's focusable.
     fn is_focusable(&self) -> bool {
-        self.node.is_focusable()
+        self.node.is_focusable() && self.is_enabled() && !self.node.is_invisible()
     }
 
     fn is_focused(&self) -> bool {
         self.node.is_focused()
     }
 
+    fn is_password(&self) -> bool {
+        self.node.data().protected || self.node.input_type() == Some("password")
+    }
+
+    fn is_data_valid_for_form(&self) -> bool {
+        matches!(
+            self.node.data().invalid_state,
+            None | Some(InvalidState::False)
+        )
+    }
+
+    // An error message takes precedence, since it's more urgent than
+    // the target of a link or the expansion of an abbreviation.
+    fn full_description(&self) -> Option<String> {
+        self.node
+            .error_message()
+            .and_then(|node| node.name().map(String::from))
+            .or_else(|| {
+                if self.control_type() == UIA_HyperlinkControlTypeId {
+                    self.node.url().map(String::from)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| self.node.abbreviation_expansion().map(String::from))
+    }
+
+    // There's no UIA property for a color, so like Chromium, we expose
+    // the selected color of a color well as its value.
+    fn value(&self) -> Option<String> {
+        self.node.color_value().map(|color| color.to_string())
+    }
+
+    fn orientation(&self) -> Option<i32> {
+        self.node
+            .orientation()
+            .map(|orientation| match orientation {
+                Orientation::Horizontal => OrientationType_Horizontal,
+                Orientation::Vertical => OrientationType_Vertical,
+            })
+    }
+
+    fn class_name(&self) -> Option<&str> {
+        self.node.class_name()
+    }
+
+    fn accelerator_key(&self) -> Option<String> {
+        let shortcuts = self.node.key_shortcuts();
+        if shortcuts.is_empty() {
+            None
+        } else {
+            Some(shortcuts.join(", "))
+        }
+    }
+
+    fn aria_properties(&self) -> Option<String> {
+        let mut properties = Vec::new();
+        if let Some(has_popup) = self.node.data().has_popup {
+            properties.push(format!("haspopup={}", has_popup_value(has_popup)));
+        }
+        if let Some(invalid_state) = &self.node.data().invalid_state {
+            properties.push(format!("invalid={}", invalid_state_value(invalid_state)));
+        }
+        if let Some(auto_complete) = self.node.auto_complete() {
+            properties.push(format!("autocomplete={}", auto_complete));
+        }
+        if let Some(sort_direction) = self.node.data().sort_direction {
+            properties.push(format!("sort={}", sort_direction_value(sort_direction)));
+        }
+        if let Some(current) = self.node.aria_current() {
+            properties.push(format!("current={}", aria_current_value(current)));
+        }
+        if properties.is_empty() {
+            None
+        } else {
+            Some(properties.join(";"))
+        }
+    }
+
+    fn level(&self) -> Option<i32> {
+        self.node.data().hierarchical_level.map(saturating_i32)
+    }
+
+    fn position_in_set(&self) -> Option<i32> {
+        self.node.position_in_set().map(saturating_i32)
+    }
+
+    fn size_of_set(&self) -> Option<i32> {
+        self.node.size_of_set().map(saturating_i32)
+    }
+
+    // The root scroller is scrolled by the host window, which reports
+    // its own scroll position.
+    fn scroll_percent(&self, value: Option<f32>, percent: Option<f32>) -> Option<f64> {
+        if self.node.is_root_scroller() {
+            return None;
+        }
+        value?;
+        Some(percent.map_or(SCROLL_PATTERN_NO_SCROLL, f64::from))
+    }
+
+    fn horizontal_scroll_percent(&self) -> Option<f64> {
+        self.scroll_percent(self.node.data().scroll_x, self.node.scroll_percent_x())
+    }
+
+    fn vertical_scroll_percent(&self) -> Option<f64> {
+        self.scroll_percent(self.node.data().scroll_y, self.node.scroll_percent_y())
+    }
+
+    fn localized_control_type(&self) -> Option<String> {
+        match self.node.role_description() {
+            Some(description) => Some(description.into()),
+            None => self
+                .localizer
+                .role_description(self.node.role())
+                .or_else(|| self.default_role_description().map(String::from)),
+        }
+    }
+
+    // Core-AAM exposes a meter as a progress bar that's described
+    // differently, since UIA has no control type for meters.
+    fn default_role_description(&self) -> Option<&'static str> {
+        if self.node.is_meter() {
+            Some("meter")
+        } else {
+            None
+        }
+    }
+
+    fn item_status(&self) -> Option<String> {
+        match self.node.checked_state_description() {
+            Some(description) => Some(description.into()),
+            None => self
+                .node
+                .data()
+                .checked_state
+                .and_then(|state| self.localizer.checked_state_description(state))
+                .or_else(|| self.sort_status().map(String::from)),
+        }
+    }
+
+    // Core-AAM maps `aria-sort` on a header to the item status,
+    // since UIA's table item pattern has no sort property.
+    fn sort_status(&self) -> Option<&'static str> {
+        if !matches!(self.node.role(), Role::ColumnHeader | Role::RowHeader) {
+            return None;
+        }
+        match self.node.data().sort_direction? {
+            SortDirection::Unsorted => None,
+            sort_direction => Some(sort_direction_value(sort_direction)),
+        }
+    }
+
+    fn default_action(&self) -> Option<String> {
+        let verb = self.node.data().default_action_verb?;
+        Some(
+            self.localizer
+                .default_action_verb(verb)
+                .unwrap_or_else(|| default_action_verb_string(verb).into()),
+        )
+    }
+
+    fn msaa_role(&self) -> u32 {
+        if self.node.is_root() {
+            ROLE_SYSTEM_CLIENT
+        } else {
+            msaa_role(self.node.role())
+        }
+    }
+
+    fn msaa_state(&self) -> u32 {
+        let data = self.node.data();
+        let mut state = 0;
+        if !self.is_enabled() {
+            state |= STATE_SYSTEM_UNAVAILABLE;
+        }
+        if self.is_focusable() {
+            state |= STATE_SYSTEM_FOCUSABLE;
+        }
+        if self.is_focused() {
+            state |= STATE_SYSTEM_FOCUSED;
+        }
+        if self.node.is_invisible() {
+            state |= STATE_SYSTEM_INVISIBLE;
+        }
+        match data.checked_state {
+            Some(CheckedState::True) if self.node.role() == Role::ToggleButton => {
+                state |= STATE_SYSTEM_PRESSED;
+            }
+            Some(CheckedState::True) => state |= STATE_SYSTEM_CHECKED,
+            Some(CheckedState::Mixed) => state |= STATE_SYSTEM_MIXED,
+            Some(CheckedState::False) | None => (),
+        }
+        match data.selected {
+            Some(true) => state |= STATE_SYSTEM_SELECTABLE | STATE_SYSTEM_SELECTED,
+            Some(false) => state |= STATE_SYSTEM_SELECTABLE,
+            None => (),
+        }
+        match data.expanded {
+            Some(true) => state |= STATE_SYSTEM_EXPANDED,
+            Some(false) => state |= STATE_SYSTEM_COLLAPSED,
+            None => (),
+        }
+        if data.has_popup.is_some() {
+            state |= STATE_SYSTEM_HASPOPUP;
+        }
+        if data.read_only {
+            state |= STATE_SYSTEM_READONLY;
+        }
+        if self.is_password() {
+            state |= STATE_SYSTEM_PROTECTED;
+        }
+        if data.linked || self.node.role() == Role::Link {
+            state |= STATE_SYSTEM_LINKED;
+        }
+        if data.visited {
+            state |= STATE_SYSTEM_TRAVERSED;
+        }
+        if data.busy {
+            state |= STATE_SYSTEM_BUSY;
+        }
+        state
+    }
+
+    // MSAA has a single value string, so a text field's value takes
+    // precedence over the color of a color well.
+    fn legacy_value(&self) -> Option<String> {
+        self.node
+            .data()
+            .value
+            .as_deref()
+            .map(String::from)
+            .or_else(|| self.value())
+    }
+
+    fn legacy_description(&self) -> Option<&str> {
+        self.node.data().description.as_deref()
+    }
+
+    fn help(&self) -> Option<&str> {
+        self.node.data().tooltip.as_deref()
+    }
+
+    fn keyboard_shortcut(&self) -> Option<String> {
+        self.node
+            .data()
+            .access_key
+            .as_deref()
+            .map(String::from)
+            .or_else(|| self.accelerator_key())
+    }
+
+    fn do_default_action(&self) {
+        self.node.do_default_action();
+    }
+
     fn raise_property_change(
         &self,
         property_id: i32,
@@ -312,6 +801,80 @@ impl ResolvedPlatformNode<'_> {
             .unwrap();
     }
 
+    /// Raises the UIA text events for any change to this node's text
+    /// selection or text since `old`. A change to the text of an inline
+    /// text box is reported on the text field or document that contains it.
+    pub(crate) fn raise_text_changes(&self, old: &ResolvedPlatformNode) {
+        let old_data = old.node.data();
+        let data = self.node.data();
+        if data.text_selection != old_data.text_selection {
+            if let Some(container) = self.text_container() {
+                container.raise_event(UIA_Text_TextSelectionChangedEventId);
+            }
+        }
+        // Screen readers track the insertion point through selection
+        // events on the field, so a moving caret is reported the same way,
+        // on the field whose caret range it is.
+        if self.node.role() == Role::Caret && data.bounds != old_data.bounds {
+            if let Some(container) = self.text_container() {
+                container.raise_event(UIA_Text_TextSelectionChangedEventId);
+            }
+        }
+        let text_changed = data.value != old_data.value
+            || (self.node.role() == Role::InlineTextBox && data.name != old_data.name);
+        if text_changed {
+            if let Some(container) = self.text_container() {
+                container.raise_event(UIA_Text_TextChangedEventId);
+            }
+        }
+    }
+
+    /// Raises the UIA tooltip events if this is a tooltip that has
+    /// appeared or disappeared since `old`. A tooltip that's added
+    /// without being invisible has appeared.
+    pub(crate) fn raise_tooltip_changes(&self, old: Option<&ResolvedPlatformNode>) {
+        if self.node.role() != Role::Tooltip {
+            return;
+        }
+        let was_visible = old.is_some_and(|old| !old.node.is_invisible());
+        let is_visible = !self.node.is_invisible();
+        if is_visible && !was_visible {
+            self.raise_event(UIA_ToolTipOpenedEventId);
+        } else if was_visible && !is_visible {
+            self.raise_event(UIA_ToolTipClosedEventId);
+        }
+    }
+
+    // UIA only accepts text events from an element that supports
+    // the text pattern, so they're raised on the nearest such node,
+    // or not at all if the text isn't in one.
+    fn text_container(&self) -> Option<ResolvedPlatformNode> {
+        std::iter::successors(Some(self.node), |node| node.parent())
+            .map(|node| self.relative(node))
+            .find(|resolved| resolved.is_text_pattern_supported())
+    }
+
+    fn raise_event(&self, event_id: i32) {
+        let el: IRawElementProviderSimple = self.downgrade().into();
+        unsafe { UiaRaiseAutomationEvent(el, event_id) }.unwrap();
+    }
+
+    /// Tells UIA that this node's entire subtree has been replaced,
+    /// so that clients discard any cached elements within it.
+    pub(crate) fn raise_children_invalidated(&self) {
+        let el: IRawElementProviderSimple = self.downgrade().into();
+        let mut runtime_id = self.runtime_id().to_vec();
+        unsafe {
+            UiaRaiseStructureChangedEvent(
+                el,
+                StructureChangeType_ChildrenInvalidated,
+                runtime_id.as_mut_ptr(),
+                runtime_id.len() as i32,
+            )
+        }
+        .unwrap();
+    }
+
     properties! {
         (UIA_ControlTypePropertyId, control_type),
         (UIA_NamePropertyId, name),
@@ -319,7 +882,36 @@ impl ResolvedPlatformNode<'_> {
         (UIA_IsControlElementPropertyId, is_content_element),
         (UIA_IsEnabledPropertyId, is_enabled),
         (UIA_IsKeyboardFocusablePropertyId, is_focusable),
-        (UIA_HasKeyboardFocusPropertyId, is_focused)
+        (UIA_HasKeyboardFocusPropertyId, is_focused),
+        (UIA_IsPasswordPropertyId, is_password),
+        (UIA_DragIsGrabbedPropertyId, is_grabbed),
+        (UIA_AnnotationAnnotationTypeIdPropertyId, annotation_type_id),
+        (UIA_IsDataValidForFormPropertyId, is_data_valid_for_form),
+        (UIA_FullDescriptionPropertyId, full_description),
+        (UIA_ValueValuePropertyId, value),
+        (UIA_AcceleratorKeyPropertyId, accelerator_key),
+        (UIA_ClassNamePropertyId, class_name),
+        (UIA_OrientationPropertyId, orientation),
+        (UIA_AriaPropertiesPropertyId, aria_properties),
+        (UIA_LevelPropertyId, level),
+        (UIA_PositionInSetPropertyId, position_in_set),
+        (UIA_SizeOfSetPropertyId, size_of_set),
+        (UIA_ScrollHorizontalScrollPercentPropertyId, horizontal_scroll_percent),
+        (UIA_ScrollVerticalScrollPercentPropertyId, vertical_scroll_percent),
+        (UIA_LocalizedControlTypePropertyId, localized_control_type),
+        (UIA_ItemStatusPropertyId, item_status),
+        (UIA_LegacyIAccessibleDefaultActionPropertyId, default_action)
+    }
+
+    // Relations aren't listed in `properties!`, because every call creates
+    // new element providers, which would never compare equal and so would
+    // raise a property change event on every update.
+    fn relation_property_value(&self, property_id: i32) -> VariantFactory {
+        match property_id {
+            UIA_DescribedByPropertyId => self.described_by().into(),
+            UIA_AnnotationObjectsPropertyId => self.annotation_objects().into(),
+            _ => VariantFactory::empty(),
+        }
     }
 
     fn host_provider(&self) -> Result<IRawElementProviderSimple> {
@@ -354,20 +946,27 @@ impl ResolvedPlatformNode<'_> {
         result
     }
 
-    fn bounding_rectangle(&self) -> UiaRect {
-        self.node.bounds().map_or(UiaRect::default(), |rect| {
-            let mut result = UiaRect {
-                left: rect.left.into(),
-                top: rect.top.into(),
-                width: rect.width.into(),
-                height: rect.height.into(),
-            };
-            let mut client_top_left = POINT::default();
-            unsafe { ClientToScreen(self.hwnd, &mut client_top_left) }.unwrap();
-            result.left += f64::from(client_top_left.x);
-            result.top += f64::from(client_top_left.y);
-            result
-        })
+    pub(crate) fn bounding_rectangle(&self) -> UiaRect {
+        self.node
+            .bounds()
+            .map_or(UiaRect::default(), |rect| self.screen_rect(rect))
+    }
+
+    // Converts a rectangle in the tree's coordinate space to screen
+    // coordinates, as UIA expects.
+    fn screen_rect(&self, rect: Rect) -> UiaRect {
+        let rect = rect.scale(self.scale_factor.get());
+        let mut result = UiaRect {
+            left: rect.left.into(),
+            top: rect.top.into(),
+            width: rect.width.into(),
+            height: rect.height.into(),
+        };
+        let mut client_top_left = POINT::default();
+        unsafe { ClientToScreen(self.hwnd, &mut client_top_left) }.unwrap();
+        result.left += f64::from(client_top_left.x);
+        result.top += f64::from(client_top_left.y);
+        result
     }
 
     fn set_focus(&self) {
@@ -389,31 +988,192 @@ impl ResolvedPlatformNode<'_> {
     }
 }
 
+// Values follow the ARIA `aria-dropeffect` tokens, which is what clients
+// expect from web content.
+pub(crate) fn drop_effect_name(effect: DropEffect) -> &'static str {
+    match effect {
+        DropEffect::Copy => "copy",
+        DropEffect::Execute => "execute",
+        DropEffect::Link => "link",
+        DropEffect::Move => "move",
+        DropEffect::Popup => "popup",
+    }
+}
+
+// A suggestion is a proposed set of insertions and deletions, so it's
+// reported as tracked changes, as is a mark that highlights a suggestion.
+// Any other mark is just highlighted.
+fn annotation_type(node: &Node) -> Option<i32> {
+    match node.role() {
+        Role::Comment => Some(AnnotationType_Comment),
+        Role::ContentInsertion => Some(AnnotationType_InsertionChange),
+        Role::ContentDeletion => Some(AnnotationType_DeletionChange),
+        Role::Suggestion => Some(AnnotationType_TrackChanges),
+        Role::Mark => {
+            let is_suggestion = node.data().markers.iter().any(|marker| {
+                matches!(
+                    marker.marker_type,
+                    MarkerType::Suggestion | MarkerType::ActiveSuggestion
+                )
+            });
+            Some(if is_suggestion {
+                AnnotationType_TrackChanges
+            } else {
+                AnnotationType_Highlighted
+            })
+        }
+        _ => None,
+    }
+}
+
+// Roles that have no MSAA equivalent are reported as groupings,
+// as Chromium does.
+fn msaa_role(role: Role) -> u32 {
+    match role {
+        Role::Alert => ROLE_SYSTEM_ALERT,
+        Role::AlertDialog | Role::Dialog => ROLE_SYSTEM_DIALOG,
+        Role::Application | Role::Window => ROLE_SYSTEM_WINDOW,
+        Role::Button | Role::PopupButton | Role::ToggleButton => ROLE_SYSTEM_PUSHBUTTON,
+        Role::Caret => ROLE_SYSTEM_CARET,
+        Role::Cell | Role::LayoutTableCell => ROLE_SYSTEM_CELL,
+        Role::CheckBox | Role::MenuItemCheckBox | Role::Switch => ROLE_SYSTEM_CHECKBUTTON,
+        Role::Client => ROLE_SYSTEM_CLIENT,
+        Role::ColumnHeader => ROLE_SYSTEM_COLUMNHEADER,
+        Role::Column => ROLE_SYSTEM_COLUMN,
+        Role::ComboBoxGrouping | Role::ComboBoxMenuButton | Role::TextFieldWithComboBox => {
+            ROLE_SYSTEM_COMBOBOX
+        }
+        Role::Document
+        | Role::RootWebArea
+        | Role::PdfRoot
+        | Role::SvgRoot
+        | Role::GraphicsDocument => ROLE_SYSTEM_DOCUMENT,
+        Role::Grid | Role::ListGrid | Role::Table | Role::TreeGrid | Role::LayoutTable => {
+            ROLE_SYSTEM_TABLE
+        }
+        Role::Heading | Role::Paragraph | Role::GenericContainer => ROLE_SYSTEM_GROUPING,
+        Role::Image | Role::Canvas | Role::GraphicsSymbol => ROLE_SYSTEM_GRAPHIC,
+        Role::Link => ROLE_SYSTEM_LINK,
+        Role::List | Role::ListBox | Role::DescriptionList => ROLE_SYSTEM_LIST,
+        Role::ListBoxOption | Role::ListItem | Role::MenuListOption => ROLE_SYSTEM_LISTITEM,
+        Role::Menu | Role::MenuListPopup => ROLE_SYSTEM_MENUPOPUP,
+        Role::MenuBar => ROLE_SYSTEM_MENUBAR,
+        Role::MenuItem => ROLE_SYSTEM_MENUITEM,
+        Role::MenuItemRadio | Role::RadioButton => ROLE_SYSTEM_RADIOBUTTON,
+        Role::Meter | Role::ProgressIndicator => ROLE_SYSTEM_PROGRESSBAR,
+        Role::Pane | Role::ScrollView => ROLE_SYSTEM_PANE,
+        Role::Row | Role::LayoutTableRow => ROLE_SYSTEM_ROW,
+        Role::RowHeader => ROLE_SYSTEM_ROWHEADER,
+        Role::ScrollBar => ROLE_SYSTEM_SCROLLBAR,
+        Role::SearchBox | Role::TextField => ROLE_SYSTEM_TEXT,
+        Role::Slider => ROLE_SYSTEM_SLIDER,
+        Role::SpinButton => ROLE_SYSTEM_SPINBUTTON,
+        Role::Splitter => ROLE_SYSTEM_SEPARATOR,
+        Role::StaticText | Role::LabelText => ROLE_SYSTEM_STATICTEXT,
+        Role::Status => ROLE_SYSTEM_STATUSBAR,
+        Role::Tab => ROLE_SYSTEM_PAGETAB,
+        Role::TabList => ROLE_SYSTEM_PAGETABLIST,
+        Role::TabPanel => ROLE_SYSTEM_PROPERTYPAGE,
+        Role::TitleBar => ROLE_SYSTEM_TITLEBAR,
+        Role::Toolbar => ROLE_SYSTEM_TOOLBAR,
+        Role::Tooltip => ROLE_SYSTEM_TOOLTIP,
+        Role::Tree => ROLE_SYSTEM_OUTLINE,
+        Role::TreeItem => ROLE_SYSTEM_OUTLINEITEM,
+        _ => ROLE_SYSTEM_GROUPING,
+    }
+}
+
+// Values follow the ARIA `aria-haspopup` tokens, which is what clients
+// expect to find in `UIA_AriaPropertiesPropertyId`.
+fn has_popup_value(has_popup: HasPopup) -> &'static str {
+    match has_popup {
+        HasPopup::True => "true",
+        HasPopup::Menu => "menu",
+        HasPopup::Listbox => "listbox",
+        HasPopup::Tree => "tree",
+        HasPopup::Grid => "grid",
+        HasPopup::Dialog => "dialog",
+    }
+}
+
+fn aria_current_value(current: AriaCurrent) -> &'static str {
+    match current {
+        AriaCurrent::False => "false",
+        AriaCurrent::True => "true",
+        AriaCurrent::Page => "page",
+        AriaCurrent::Step => "step",
+        AriaCurrent::Location => "location",
+        AriaCurrent::Date => "date",
+        AriaCurrent::Time => "time",
+    }
+}
+
+fn sort_direction_value(sort_direction: SortDirection) -> &'static str {
+    match sort_direction {
+        SortDirection::Unsorted => "none",
+        SortDirection::Ascending => "ascending",
+        SortDirection::Descending => "descending",
+        SortDirection::Other => "other",
+    }
+}
+
+// `aria-invalid` also allows the "grammar" and "spelling" tokens, which
+// providers express as `InvalidState::Other`; those pass through unchanged.
+fn invalid_state_value(invalid_state: &InvalidState) -> &str {
+    match invalid_state {
+        InvalidState::False => "false",
+        InvalidState::True => "true",
+        InvalidState::Other(value) => value,
+    }
+}
+
 #[implement(
     Windows::Win32::UI::Accessibility::IRawElementProviderSimple,
     Windows::Win32::UI::Accessibility::IRawElementProviderFragment,
-    Windows::Win32::UI::Accessibility::IRawElementProviderFragmentRoot
+    Windows::Win32::UI::Accessibility::IRawElementProviderFragmentRoot,
+    Windows::Win32::UI::Accessibility::IRangeValueProvider,
+    Windows::Win32::UI::Accessibility::IDragProvider,
+    Windows::Win32::UI::Accessibility::IDropTargetProvider,
+    Windows::Win32::UI::Accessibility::IAnnotationProvider,
+    Windows::Win32::UI::Accessibility::ITextProvider,
+    Windows::Win32::UI::Accessibility::ITextProvider2
 )]
 pub(crate) struct PlatformNode {
     node: WeakNode,
     hwnd: HWND,
+    localizer: Arc<dyn Localizer>,
+    scale_factor: Arc<ScaleFactor>,
 }
 
 #[allow(non_snake_case)]
 impl PlatformNode {
-    pub(crate) fn new(node: &Node, hwnd: HWND) -> Self {
+    pub(crate) fn new(
+        node: &Node,
+        hwnd: HWND,
+        localizer: Arc<dyn Localizer>,
+        scale_factor: Arc<ScaleFactor>,
+    ) -> Self {
         Self {
             node: node.downgrade(),
             hwnd,
+            localizer,
+            scale_factor,
         }
     }
 
-    fn resolve<F, T>(&self, f: F) -> Result<T>
+    pub(crate) fn resolve<F, T>(&self, f: F) -> Result<T>
     where
         for<'a> F: FnOnce(ResolvedPlatformNode<'a>) -> Result<T>,
     {
         self.node
-            .map(|node| f(ResolvedPlatformNode::new(node, self.hwnd)))
+            .map(|node| {
+                f(ResolvedPlatformNode::new(
+                    node,
+                    self.hwnd,
+                    self.localizer.clone(),
+                    self.scale_factor.clone(),
+                ))
+            })
             .unwrap_or_else(|| Err(Error::new(HRESULT(UIA_E_ELEMENTNOTAVAILABLE), "".into())))
     }
 
@@ -429,7 +1189,11 @@ impl PlatformNode {
 
     fn GetPatternProvider(&mut self, pattern_id: i32) -> Result<IUnknown> {
         let supported = self.resolve(|resolved| Ok(resolved.is_pattern_supported(pattern_id)))?;
-        if supported {
+        if supported && pattern_id == UIA_LegacyIAccessiblePatternId {
+            let node = self.resolve(|resolved| Ok(resolved.downgrade()))?;
+            let provider: ILegacyIAccessibleProvider = LegacyAccessibleProvider { node }.into();
+            Ok(provider.into())
+        } else if supported {
             let intermediate: IRawElementProviderSimple = self.into();
             Ok(intermediate.into())
         } else {
@@ -512,4 +1276,231 @@ impl PlatformNode {
             None => Err(Error::OK),
         })
     }
+
+    fn SetValue(&self, value: f64) -> Result<()> {
+        self.resolve(|resolved| {
+            if resolved.is_read_only() {
+                return Err(Error::new(HRESULT(UIA_E_ELEMENTNOTENABLED), "".into()));
+            }
+            resolved.set_range_value(value);
+            Ok(())
+        })
+    }
+
+    fn Value(&self) -> Result<f64> {
+        self.resolve(|resolved| Ok(resolved.range_value()))
+    }
+
+    fn IsReadOnly(&self) -> Result<BOOL> {
+        self.resolve(|resolved| Ok(resolved.is_read_only().into()))
+    }
+
+    fn Maximum(&self) -> Result<f64> {
+        self.resolve(|resolved| Ok(resolved.max_range_value()))
+    }
+
+    fn Minimum(&self) -> Result<f64> {
+        self.resolve(|resolved| Ok(resolved.min_range_value()))
+    }
+
+    fn LargeChange(&self) -> Result<f64> {
+        self.resolve(|resolved| Ok(resolved.large_change()))
+    }
+
+    fn SmallChange(&self) -> Result<f64> {
+        self.resolve(|resolved| Ok(resolved.small_change()))
+    }
+
+    fn IsGrabbed(&self) -> Result<BOOL> {
+        self.resolve(|resolved| Ok(resolved.is_grabbed().into()))
+    }
+
+    fn DropEffect(&self) -> Result<BSTR> {
+        self.resolve(|resolved| Ok(resolved.drop_effect().into()))
+    }
+
+    fn DropEffects(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|resolved| Ok(safe_array_from_strings(&resolved.drop_effects())))
+    }
+
+    fn GetGrabbedItems(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|resolved| Ok(safe_array_from_interfaces(&resolved.grabbed_items())))
+    }
+
+    fn DropTargetEffect(&self) -> Result<BSTR> {
+        self.resolve(|resolved| Ok(resolved.drop_effect().into()))
+    }
+
+    fn DropTargetEffects(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|resolved| Ok(safe_array_from_strings(&resolved.drop_effects())))
+    }
+
+    fn AnnotationTypeId(&self) -> Result<i32> {
+        self.resolve(|resolved| Ok(resolved.annotation_type_id().unwrap_or(0)))
+    }
+
+    // UIA derives a localized name from the type ID, and we don't
+    // have an author or date for any annotation.
+    fn AnnotationTypeName(&self) -> Result<BSTR> {
+        self.resolve(|_resolved| Ok(BSTR::new()))
+    }
+
+    fn Author(&self) -> Result<BSTR> {
+        self.resolve(|_resolved| Ok(BSTR::new()))
+    }
+
+    fn DateTime(&self) -> Result<BSTR> {
+        self.resolve(|_resolved| Ok(BSTR::new()))
+    }
+
+    fn Target(&self) -> Result<IRawElementProviderSimple> {
+        self.resolve(|resolved| Ok(resolved.annotation_target()))
+    }
+
+    fn GetSelection(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|resolved| {
+            let ranges: Vec<IUnknown> = resolved
+                .text_selection()
+                .into_iter()
+                .map(|range| range.into())
+                .collect();
+            Ok(safe_array_from_interfaces(&ranges))
+        })
+    }
+
+    // We don't know which part of the text has been scrolled out of view,
+    // so all of it is reported as visible.
+    fn GetVisibleRanges(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|resolved| {
+            let range: IUnknown = resolved.document_range().into();
+            Ok(safe_array_from_interfaces(&[range]))
+        })
+    }
+
+    // As with text ranges, there's no way to get from an element provider
+    // that UIA passes to us back to the node that it belongs to.
+    fn RangeFromChild(
+        &self,
+        _child_element: &Option<IRawElementProviderSimple>,
+    ) -> Result<ITextRangeProvider> {
+        Err(Error::new(E_NOTIMPL, "".into()))
+    }
+
+    fn RangeFromPoint(&self, point: UiaPoint) -> Result<ITextRangeProvider> {
+        self.resolve(|resolved| Ok(resolved.text_range_from_point(point)))
+    }
+
+    fn DocumentRange(&self) -> Result<ITextRangeProvider> {
+        self.resolve(|resolved| Ok(resolved.document_range()))
+    }
+
+    fn SupportedTextSelection(&self) -> Result<SupportedTextSelection> {
+        self.resolve(|_resolved| Ok(SupportedTextSelection_Single))
+    }
+
+    // As with `RangeFromChild`, we can't find the node that belongs to
+    // the annotation element.
+    fn RangeFromAnnotation(
+        &self,
+        _annotation_element: &Option<IRawElementProviderSimple>,
+    ) -> Result<ITextRangeProvider> {
+        Err(Error::new(E_NOTIMPL, "".into()))
+    }
+
+    fn GetCaretRange(&self, is_active: *mut BOOL) -> Result<ITextRangeProvider> {
+        self.resolve(|resolved| {
+            unsafe { *is_active = resolved.is_caret_active().into() };
+            resolved.caret_range().ok_or(Error::OK)
+        })
+    }
+}
+
+// MSAA clients reach the legacy pattern through UIA's MSAA proxy.
+// It's a separate object from `PlatformNode` because some of its methods,
+// such as `Value` and `SetValue`, have the same names as methods of
+// other patterns with different signatures.
+#[implement(Windows::Win32::UI::Accessibility::ILegacyIAccessibleProvider)]
+pub(crate) struct LegacyAccessibleProvider {
+    node: PlatformNode,
+}
+
+#[allow(non_snake_case)]
+impl LegacyAccessibleProvider {
+    fn Select(&self, _flags: i32) -> Result<()> {
+        Err(Error::new(E_NOTIMPL, "".into()))
+    }
+
+    fn DoDefaultAction(&self) -> Result<()> {
+        self.node.resolve(|resolved| {
+            resolved.do_default_action();
+            Ok(())
+        })
+    }
+
+    fn SetValue(&self, _value: PWSTR) -> Result<()> {
+        Err(Error::new(E_NOTIMPL, "".into()))
+    }
+
+    fn GetIAccessible(&self) -> Result<IAccessible> {
+        Err(Error::OK)
+    }
+
+    // The element itself, rather than one of its children, which is
+    // `CHILDID_SELF`.
+    fn ChildId(&self) -> Result<i32> {
+        self.node.resolve(|_resolved| Ok(0))
+    }
+
+    fn Name(&self) -> Result<BSTR> {
+        self.node
+            .resolve(|resolved| Ok(resolved.name().unwrap_or_default().into()))
+    }
+
+    fn Value(&self) -> Result<BSTR> {
+        self.node
+            .resolve(|resolved| Ok(resolved.legacy_value().unwrap_or_default().as_str().into()))
+    }
+
+    fn Description(&self) -> Result<BSTR> {
+        self.node
+            .resolve(|resolved| Ok(resolved.legacy_description().unwrap_or_default().into()))
+    }
+
+    fn Role(&self) -> Result<u32> {
+        self.node.resolve(|resolved| Ok(resolved.msaa_role()))
+    }
+
+    fn State(&self) -> Result<u32> {
+        self.node.resolve(|resolved| Ok(resolved.msaa_state()))
+    }
+
+    fn Help(&self) -> Result<BSTR> {
+        self.node
+            .resolve(|resolved| Ok(resolved.help().unwrap_or_default().into()))
+    }
+
+    fn KeyboardShortcut(&self) -> Result<BSTR> {
+        self.node.resolve(|resolved| {
+            Ok(resolved
+                .keyboard_shortcut()
+                .unwrap_or_default()
+                .as_str()
+                .into())
+        })
+    }
+
+    fn GetSelection(&self) -> Result<*mut SAFEARRAY> {
+        self.node
+            .resolve(|_resolved| Ok(safe_array_from_interfaces(&[])))
+    }
+
+    fn DefaultAction(&self) -> Result<BSTR> {
+        self.node.resolve(|resolved| {
+            Ok(resolved
+                .default_action()
+                .unwrap_or_default()
+                .as_str()
+                .into())
+        })
+    }
 }
@@ -0,0 +1,90 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use std::{
+    num::NonZeroU64,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use accesskit_schema::{Node, NodeId, Role, StringEncoding, Tree, TreeId, TreeUpdate};
+use windows::Win32::Foundation::*;
+
+use super::*;
+use crate::FnInitTree;
+
+const ROOT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+const BUTTON_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+
+// As in the queue test, nothing here raises UIA events,
+// so no window is needed.
+
+#[test]
+fn tree_is_built_on_first_use() {
+    let init_count = Arc::new(AtomicUsize::new(0));
+    let manager = {
+        let init_count = Arc::clone(&init_count);
+        Manager::new(
+            HWND(0),
+            FnInitTree(move || {
+                init_count.fetch_add(1, Ordering::SeqCst);
+                TreeUpdate {
+                    nodes: vec![Node::new(ROOT_ID, Role::Window)],
+                    tree: Some(Tree::new(
+                        TreeId("test".into()),
+                        ROOT_ID,
+                        StringEncoding::Utf8,
+                    )),
+                    ..Default::default()
+                }
+            }),
+        )
+    };
+    assert_eq!(0, init_count.load(Ordering::SeqCst));
+    assert!(!manager.is_tree_initialized());
+
+    let tree = manager.get_or_create_tree();
+    assert_eq!(1, init_count.load(Ordering::SeqCst));
+    assert!(manager.is_tree_initialized());
+    assert_eq!(ROOT_ID, tree.read().root().id());
+
+    let same_tree = manager.get_or_create_tree();
+    assert_eq!(1, init_count.load(Ordering::SeqCst));
+    assert!(Arc::ptr_eq(&tree, &same_tree));
+}
+
+#[test]
+fn exported_tree_initializes_another_manager() {
+    let initial_state = TreeUpdate {
+        nodes: vec![
+            Node {
+                children: Box::new([BUTTON_ID]),
+                ..Node::new(ROOT_ID, Role::Window)
+            },
+            Node {
+                name: Some("OK".into()),
+                ..Node::new(BUTTON_ID, Role::Button)
+            },
+        ],
+        tree: Some(Tree::new(
+            TreeId("test".into()),
+            ROOT_ID,
+            StringEncoding::Utf8,
+        )),
+        focus: Some(BUTTON_ID),
+        ..Default::default()
+    };
+    let manager = Manager::new(HWND(0), initial_state);
+    let exported = manager.export_tree();
+    let imported = Manager::new(HWND(0), exported.clone());
+    assert!(exported == imported.export_tree());
+    assert!(accesskit_consumer::diff_summary(
+        &manager.get_or_create_tree(),
+        &imported.get_or_create_tree()
+    )
+    .is_empty());
+}
@@ -3,7 +3,7 @@
 // the LICENSE-APACHE file) or the MIT license (found in
 // the LICENSE-MIT file), at your option.
 
-use accesskit_schema::{NodeId, TreeUpdate};
+use accesskit_schema::{ActionHandler, ActionRequest, NodeId, TreeUpdate};
 use lazy_static::lazy_static;
 use parking_lot::{const_mutex, Condvar, Mutex};
 use std::{cell::Cell, sync::Arc, time::Duration};
@@ -13,15 +13,27 @@ use windows::{
     Win32::{
         Foundation::*,
         Graphics::Gdi::ValidateRect,
-        System::{Com::*, LibraryLoader::GetModuleHandleW},
+        System::{
+            Com::*,
+            LibraryLoader::GetModuleHandleW,
+            Ole::{VT_BOOL, VT_BSTR, VT_R8},
+        },
         UI::{Accessibility::*, WindowsAndMessaging::*},
     },
 };
 
 use super::Manager;
+use crate::{
+    localizer::{DefaultLocalizer, Localizer},
+    util::VariantFactory,
+};
 
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
 
+// Posted by `Scope::update` to make the window thread apply the updates
+// that were queued from the test thread.
+const WM_PROCESS_QUEUED_UPDATES: u32 = WM_APP;
+
 lazy_static! {
     static ref WIN32_INSTANCE: HINSTANCE = {
         let instance = unsafe { GetModuleHandleW(None) };
@@ -67,6 +79,12 @@ lazy_static! {
     };
 }
 
+pub(crate) struct NullActionHandler;
+
+impl ActionHandler for NullActionHandler {
+    fn do_action(&self, _request: ActionRequest) {}
+}
+
 struct WindowState {
     manager: Manager,
     focus: Cell<NodeId>,
@@ -79,15 +97,13 @@ unsafe fn get_window_state(window: HWND) -> *const WindowState {
 fn update_focus(window: HWND, is_window_focused: bool) {
     let window_state = unsafe { &*get_window_state(window) };
     let update = TreeUpdate {
-        clear: None,
-        nodes: vec![],
-        tree: None,
         focus: is_window_focused.then(|| window_state.focus.get()),
+        ..Default::default()
     };
     window_state.manager.update(update);
 }
 
-struct WindowCreateParams(TreeUpdate, NodeId);
+struct WindowCreateParams(TreeUpdate, NodeId, Box<dyn Localizer>);
 
 extern "system" fn wndproc(window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
     match message as u32 {
@@ -95,8 +111,13 @@ extern "system" fn wndproc(window: HWND, message: u32, wparam: WPARAM, lparam: L
             let create_struct: &CREATESTRUCTW = unsafe { &mut *(lparam.0 as *mut _) };
             let create_params: Box<WindowCreateParams> =
                 unsafe { Box::from_raw(create_struct.lpCreateParams as _) };
-            let WindowCreateParams(initial_state, initial_focus) = *create_params;
-            let manager = Manager::new(window, initial_state);
+            let WindowCreateParams(initial_state, initial_focus, localizer) = *create_params;
+            let manager = Manager::with_localizer(
+                window,
+                initial_state,
+                Box::new(NullActionHandler),
+                localizer,
+            );
             let state = Box::new(WindowState {
                 manager,
                 focus: Cell::new(initial_focus),
@@ -135,12 +156,22 @@ extern "system" fn wndproc(window: HWND, message: u32, wparam: WPARAM, lparam: L
             update_focus(window, false);
             LRESULT(0)
         }
+        WM_PROCESS_QUEUED_UPDATES => {
+            let window_state = unsafe { &*get_window_state(window) };
+            window_state.manager.process_queued();
+            LRESULT(0)
+        }
         _ => unsafe { DefWindowProcW(window, message, wparam, lparam) },
     }
 }
 
-fn create_window(title: &str, initial_state: TreeUpdate, initial_focus: NodeId) -> Result<HWND> {
-    let create_params = Box::new(WindowCreateParams(initial_state, initial_focus));
+fn create_window(
+    title: &str,
+    initial_state: TreeUpdate,
+    initial_focus: NodeId,
+    localizer: Box<dyn Localizer>,
+) -> Result<HWND> {
+    let create_params = Box::new(WindowCreateParams(initial_state, initial_focus, localizer));
 
     let window = unsafe {
         CreateWindowExW(
@@ -175,6 +206,27 @@ impl Scope {
         unsafe { ShowWindow(self.window, SW_SHOW) };
         unsafe { SetForegroundWindow(self.window) };
     }
+
+    pub(crate) fn find_element_by_name(&self, name: &str) -> Result<IUIAutomationElement> {
+        let root = unsafe { self.uia.ElementFromHandle(self.window) }?;
+        let name: VARIANT = VariantFactory::from(name).into();
+        let condition = unsafe { self.uia.CreatePropertyCondition(UIA_NamePropertyId, name) }?;
+        unsafe { root.FindFirst(TreeScope_Descendants, condition) }
+    }
+
+    /// Applies the update on the window's thread, which raises
+    /// the corresponding UIA events.
+    pub(crate) fn update(&self, update: TreeUpdate) {
+        let window_state = unsafe { &*get_window_state(self.window) };
+        window_state.manager.queue_update(update);
+        unsafe { PostMessageW(self.window, WM_PROCESS_QUEUED_UPDATES, WPARAM(0), LPARAM(0)) }
+            .unwrap();
+    }
+
+    pub(crate) fn set_scale_factor(&self, factor: f32) {
+        let window_state = unsafe { &*get_window_state(self.window) };
+        window_state.manager.set_scale_factor(factor);
+    }
 }
 
 // It's not safe to run these UI-related tests concurrently.
@@ -186,6 +238,25 @@ pub(crate) fn scope<F>(
     initial_focus: NodeId,
     f: F,
 ) -> Result<()>
+where
+    F: FnOnce(&Scope) -> Result<()>,
+{
+    scope_with_localizer(
+        window_title,
+        initial_state,
+        initial_focus,
+        Box::new(DefaultLocalizer),
+        f,
+    )
+}
+
+pub(crate) fn scope_with_localizer<F>(
+    window_title: &str,
+    initial_state: TreeUpdate,
+    initial_focus: NodeId,
+    localizer: Box<dyn Localizer>,
+    f: F,
+) -> Result<()>
 where
     F: FnOnce(&Scope) -> Result<()>,
 {
@@ -203,7 +274,8 @@ where
             // initialized after the window is shown (as is the case,
             // at least on some Windows 10 machines, due to IME support).
 
-            let window = create_window(window_title, initial_state, initial_focus).unwrap();
+            let window =
+                create_window(window_title, initial_state, initial_focus, localizer).unwrap();
 
             {
                 let mut state = window_mutex.lock();
@@ -318,4 +390,183 @@ impl FocusEventHandler {
     }
 }
 
+// Only property values that are doubles, booleans, or strings are recorded,
+// since those are all that the tests need so far.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum ReceivedValue {
+    F64(f64),
+    Bool(bool),
+    String(String),
+    Other,
+}
+
+pub(crate) struct ReceivedPropertyChange {
+    mutex: Mutex<Option<(i32, ReceivedValue)>>,
+    cv: Condvar,
+}
+
+impl ReceivedPropertyChange {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            mutex: Mutex::new(None),
+            cv: Condvar::new(),
+        })
+    }
+
+    /// Waits for a change to the given property and returns the new value.
+    pub(crate) fn wait_for(&self, property_id: i32) -> ReceivedValue {
+        let mut received = self.mutex.lock();
+        loop {
+            if let Some((received_property_id, value)) = received.take() {
+                if received_property_id == property_id {
+                    return value;
+                }
+            }
+            let result = self.cv.wait_for(&mut received, DEFAULT_TIMEOUT);
+            assert!(!result.timed_out());
+        }
+    }
+
+    /// Waits for a change to the given property and returns the new value,
+    /// which must be a double.
+    pub(crate) fn wait_for_f64(&self, property_id: i32) -> f64 {
+        match self.wait_for(property_id) {
+            ReceivedValue::F64(value) => value,
+            value => panic!("expected a double, got {:?}", value),
+        }
+    }
+
+    /// Waits for a change to the given property and returns the new value,
+    /// which must be a boolean.
+    pub(crate) fn wait_for_bool(&self, property_id: i32) -> bool {
+        match self.wait_for(property_id) {
+            ReceivedValue::Bool(value) => value,
+            value => panic!("expected a boolean, got {:?}", value),
+        }
+    }
+
+    /// Waits for a change to the given property and returns the new value,
+    /// which must be a string.
+    pub(crate) fn wait_for_string(&self, property_id: i32) -> String {
+        match self.wait_for(property_id) {
+            ReceivedValue::String(value) => value,
+            value => panic!("expected a string, got {:?}", value),
+        }
+    }
+
+    fn put(&self, property_id: i32, value: ReceivedValue) {
+        let mut received = self.mutex.lock();
+        *received = Some((property_id, value));
+        self.cv.notify_one();
+    }
+}
+
+#[implement(Windows::Win32::UI::Accessibility::IUIAutomationPropertyChangedEventHandler)]
+pub(crate) struct PropertyChangedEventHandler {
+    received: Arc<ReceivedPropertyChange>,
+}
+
+#[allow(non_snake_case)]
+impl PropertyChangedEventHandler {
+    #[allow(clippy::new_ret_no_self)] // it does return self, but wrapped
+    pub(crate) fn new() -> (
+        IUIAutomationPropertyChangedEventHandler,
+        Arc<ReceivedPropertyChange>,
+    ) {
+        let received = ReceivedPropertyChange::new();
+        (
+            Self {
+                received: received.clone(),
+            }
+            .into(),
+            received,
+        )
+    }
+
+    fn HandlePropertyChangedEvent(
+        &self,
+        _sender: &Option<IUIAutomationElement>,
+        property_id: i32,
+        new_value: &VARIANT,
+    ) -> Result<()> {
+        let new_value = unsafe { &new_value.Anonymous.Anonymous };
+        let new_value = if new_value.vt == VT_R8.0 as u16 {
+            ReceivedValue::F64(unsafe { new_value.Anonymous.dblVal })
+        } else if new_value.vt == VT_BOOL.0 as u16 {
+            ReceivedValue::Bool(unsafe { new_value.Anonymous.boolVal } != 0)
+        } else if new_value.vt == VT_BSTR.0 as u16 {
+            ReceivedValue::String(unsafe { &new_value.Anonymous.bstrVal }.to_string())
+        } else {
+            ReceivedValue::Other
+        };
+        self.received.put(property_id, new_value);
+        Ok(())
+    }
+}
+
+pub(crate) struct ReceivedAutomationEvent {
+    mutex: Mutex<Option<i32>>,
+    cv: Condvar,
+}
+
+impl ReceivedAutomationEvent {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            mutex: Mutex::new(None),
+            cv: Condvar::new(),
+        })
+    }
+
+    /// Waits for the given event.
+    pub(crate) fn wait_for(&self, event_id: i32) {
+        let mut received = self.mutex.lock();
+        loop {
+            if received.take() == Some(event_id) {
+                return;
+            }
+            let result = self.cv.wait_for(&mut received, DEFAULT_TIMEOUT);
+            assert!(!result.timed_out());
+        }
+    }
+
+    fn put(&self, event_id: i32) {
+        let mut received = self.mutex.lock();
+        *received = Some(event_id);
+        self.cv.notify_one();
+    }
+}
+
+#[implement(Windows::Win32::UI::Accessibility::IUIAutomationEventHandler)]
+pub(crate) struct AutomationEventHandler {
+    received: Arc<ReceivedAutomationEvent>,
+}
+
+#[allow(non_snake_case)]
+impl AutomationEventHandler {
+    #[allow(clippy::new_ret_no_self)] // it does return self, but wrapped
+    pub(crate) fn new() -> (IUIAutomationEventHandler, Arc<ReceivedAutomationEvent>) {
+        let received = ReceivedAutomationEvent::new();
+        (
+            Self {
+                received: received.clone(),
+            }
+            .into(),
+            received,
+        )
+    }
+
+    fn HandleAutomationEvent(
+        &self,
+        _sender: &Option<IUIAutomationElement>,
+        event_id: i32,
+    ) -> Result<()> {
+        self.received.put(event_id);
+        Ok(())
+    }
+}
+
+mod init;
+mod properties;
+mod queue;
 mod simple;
+mod text;
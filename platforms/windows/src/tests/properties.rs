@@ -0,0 +1,1297 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use std::{convert::TryInto, num::NonZeroU64};
+
+use accesskit_schema::{
+    AriaCurrent, CheckedState, DefaultActionVerb, DropEffect, HasPopup, InvalidState, MarkerType,
+    Node, NodeId, Orientation, Rect, RelativeBounds, Role, SortDirection, StringEncoding,
+    TextMarker, Tree, TreeId, TreeUpdate,
+};
+use windows::core::*;
+
+use super::*;
+
+const WINDOW_TITLE: &str = "Properties test";
+
+const WINDOW_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+
+fn node_id(index: usize) -> NodeId {
+    NodeId(NonZeroU64::new(index as u64 + 2).unwrap())
+}
+
+// Each test builds a window whose children are the given nodes,
+// so that the nodes can be looked up by name through UIA.
+fn window_with_children(children: Vec<Node>) -> TreeUpdate {
+    let root = Node {
+        children: children.iter().map(|child| child.id).collect(),
+        name: Some(WINDOW_TITLE.into()),
+        ..Node::new(WINDOW_ID, Role::Window)
+    };
+    let mut nodes = vec![root];
+    nodes.extend(children);
+    TreeUpdate {
+        nodes,
+        tree: Some(Tree::new(
+            TreeId("test".into()),
+            WINDOW_ID,
+            StringEncoding::Utf8,
+        )),
+        ..Default::default()
+    }
+}
+
+fn aria_properties(s: &Scope, name: &str) -> Result<String> {
+    let element = s.find_element_by_name(name)?;
+    let properties = unsafe { element.CurrentAriaProperties() }?;
+    Ok(properties.try_into().unwrap())
+}
+
+#[test]
+fn has_popup() -> Result<()> {
+    let cases = [
+        ("No popup", None, ""),
+        ("True", Some(HasPopup::True), "haspopup=true"),
+        ("Menu", Some(HasPopup::Menu), "haspopup=menu"),
+        ("Listbox", Some(HasPopup::Listbox), "haspopup=listbox"),
+        ("Tree", Some(HasPopup::Tree), "haspopup=tree"),
+        ("Grid", Some(HasPopup::Grid), "haspopup=grid"),
+        ("Dialog", Some(HasPopup::Dialog), "haspopup=dialog"),
+    ];
+    let children = cases
+        .iter()
+        .enumerate()
+        .map(|(i, (name, has_popup, _))| Node {
+            name: Some((*name).into()),
+            has_popup: *has_popup,
+            ..Node::new(node_id(i), Role::Button)
+        })
+        .collect();
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, _, expected) in &cases {
+            assert_eq!(*expected, aria_properties(s, name)?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn aria_current() -> Result<()> {
+    let cases = [
+        ("Home", None, ""),
+        ("About", Some(AriaCurrent::False), ""),
+        ("Products", Some(AriaCurrent::Page), "current=page"),
+        ("Checkout", Some(AriaCurrent::Step), "current=step"),
+    ];
+    let children = cases
+        .iter()
+        .enumerate()
+        .map(|(i, (name, aria_current, _))| Node {
+            name: Some((*name).into()),
+            aria_current: *aria_current,
+            ..Node::new(node_id(i), Role::Link)
+        })
+        .collect();
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, _, expected) in &cases {
+            assert_eq!(*expected, aria_properties(s, name)?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn auto_complete() -> Result<()> {
+    let cases = [
+        ("No completion", None, ""),
+        ("Inline", Some("inline"), "autocomplete=inline"),
+        ("List", Some("list"), "autocomplete=list"),
+        ("Both", Some("both"), "autocomplete=both"),
+    ];
+    let children = cases
+        .iter()
+        .enumerate()
+        .map(|(i, (name, auto_complete, _))| Node {
+            name: Some((*name).into()),
+            auto_complete: auto_complete.map(Into::into),
+            ..Node::new(node_id(i), Role::SearchBox)
+        })
+        .collect();
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, _, expected) in &cases {
+            assert_eq!(*expected, aria_properties(s, name)?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn invalid_state() -> Result<()> {
+    let cases = [
+        ("Unset", None, true, ""),
+        ("False", Some(InvalidState::False), true, "invalid=false"),
+        ("True", Some(InvalidState::True), false, "invalid=true"),
+        (
+            "Spelling",
+            Some(InvalidState::Other("spelling".into())),
+            false,
+            "invalid=spelling",
+        ),
+    ];
+    let children = cases
+        .iter()
+        .enumerate()
+        .map(|(i, (name, invalid_state, _, _))| Node {
+            name: Some((*name).into()),
+            invalid_state: invalid_state.clone(),
+            ..Node::new(node_id(i), Role::TextField)
+        })
+        .collect();
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, _, expected_valid, expected_aria_properties) in &cases {
+            let element = s.find_element_by_name(name)?;
+            let is_valid: bool = unsafe { element.CurrentIsDataValidForForm() }?.into();
+            assert_eq!(*expected_valid, is_valid);
+            assert_eq!(*expected_aria_properties, aria_properties(s, name)?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn error_message() -> Result<()> {
+    const ERROR_TEXT: &str = "Enter a valid email address";
+    let children = vec![
+        Node {
+            name: Some("Email".into()),
+            invalid_state: Some(InvalidState::True),
+            error_message: Some(node_id(1)),
+            ..Node::new(node_id(0), Role::TextField)
+        },
+        Node {
+            name: Some(ERROR_TEXT.into()),
+            ..Node::new(node_id(1), Role::StaticText)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s
+            .find_element_by_name("Email")?
+            .cast::<IUIAutomationElement6>()?;
+        let description: String = unsafe { element.CurrentFullDescription() }?
+            .try_into()
+            .unwrap();
+        assert_eq!(ERROR_TEXT, description);
+        Ok(())
+    })
+}
+
+#[test]
+fn link_url() -> Result<()> {
+    const URL: &str = "https://example.com/";
+    let children = vec![
+        Node {
+            name: Some("Example".into()),
+            url: Some(URL.into()),
+            ..Node::new(node_id(0), Role::Link)
+        },
+        Node {
+            name: Some("Footnote".into()),
+            url: Some(URL.into()),
+            ..Node::new(node_id(1), Role::DocNoteRef)
+        },
+        Node {
+            name: Some("No target".into()),
+            ..Node::new(node_id(2), Role::Link)
+        },
+        Node {
+            name: Some("Picture".into()),
+            url: Some(URL.into()),
+            ..Node::new(node_id(3), Role::Image)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_description) in [
+            ("Example", URL),
+            ("Footnote", URL),
+            ("No target", ""),
+            ("Picture", ""),
+        ] {
+            let element = s
+                .find_element_by_name(name)?
+                .cast::<IUIAutomationElement6>()?;
+            let description: String = unsafe { element.CurrentFullDescription() }?
+                .try_into()
+                .unwrap();
+            assert_eq!(expected_description, description, "{}", name);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn abbreviation_expansion() -> Result<()> {
+    let children = vec![Node {
+        name: Some("HTML".into()),
+        tooltip: Some("HyperText Markup Language".into()),
+        ..Node::new(node_id(0), Role::Abbr)
+    }];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s.find_element_by_name("HTML")?;
+        let description: String = unsafe { element.CurrentFullDescription() }?
+            .try_into()
+            .unwrap();
+        assert_eq!("HyperText Markup Language", description);
+        Ok(())
+    })
+}
+
+#[test]
+fn inline_role_descriptions() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Found".into()),
+            role_description: Some("highlight".into()),
+            ..Node::new(node_id(0), Role::Mark)
+        },
+        Node {
+            name: Some("Really".into()),
+            role_description: Some("stress".into()),
+            ..Node::new(node_id(1), Role::Emphasis)
+        },
+        Node {
+            name: Some("Warning".into()),
+            role_description: Some("importance".into()),
+            ..Node::new(node_id(2), Role::Strong)
+        },
+        Node {
+            name: Some("main()".into()),
+            role_description: Some("snippet".into()),
+            ..Node::new(node_id(3), Role::Code)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected) in [
+            ("Found", "highlight"),
+            ("Really", "stress"),
+            ("Warning", "importance"),
+            ("main()", "snippet"),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            let control_type: String = unsafe { element.CurrentLocalizedControlType() }?
+                .try_into()
+                .unwrap();
+            assert_eq!(expected, control_type);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn disabled_ancestor() -> Result<()> {
+    let button = |id, name: &str| Node {
+        name: Some(name.into()),
+        focusable: true,
+        ..Node::new(id, Role::Button)
+    };
+    let children = vec![
+        Node {
+            name: Some("Disabled group".into()),
+            disabled: true,
+            children: Box::new([node_id(2)]),
+            ..Node::new(node_id(0), Role::Group)
+        },
+        button(node_id(1), "Outer button"),
+    ];
+    let mut initial_state = window_with_children(children);
+    initial_state.nodes.push(button(node_id(2), "Inner button"));
+    scope(WINDOW_TITLE, initial_state, node_id(1), |s| {
+        for (name, expected) in [
+            ("Disabled group", false),
+            ("Inner button", false),
+            ("Outer button", true),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            let enabled: bool = unsafe { element.CurrentIsEnabled() }?.into();
+            assert_eq!(expected, enabled, "{}", name);
+            let focusable: bool = unsafe { element.CurrentIsKeyboardFocusable() }?.into();
+            assert_eq!(expected, focusable, "{}", name);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn meter_and_progress_indicator() -> Result<()> {
+    let range = |id, name: &str, role| Node {
+        name: Some(name.into()),
+        value_for_range: Some(40.0),
+        min_value_for_range: Some(0.0),
+        max_value_for_range: Some(100.0),
+        ..Node::new(id, role)
+    };
+    let children = vec![
+        range(node_id(0), "Battery", Role::Meter),
+        range(node_id(1), "Download", Role::ProgressIndicator),
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, is_meter) in [("Battery", true), ("Download", false)] {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(UIA_ProgressBarControlTypeId, unsafe {
+                element.CurrentControlType()
+            }?);
+            let control_type: String = unsafe { element.CurrentLocalizedControlType() }?
+                .try_into()
+                .unwrap();
+            assert_eq!(is_meter, control_type == "meter", "{}", name);
+            let pattern = unsafe { element.GetCurrentPattern(UIA_RangeValuePatternId) }?
+                .cast::<IUIAutomationRangeValuePattern>()?;
+            assert_eq!(40.0, unsafe { pattern.CurrentValue() }?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn color_well_value() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Color".into()),
+            color_value: Some(0xFF80_00C0),
+            ..Node::new(node_id(0), Role::ColorWell)
+        },
+        Node {
+            name: Some("No color".into()),
+            ..Node::new(node_id(1), Role::ColorWell)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_value) in [("Color", "#FF8000C0"), ("No color", "")] {
+            let element = s.find_element_by_name(name)?;
+            let value = unsafe { element.GetCurrentPropertyValue(UIA_ValueValuePropertyId) }?;
+            let value = unsafe { &value.Anonymous.Anonymous.Anonymous.bstrVal };
+            assert_eq!(expected_value, value.to_string(), "{}", name);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn menu_item_accelerators() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Save".into()),
+            key_shortcuts: Some("Control+S Meta+S".into()),
+            ..Node::new(node_id(0), Role::MenuItem)
+        },
+        Node {
+            name: Some("Word wrap".into()),
+            key_shortcuts: Some("Alt+Z".into()),
+            ..Node::new(node_id(1), Role::MenuItemCheckBox)
+        },
+        Node {
+            name: Some("Zoom".into()),
+            ..Node::new(node_id(2), Role::MenuItemRadio)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected) in [
+            ("Save", "Control+S, Meta+S"),
+            ("Word wrap", "Alt+Z"),
+            ("Zoom", ""),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            let accelerator: String = unsafe { element.CurrentAcceleratorKey() }?
+                .try_into()
+                .unwrap();
+            assert_eq!(expected, accelerator);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn class_names() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Save".into()),
+            class_name: Some("toolbar-button".into()),
+            ..Node::new(node_id(0), Role::Button)
+        },
+        Node {
+            name: Some("Plain".into()),
+            ..Node::new(node_id(1), Role::Button)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected) in [("Save", "toolbar-button"), ("Plain", "")] {
+            let element = s.find_element_by_name(name)?;
+            let class_name: String = unsafe { element.CurrentClassName() }?.try_into().unwrap();
+            assert_eq!(expected, class_name);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn range_value_steps() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Explicit".into()),
+            value_for_range: Some(50.0),
+            step_value_for_range: Some(1.0),
+            large_step_value_for_range: Some(25.0),
+            ..Node::new(node_id(0), Role::Slider)
+        },
+        Node {
+            name: Some("Implicit".into()),
+            value_for_range: Some(50.0),
+            step_value_for_range: Some(2.0),
+            ..Node::new(node_id(1), Role::Slider)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_small, expected_large) in
+            [("Explicit", 1.0, 25.0), ("Implicit", 2.0, 20.0)]
+        {
+            let element = s.find_element_by_name(name)?;
+            let pattern = unsafe { element.GetCurrentPattern(UIA_RangeValuePatternId) }?
+                .cast::<IUIAutomationRangeValuePattern>()?;
+            assert_eq!(expected_small, unsafe { pattern.CurrentSmallChange() }?);
+            assert_eq!(expected_large, unsafe { pattern.CurrentLargeChange() }?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn horizontal_splitter() -> Result<()> {
+    let children = vec![Node {
+        name: Some("Sidebar width".into()),
+        focusable: true,
+        value_for_range: Some(30.0),
+        min_value_for_range: Some(10.0),
+        max_value_for_range: Some(90.0),
+        orientation: Some(Orientation::Horizontal),
+        ..Node::new(node_id(0), Role::Splitter)
+    }];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s.find_element_by_name("Sidebar width")?;
+        assert_eq!(OrientationType_Horizontal, unsafe {
+            element.CurrentOrientation()
+        }?);
+        let pattern = unsafe { element.GetCurrentPattern(UIA_RangeValuePatternId) }?
+            .cast::<IUIAutomationRangeValuePattern>()?;
+        assert_eq!(30.0, unsafe { pattern.CurrentValue() }?);
+        assert_eq!(10.0, unsafe { pattern.CurrentMinimum() }?);
+        assert_eq!(90.0, unsafe { pattern.CurrentMaximum() }?);
+        assert!(!unsafe { pattern.CurrentIsReadOnly() }?.as_bool());
+        Ok(())
+    })
+}
+
+#[test]
+fn read_only_and_disabled_ranges() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Read-only".into()),
+            value_for_range: Some(50.0),
+            read_only: true,
+            ..Node::new(node_id(0), Role::Slider)
+        },
+        Node {
+            name: Some("Disabled".into()),
+            value_for_range: Some(50.0),
+            disabled: true,
+            ..Node::new(node_id(1), Role::Slider)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for name in ["Read-only", "Disabled"] {
+            let element = s.find_element_by_name(name)?;
+            let pattern = unsafe { element.GetCurrentPattern(UIA_RangeValuePatternId) }?
+                .cast::<IUIAutomationRangeValuePattern>()?;
+            assert!(unsafe { pattern.CurrentIsReadOnly() }?.as_bool());
+            let error = unsafe { pattern.SetValue(10.0) }.unwrap_err();
+            assert_eq!(HRESULT(UIA_E_ELEMENTNOTENABLED), error.code());
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn determinate_and_indeterminate_progress() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Determinate".into()),
+            busy: true,
+            value_for_range: Some(40.0),
+            min_value_for_range: Some(0.0),
+            max_value_for_range: Some(100.0),
+            ..Node::new(node_id(0), Role::ProgressIndicator)
+        },
+        Node {
+            name: Some("Indeterminate".into()),
+            busy: true,
+            ..Node::new(node_id(1), Role::ProgressIndicator)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_available) in [("Determinate", true), ("Indeterminate", false)] {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(UIA_ProgressBarControlTypeId, unsafe {
+                element.CurrentControlType()
+            }?);
+            let available = unsafe {
+                element.GetCurrentPropertyValue(UIA_IsRangeValuePatternAvailablePropertyId)
+            }?;
+            let available = unsafe { available.Anonymous.Anonymous.Anonymous.boolVal } != 0;
+            assert_eq!(expected_available, available, "{}", name);
+        }
+        let pattern = unsafe {
+            s.find_element_by_name("Determinate")?
+                .GetCurrentPattern(UIA_RangeValuePatternId)
+        }?
+        .cast::<IUIAutomationRangeValuePattern>()?;
+        assert_eq!(40.0, unsafe { pattern.CurrentValue() }?);
+        Ok(())
+    })
+}
+
+#[test]
+fn input_types() -> Result<()> {
+    let text_field = |index, name: &str, input_type: Option<&str>| Node {
+        name: Some(name.into()),
+        input_type: input_type.map(Into::into),
+        ..Node::new(node_id(index), Role::TextField)
+    };
+    let children = vec![
+        text_field(0, "Email", Some("email")),
+        text_field(1, "Password", Some("password")),
+        Node {
+            protected: true,
+            ..text_field(2, "PIN", None)
+        },
+        text_field(3, "Quantity", Some("number")),
+        text_field(4, "Volume", Some("range")),
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_control_type, expected_password) in [
+            ("Email", UIA_EditControlTypeId, false),
+            ("Password", UIA_EditControlTypeId, true),
+            ("PIN", UIA_EditControlTypeId, true),
+            ("Quantity", UIA_SpinnerControlTypeId, false),
+            ("Volume", UIA_SliderControlTypeId, false),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(expected_control_type, unsafe {
+                element.CurrentControlType()
+            }?);
+            assert_eq!(
+                expected_password,
+                unsafe { element.CurrentIsPassword() }?.as_bool(),
+                "{}",
+                name
+            );
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn drag_and_drop() -> Result<()> {
+    let item = |index, name: &str, grabbed| Node {
+        name: Some(name.into()),
+        grabbed,
+        ..Node::new(node_id(index), Role::ListItem)
+    };
+    let children = vec![
+        item(0, "Grabbed", Some(true)),
+        item(1, "Not grabbed", Some(false)),
+        item(2, "Not draggable", None),
+        Node {
+            name: Some("Trash".into()),
+            drop_effects: DropEffect::Move | DropEffect::Copy,
+            ..Node::new(node_id(3), Role::Button)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_grabbed) in [("Grabbed", true), ("Not grabbed", false)] {
+            let pattern = unsafe {
+                s.find_element_by_name(name)?
+                    .GetCurrentPattern(UIA_DragPatternId)
+            }?
+            .cast::<IUIAutomationDragPattern>()?;
+            assert_eq!(
+                expected_grabbed,
+                unsafe { pattern.CurrentIsGrabbed() }?.as_bool(),
+                "{}",
+                name
+            );
+        }
+        assert!(unsafe {
+            s.find_element_by_name("Not draggable")?
+                .GetCurrentPattern(UIA_DragPatternId)
+        }
+        .is_err());
+
+        let pattern = unsafe {
+            s.find_element_by_name("Trash")?
+                .GetCurrentPattern(UIA_DropTargetPatternId)
+        }?
+        .cast::<IUIAutomationDropTargetPattern>()?;
+        let effect = unsafe { pattern.CurrentDropTargetEffect() }?;
+        assert_eq!("copy", effect.to_string());
+        Ok(())
+    })
+}
+
+#[test]
+fn annotation_types() -> Result<()> {
+    let annotation = |index, name: &str, role| Node {
+        name: Some(name.into()),
+        ..Node::new(node_id(index), role)
+    };
+    let children = vec![
+        Node {
+            details: Box::new([node_id(1)]),
+            ..annotation(0, "Commented text", Role::StaticText)
+        },
+        annotation(1, "Comment", Role::Comment),
+        annotation(2, "Insertion", Role::ContentInsertion),
+        annotation(3, "Deletion", Role::ContentDeletion),
+        annotation(4, "Suggestion", Role::Suggestion),
+        annotation(5, "Highlight", Role::Mark),
+        Node {
+            markers: Box::new([TextMarker {
+                marker_type: MarkerType::ActiveSuggestion,
+                range: 0..9,
+            }]),
+            ..annotation(6, "Suggested", Role::Mark)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_type) in [
+            ("Comment", AnnotationType_Comment),
+            ("Insertion", AnnotationType_InsertionChange),
+            ("Deletion", AnnotationType_DeletionChange),
+            ("Suggestion", AnnotationType_TrackChanges),
+            ("Highlight", AnnotationType_Highlighted),
+            ("Suggested", AnnotationType_TrackChanges),
+        ] {
+            let pattern = unsafe {
+                s.find_element_by_name(name)?
+                    .GetCurrentPattern(UIA_AnnotationPatternId)
+            }?
+            .cast::<IUIAutomationAnnotationPattern>()?;
+            assert_eq!(
+                expected_type,
+                unsafe { pattern.CurrentAnnotationTypeId() }?,
+                "{}",
+                name
+            );
+        }
+        assert!(unsafe {
+            s.find_element_by_name("Commented text")?
+                .GetCurrentPattern(UIA_AnnotationPatternId)
+        }
+        .is_err());
+
+        let pattern = unsafe {
+            s.find_element_by_name("Comment")?
+                .GetCurrentPattern(UIA_AnnotationPatternId)
+        }?
+        .cast::<IUIAutomationAnnotationPattern>()?;
+        let target = unsafe { pattern.CurrentTarget() }?;
+        let target_name: String = unsafe { target.CurrentName() }?.try_into().unwrap();
+        assert_eq!("Commented text", target_name);
+        Ok(())
+    })
+}
+
+#[test]
+fn details() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Ticket".into()),
+            details: Box::new([node_id(1), node_id(2), node_id(9)]),
+            ..Node::new(node_id(0), Role::Button)
+        },
+        Node {
+            name: Some("Booking details".into()),
+            ..Node::new(node_id(1), Role::Group)
+        },
+        Node {
+            name: Some("Reviewer comment".into()),
+            ..Node::new(node_id(2), Role::Comment)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s.find_element_by_name("Ticket")?;
+        let described_by = unsafe { element.CurrentDescribedBy() }?;
+        assert_eq!(1, unsafe { described_by.Length() }?);
+        let name: String = unsafe { described_by.GetElement(0)?.CurrentName() }?
+            .try_into()
+            .unwrap();
+        assert_eq!("Booking details", name);
+
+        let element = element.cast::<IUIAutomationElement5>()?;
+        let annotations = unsafe { element.CurrentAnnotationObjects() }?;
+        assert_eq!(1, unsafe { annotations.Length() }?);
+        let name: String = unsafe { annotations.GetElement(0)?.CurrentName() }?
+            .try_into()
+            .unwrap();
+        assert_eq!("Reviewer comment", name);
+        Ok(())
+    })
+}
+
+#[test]
+fn drop_effect_names() {
+    use crate::node::drop_effect_name;
+    let names: Vec<&str> = [
+        DropEffect::Copy,
+        DropEffect::Execute,
+        DropEffect::Link,
+        DropEffect::Move,
+        DropEffect::Popup,
+    ]
+    .iter()
+    .map(|effect| drop_effect_name(*effect))
+    .collect();
+    assert_eq!(vec!["copy", "execute", "link", "move", "popup"], names);
+}
+
+#[test]
+fn window_and_application_control_types() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Dialog".into()),
+            ..Node::new(node_id(0), Role::Window)
+        },
+        Node {
+            name: Some("Canvas".into()),
+            ..Node::new(node_id(1), Role::Application)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_control_type) in [
+            (WINDOW_TITLE, UIA_WindowControlTypeId),
+            ("Dialog", UIA_PaneControlTypeId),
+            ("Canvas", UIA_PaneControlTypeId),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(
+                expected_control_type,
+                unsafe { element.CurrentControlType() }?,
+                "{}",
+                name
+            );
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn graphics_control_types() -> Result<()> {
+    let cases = [
+        ("Sales chart", Role::SvgRoot, UIA_DocumentControlTypeId),
+        (
+            "Org chart",
+            Role::GraphicsDocument,
+            UIA_DocumentControlTypeId,
+        ),
+        ("Quarter", Role::GraphicsObject, UIA_GroupControlTypeId),
+        (
+            "Legend marker",
+            Role::GraphicsSymbol,
+            UIA_ImageControlTypeId,
+        ),
+    ];
+    let children = cases
+        .iter()
+        .enumerate()
+        .map(|(i, (name, role, _))| Node {
+            name: Some((*name).into()),
+            ..Node::new(node_id(i), *role)
+        })
+        .collect();
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, _, expected_control_type) in cases {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(
+                expected_control_type,
+                unsafe { element.CurrentControlType() }?,
+                "{}",
+                name
+            );
+            let is_content: bool = unsafe { element.CurrentIsContentElement() }?.into();
+            assert!(is_content, "{}", name);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn web_area_is_document() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Page".into()),
+            children: Box::new([node_id(1)]),
+            ..Node::new(node_id(0), Role::RootWebArea)
+        },
+        Node {
+            name: Some("Hello".into()),
+            ..Node::new(node_id(1), Role::StaticText)
+        },
+    ];
+    let mut initial_state = window_with_children(children);
+    // Only the web area is a direct child of the window.
+    initial_state.nodes[0].children = Box::new([node_id(0)]);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_control_type) in [
+            ("Page", UIA_DocumentControlTypeId),
+            ("Hello", UIA_TextControlTypeId),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(expected_control_type, unsafe {
+                element.CurrentControlType()
+            }?);
+            assert!(unsafe { element.CurrentIsContentElement() }?.as_bool());
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn tree_item_position() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Tree".into()),
+            children: Box::new([node_id(1)]),
+            ..Node::new(node_id(0), Role::Tree)
+        },
+        Node {
+            name: Some("Parent".into()),
+            hierarchical_level: Some(1),
+            children: Box::new([node_id(2)]),
+            ..Node::new(node_id(1), Role::TreeItem)
+        },
+        Node {
+            children: Box::new([node_id(3), node_id(4)]),
+            ..Node::new(node_id(2), Role::Group)
+        },
+        Node {
+            name: Some("Explicit".into()),
+            hierarchical_level: Some(2),
+            pos_in_set: Some(3),
+            set_size: Some(5),
+            ..Node::new(node_id(3), Role::TreeItem)
+        },
+        Node {
+            name: Some("Computed".into()),
+            hierarchical_level: Some(2),
+            ..Node::new(node_id(4), Role::TreeItem)
+        },
+    ];
+    let mut initial_state = window_with_children(children);
+    // Only the tree is a direct child of the window.
+    initial_state.nodes[0].children = Box::new([node_id(0)]);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_level, expected_position, expected_size) in [
+            ("Parent", 1, 1, 1),
+            ("Explicit", 2, 3, 5),
+            ("Computed", 2, 2, 2),
+        ] {
+            let element = s
+                .find_element_by_name(name)?
+                .cast::<IUIAutomationElement4>()?;
+            assert_eq!(expected_level, unsafe { element.CurrentLevel() }?);
+            assert_eq!(expected_position, unsafe {
+                element.CurrentPositionInSet()
+            }?);
+            assert_eq!(expected_size, unsafe { element.CurrentSizeOfSet() }?);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn scroll_percent_changed() -> Result<()> {
+    let scroller = |scroll_y| Node {
+        name: Some("Scroller".into()),
+        scroll_y: Some(scroll_y),
+        scroll_y_min: Some(0.0),
+        scroll_y_max: Some(200.0),
+        ..Node::new(node_id(0), Role::ScrollView)
+    };
+    let initial_state = window_with_children(vec![scroller(0.0)]);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s.find_element_by_name("Scroller")?;
+        let (handler, received) = PropertyChangedEventHandler::new();
+        let property_ids = [UIA_ScrollVerticalScrollPercentPropertyId];
+        unsafe {
+            s.uia.AddPropertyChangedEventHandlerNativeArray(
+                element,
+                TreeScope_Element,
+                None,
+                handler,
+                property_ids.as_ptr(),
+                property_ids.len() as i32,
+            )
+        }?;
+
+        s.update(TreeUpdate {
+            nodes: vec![scroller(50.0)],
+            ..Default::default()
+        });
+        assert_eq!(
+            25.0,
+            received.wait_for_f64(UIA_ScrollVerticalScrollPercentPropertyId)
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn legacy_accessible_role_and_state() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Button".into()),
+            focusable: true,
+            default_action_verb: Some(DefaultActionVerb::Press),
+            ..Node::new(node_id(0), Role::Button)
+        },
+        Node {
+            name: Some("Checked".into()),
+            focusable: true,
+            checked_state: Some(CheckedState::True),
+            ..Node::new(node_id(1), Role::CheckBox)
+        },
+        Node {
+            name: Some("Mixed".into()),
+            disabled: true,
+            checked_state: Some(CheckedState::Mixed),
+            ..Node::new(node_id(2), Role::CheckBox)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_role, expected_state, expected_action) in [
+            (
+                "Button",
+                ROLE_SYSTEM_PUSHBUTTON,
+                STATE_SYSTEM_FOCUSABLE,
+                "press",
+            ),
+            (
+                "Checked",
+                ROLE_SYSTEM_CHECKBUTTON,
+                STATE_SYSTEM_FOCUSABLE | STATE_SYSTEM_CHECKED,
+                "",
+            ),
+            (
+                "Mixed",
+                ROLE_SYSTEM_CHECKBUTTON,
+                STATE_SYSTEM_UNAVAILABLE | STATE_SYSTEM_MIXED,
+                "",
+            ),
+        ] {
+            let element = s.find_element_by_name(name)?;
+            let pattern = unsafe { element.GetCurrentPattern(UIA_LegacyIAccessiblePatternId) }?
+                .cast::<IUIAutomationLegacyIAccessiblePattern>()?;
+            assert_eq!(expected_role, unsafe { pattern.CurrentRole() }?, "{}", name);
+            assert_eq!(
+                expected_state,
+                unsafe { pattern.CurrentState() }?,
+                "{}",
+                name
+            );
+            let name_value: String = unsafe { pattern.CurrentName() }?.try_into().unwrap();
+            assert_eq!(name, name_value);
+            let action: String = unsafe { pattern.CurrentDefaultAction() }?
+                .try_into()
+                .unwrap();
+            assert_eq!(expected_action, action, "{}", name);
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn sort_direction() -> Result<()> {
+    let header = |sort_direction| Node {
+        name: Some("Name".into()),
+        sort_direction: Some(sort_direction),
+        ..Node::new(node_id(0), Role::ColumnHeader)
+    };
+    let initial_state = window_with_children(vec![header(SortDirection::Unsorted)]);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s.find_element_by_name("Name")?;
+        let status: String = unsafe { element.CurrentItemStatus() }?.try_into().unwrap();
+        assert_eq!("", status);
+        assert_eq!("sort=none", aria_properties(s, "Name")?);
+
+        let (handler, received) = PropertyChangedEventHandler::new();
+        let property_ids = [UIA_ItemStatusPropertyId];
+        unsafe {
+            s.uia.AddPropertyChangedEventHandlerNativeArray(
+                element,
+                TreeScope_Element,
+                None,
+                handler,
+                property_ids.as_ptr(),
+                property_ids.len() as i32,
+            )
+        }?;
+
+        for (sort_direction, expected_status) in [
+            (SortDirection::Ascending, "ascending"),
+            (SortDirection::Descending, "descending"),
+            (SortDirection::Other, "other"),
+        ] {
+            s.update(TreeUpdate {
+                nodes: vec![header(sort_direction)],
+                ..Default::default()
+            });
+            assert_eq!(
+                expected_status,
+                received.wait_for_string(UIA_ItemStatusPropertyId)
+            );
+            assert_eq!(
+                format!("sort={}", expected_status),
+                aria_properties(s, "Name")?
+            );
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn keyboard_focus() -> Result<()> {
+    let button = |index, name: &str| Node {
+        name: Some(name.into()),
+        focusable: true,
+        ..Node::new(node_id(index), Role::Button)
+    };
+    let children = vec![
+        button(0, "First"),
+        button(1, "Second"),
+        Node {
+            disabled: true,
+            ..button(2, "Disabled")
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        for (name, expected_focusable) in [("First", true), ("Second", true), ("Disabled", false)] {
+            let element = s.find_element_by_name(name)?;
+            assert_eq!(
+                expected_focusable,
+                unsafe { element.CurrentIsKeyboardFocusable() }?.as_bool(),
+                "{}",
+                name
+            );
+        }
+
+        let element = s.find_element_by_name("Second")?;
+        assert!(!unsafe { element.CurrentHasKeyboardFocus() }?.as_bool());
+        let (handler, received) = PropertyChangedEventHandler::new();
+        let property_ids = [UIA_HasKeyboardFocusPropertyId];
+        unsafe {
+            s.uia.AddPropertyChangedEventHandlerNativeArray(
+                element.clone(),
+                TreeScope_Element,
+                None,
+                handler,
+                property_ids.as_ptr(),
+                property_ids.len() as i32,
+            )
+        }?;
+
+        s.update(TreeUpdate {
+            focus: Some(node_id(1)),
+            ..Default::default()
+        });
+        assert!(received.wait_for_bool(UIA_HasKeyboardFocusPropertyId));
+        assert!(unsafe { element.CurrentHasKeyboardFocus() }?.as_bool());
+        Ok(())
+    })
+}
+
+struct TestLocalizer;
+
+impl Localizer for TestLocalizer {
+    fn role_description(&self, role: Role) -> Option<String> {
+        (role == Role::CheckBox).then(|| "toggle".into())
+    }
+
+    fn checked_state_description(&self, state: CheckedState) -> Option<String> {
+        (state == CheckedState::True).then(|| "on".into())
+    }
+
+    fn default_action_verb(&self, verb: DefaultActionVerb) -> Option<String> {
+        (verb == DefaultActionVerb::Press).then(|| "appuyer".into())
+    }
+}
+
+#[test]
+fn default_action() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Pressable".into()),
+            default_action_verb: Some(DefaultActionVerb::Press),
+            ..Node::new(node_id(0), Role::Button)
+        },
+        Node {
+            name: Some("Link".into()),
+            default_action_verb: Some(DefaultActionVerb::Jump),
+            ..Node::new(node_id(1), Role::Link)
+        },
+        Node {
+            name: Some("No action".into()),
+            ..Node::new(node_id(2), Role::StaticText)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope_with_localizer(
+        WINDOW_TITLE,
+        initial_state,
+        node_id(0),
+        Box::new(TestLocalizer),
+        |s| {
+            for (name, expected) in [
+                ("Pressable", "appuyer"),
+                ("Link", "jump"),
+                ("No action", ""),
+            ] {
+                let element = s.find_element_by_name(name)?;
+                let value = unsafe {
+                    element.GetCurrentPropertyValue(UIA_LegacyIAccessibleDefaultActionPropertyId)
+                }?;
+                let value = unsafe { &value.Anonymous.Anonymous.Anonymous.bstrVal };
+                assert_eq!(expected, value.to_string(), "{}", name);
+            }
+            Ok(())
+        },
+    )
+}
+
+#[test]
+fn huge_counts_are_clamped() -> Result<()> {
+    let children = vec![Node {
+        name: Some("Huge".into()),
+        hierarchical_level: Some(usize::MAX),
+        pos_in_set: Some(usize::MAX),
+        set_size: Some(usize::MAX),
+        ..Node::new(node_id(0), Role::TreeItem)
+    }];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s
+            .find_element_by_name("Huge")?
+            .cast::<IUIAutomationElement4>()?;
+        assert_eq!(i32::MAX, unsafe { element.CurrentLevel() }?);
+        assert_eq!(i32::MAX, unsafe { element.CurrentPositionInSet() }?);
+        assert_eq!(i32::MAX, unsafe { element.CurrentSizeOfSet() }?);
+        Ok(())
+    })
+}
+
+#[test]
+fn scaled_bounding_rectangle() -> Result<()> {
+    let children = vec![Node {
+        name: Some("Scaled".into()),
+        bounds: Some(RelativeBounds {
+            offset_container: None,
+            rect: Rect {
+                left: 10.0,
+                top: 20.0,
+                width: 30.0,
+                height: 40.0,
+            },
+            transform: None,
+        }),
+        ..Node::new(node_id(0), Role::Button)
+    }];
+    let initial_state = window_with_children(children);
+    scope(WINDOW_TITLE, initial_state, node_id(0), |s| {
+        let element = s.find_element_by_name("Scaled")?;
+        let unscaled = unsafe { element.CurrentBoundingRectangle() }?;
+        assert_eq!(30, unscaled.right - unscaled.left);
+        assert_eq!(40, unscaled.bottom - unscaled.top);
+
+        s.set_scale_factor(1.5);
+        let scaled = unsafe { element.CurrentBoundingRectangle() }?;
+        assert_eq!(45, scaled.right - scaled.left);
+        assert_eq!(60, scaled.bottom - scaled.top);
+        // The window's client area doesn't move, so only the node's
+        // offset within it is scaled.
+        assert_eq!(5, scaled.left - unscaled.left);
+        assert_eq!(10, scaled.top - unscaled.top);
+        Ok(())
+    })
+}
+
+#[test]
+fn localized_descriptions() -> Result<()> {
+    let children = vec![
+        Node {
+            name: Some("Custom".into()),
+            checked_state: Some(CheckedState::True),
+            checked_state_description: Some("selected".into()),
+            role_description: Some("tick box".into()),
+            ..Node::new(node_id(0), Role::CheckBox)
+        },
+        Node {
+            name: Some("Default".into()),
+            checked_state: Some(CheckedState::True),
+            ..Node::new(node_id(1), Role::CheckBox)
+        },
+    ];
+    let initial_state = window_with_children(children);
+    scope_with_localizer(
+        WINDOW_TITLE,
+        initial_state,
+        node_id(0),
+        Box::new(TestLocalizer),
+        |s| {
+            for (name, expected_type, expected_status) in [
+                ("Custom", "tick box", "selected"),
+                ("Default", "toggle", "on"),
+            ] {
+                let element = s.find_element_by_name(name)?;
+                let control_type: String = unsafe { element.CurrentLocalizedControlType() }?
+                    .try_into()
+                    .unwrap();
+                assert_eq!(expected_type, control_type);
+                let status: String = unsafe { element.CurrentItemStatus() }?.try_into().unwrap();
+                assert_eq!(expected_status, status);
+            }
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,72 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use std::{num::NonZeroU64, sync::Arc, thread};
+
+use accesskit_schema::{Node, NodeId, Role, StringEncoding, Tree, TreeId, TreeUpdate};
+use windows::Win32::Foundation::*;
+
+use super::*;
+
+const ROOT_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+const FIELD_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+
+fn field(value: &str) -> Node {
+    Node {
+        value: Some(value.into()),
+        ..Node::new(FIELD_ID, Role::TextField)
+    }
+}
+
+// Each update after the first depends on the field added by the first one,
+// so applying them out of order would fail. The updates only change
+// properties that don't raise UIA events, so no window is needed.
+#[test]
+fn queued_updates_are_applied_in_order() {
+    let initial_state = TreeUpdate {
+        nodes: vec![Node::new(ROOT_ID, Role::Window)],
+        tree: Some(Tree::new(
+            TreeId("test".into()),
+            ROOT_ID,
+            StringEncoding::Utf8,
+        )),
+        ..Default::default()
+    };
+    let manager = Arc::new(Manager::new(HWND(0), initial_state));
+
+    let queueing_manager = Arc::clone(&manager);
+    thread::spawn(move || {
+        queueing_manager.queue_update(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([FIELD_ID]),
+                    ..Node::new(ROOT_ID, Role::Window)
+                },
+                field("1"),
+            ],
+            ..Default::default()
+        });
+        for value in ["2", "3"] {
+            queueing_manager.queue_update(TreeUpdate {
+                nodes: vec![field(value)],
+                ..Default::default()
+            });
+        }
+    })
+    .join()
+    .unwrap();
+
+    assert!(manager
+        .get_or_create_tree()
+        .read()
+        .node_by_id(FIELD_ID)
+        .is_none());
+    manager.process_queued();
+    let tree = manager.get_or_create_tree();
+    let reader = tree.read();
+    let field = reader.node_by_id(FIELD_ID).unwrap();
+    assert_eq!(Some("3"), field.data().value.as_deref());
+    assert_eq!(Some(ROOT_ID), field.parent().map(|parent| parent.id()));
+}
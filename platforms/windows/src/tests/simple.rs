@@ -33,14 +33,13 @@ fn get_initial_state() -> TreeUpdate {
     let button_1 = make_button(BUTTON_1_ID, "Button 1");
     let button_2 = make_button(BUTTON_2_ID, "Button 2");
     TreeUpdate {
-        clear: None,
         nodes: vec![root, button_1, button_2],
         tree: Some(Tree::new(
             TreeId("test".into()),
             WINDOW_ID,
             StringEncoding::Utf8,
         )),
-        focus: None,
+        ..Default::default()
     }
 }
 
@@ -68,6 +67,40 @@ fn is_button_2(element: &IUIAutomationElement) -> bool {
     is_button_named(element, "Button 2")
 }
 
+#[test]
+fn tooltip_raises_events() -> Result<()> {
+    const TOOLTIP_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+    let tooltip = |invisible| Node {
+        name: Some("Tooltip".into()),
+        invisible,
+        ..Node::new(TOOLTIP_ID, Role::Tooltip)
+    };
+    let mut initial_state = get_initial_state();
+    initial_state.nodes[0].children = Box::new([BUTTON_1_ID, BUTTON_2_ID, TOOLTIP_ID]);
+    initial_state.nodes[1].described_by = Box::new([TOOLTIP_ID]);
+    initial_state.nodes.push(tooltip(true));
+    scope(WINDOW_TITLE, initial_state, BUTTON_1_ID, |s| {
+        let root = unsafe { s.uia.ElementFromHandle(s.window) }?;
+        for (invisible, event_id) in [
+            (false, UIA_ToolTipOpenedEventId),
+            (true, UIA_ToolTipClosedEventId),
+        ] {
+            let (handler, received) = AutomationEventHandler::new();
+            unsafe {
+                s.uia
+                    .AddAutomationEventHandler(event_id, &root, TreeScope_Subtree, None, handler)
+            }?;
+            s.update(TreeUpdate {
+                nodes: vec![tooltip(invisible)],
+                focus: Some(BUTTON_1_ID),
+                ..Default::default()
+            });
+            received.wait_for(event_id);
+        }
+        Ok(())
+    })
+}
+
 #[test]
 fn navigation() -> Result<()> {
     scope(WINDOW_TITLE, get_initial_state(), BUTTON_1_ID, |s| {
@@ -0,0 +1,341 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+use std::num::NonZeroU64;
+
+use accesskit_consumer::Tree as ConsumerTree;
+use accesskit_schema::{
+    Node, NodeId, Rect, RelativeBounds, Role, StringEncoding, TextSelection, Tree, TreeId,
+    TreeUpdate,
+};
+use windows::{
+    core::*,
+    Win32::{
+        System::Ole::{VT_I4, VT_UNKNOWN},
+        UI::Accessibility::*,
+    },
+};
+
+use super::*;
+use crate::text::{range_attribute_value, RangeAttributeValue, TextAttributeValue};
+
+const PARAGRAPH_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+const BOLD_RUN_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(5) });
+const PLAIN_RUN_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(6) });
+
+fn run(id: NodeId, text: &str, bold: bool) -> Node {
+    Node {
+        name: Some(text.into()),
+        font_family: Some("Segoe UI".into()),
+        font_size: Some(16.0),
+        foreground_color: Some(0xff000080),
+        bold,
+        ..Node::new(id, Role::InlineTextBox)
+    }
+}
+
+// These tests don't need a window; the text attribute logic only
+// depends on the tree.
+fn bold_and_plain_runs() -> std::sync::Arc<ConsumerTree> {
+    ConsumerTree::new(TreeUpdate {
+        nodes: vec![
+            Node {
+                children: Box::new([BOLD_RUN_ID, PLAIN_RUN_ID]),
+                ..Node::new(PARAGRAPH_ID, Role::Paragraph)
+            },
+            run(BOLD_RUN_ID, "Hello ", true),
+            run(PLAIN_RUN_ID, "world", false),
+        ],
+        tree: Some(Tree::new(
+            TreeId("test".into()),
+            PARAGRAPH_ID,
+            StringEncoding::Utf8,
+        )),
+        ..Default::default()
+    })
+}
+
+#[test]
+fn range_spanning_bold_and_plain_text() {
+    let tree = bold_and_plain_runs();
+    let reader = tree.read();
+    let runs = || reader.root().children();
+    assert_eq!(
+        RangeAttributeValue::Mixed,
+        range_attribute_value(runs(), UIA_FontWeightAttributeId)
+    );
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::String("Segoe UI".into())),
+        range_attribute_value(runs(), UIA_FontNameAttributeId)
+    );
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::Float(12.0)),
+        range_attribute_value(runs(), UIA_FontSizeAttributeId)
+    );
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::Bool(false)),
+        range_attribute_value(runs(), UIA_IsItalicAttributeId)
+    );
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::Int(TextDecorationLineStyle_None)),
+        range_attribute_value(runs(), UIA_StrikethroughStyleAttributeId)
+    );
+    // Red 0xff, green 0, blue 0 in RGBA becomes the COLORREF 0x000000ff.
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::Int(0xff)),
+        range_attribute_value(runs(), UIA_ForegroundColorAttributeId)
+    );
+    assert_eq!(
+        RangeAttributeValue::NotSupported,
+        range_attribute_value(runs(), UIA_BackgroundColorAttributeId)
+    );
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::Bool(true)),
+        range_attribute_value(runs(), UIA_IsReadOnlyAttributeId)
+    );
+}
+
+#[test]
+fn range_within_bold_text() {
+    let tree = bold_and_plain_runs();
+    let reader = tree.read();
+    let bold_run = reader.node_by_id(BOLD_RUN_ID).unwrap();
+    assert_eq!(
+        RangeAttributeValue::Value(TextAttributeValue::Int(700)),
+        range_attribute_value([bold_run], UIA_FontWeightAttributeId)
+    );
+}
+
+const WINDOW_TITLE: &str = "Text events test";
+
+const WINDOW_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(1) });
+const TEXT_FIELD_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(2) });
+const TEXT_BOX_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(3) });
+const CARET_ID: NodeId = NodeId(unsafe { NonZeroU64::new_unchecked(4) });
+
+#[test]
+fn read_only_text_field() {
+    let tree = |read_only| {
+        ConsumerTree::new(TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([TEXT_BOX_ID]),
+                    read_only,
+                    ..Node::new(TEXT_FIELD_ID, Role::TextField)
+                },
+                Node::new(TEXT_BOX_ID, Role::InlineTextBox),
+            ],
+            tree: Some(Tree::new(
+                TreeId("test".into()),
+                TEXT_FIELD_ID,
+                StringEncoding::Utf8,
+            )),
+            ..Default::default()
+        })
+    };
+    for read_only in [false, true] {
+        let tree = tree(read_only);
+        let reader = tree.read();
+        assert_eq!(
+            RangeAttributeValue::Value(TextAttributeValue::Bool(read_only)),
+            range_attribute_value(reader.root().children(), UIA_IsReadOnlyAttributeId)
+        );
+    }
+}
+
+fn text_field(text: &str, selection_offset: usize) -> Node {
+    Node {
+        name: Some("Input".into()),
+        value: Some(text.into()),
+        editable: true,
+        focusable: true,
+        text_selection: Some(TextSelection {
+            anchor_node: TEXT_BOX_ID,
+            anchor_offset: selection_offset,
+            focus_node: TEXT_BOX_ID,
+            focus_offset: selection_offset,
+        }),
+        children: Box::new([TEXT_BOX_ID]),
+        ..Node::new(TEXT_FIELD_ID, Role::TextField)
+    }
+}
+
+fn text_box(text: &str) -> Node {
+    Node {
+        name: Some(text.into()),
+        ..Node::new(TEXT_BOX_ID, Role::InlineTextBox)
+    }
+}
+
+fn text_field_in_window() -> TreeUpdate {
+    TreeUpdate {
+        nodes: vec![
+            Node {
+                name: Some(WINDOW_TITLE.into()),
+                children: Box::new([TEXT_FIELD_ID]),
+                ..Node::new(WINDOW_ID, Role::Window)
+            },
+            text_field("Hello", 0),
+            text_box("Hello"),
+        ],
+        tree: Some(Tree::new(
+            TreeId("test".into()),
+            WINDOW_ID,
+            StringEncoding::Utf8,
+        )),
+        focus: Some(TEXT_FIELD_ID),
+        ..Default::default()
+    }
+}
+
+fn subscribe(s: &Scope, event_id: i32) -> Result<Arc<ReceivedAutomationEvent>> {
+    let element = s.find_element_by_name("Input")?;
+    let (handler, received) = AutomationEventHandler::new();
+    unsafe {
+        s.uia
+            .AddAutomationEventHandler(event_id, element, TreeScope_Element, None, handler)
+    }?;
+    Ok(received)
+}
+
+#[test]
+fn selection_change_raises_event() -> Result<()> {
+    scope(WINDOW_TITLE, text_field_in_window(), TEXT_FIELD_ID, |s| {
+        let received = subscribe(s, UIA_Text_TextSelectionChangedEventId)?;
+        s.update(TreeUpdate {
+            nodes: vec![text_field("Hello", 5)],
+            focus: Some(TEXT_FIELD_ID),
+            ..Default::default()
+        });
+        received.wait_for(UIA_Text_TextSelectionChangedEventId);
+        Ok(())
+    })
+}
+
+#[test]
+fn text_change_raises_event() -> Result<()> {
+    scope(WINDOW_TITLE, text_field_in_window(), TEXT_FIELD_ID, |s| {
+        let received = subscribe(s, UIA_Text_TextChangedEventId)?;
+        s.update(TreeUpdate {
+            nodes: vec![text_field("Hello!", 0), text_box("Hello!")],
+            focus: Some(TEXT_FIELD_ID),
+            ..Default::default()
+        });
+        received.wait_for(UIA_Text_TextChangedEventId);
+        Ok(())
+    })
+}
+
+fn caret(left: f32) -> Node {
+    Node {
+        bounds: Some(RelativeBounds {
+            offset_container: Some(TEXT_FIELD_ID),
+            rect: Rect {
+                left,
+                top: 2.0,
+                width: 1.0,
+                height: 16.0,
+            },
+            transform: None,
+        }),
+        ..Node::new(CARET_ID, Role::Caret)
+    }
+}
+
+#[test]
+fn caret_movement_raises_selection_event() -> Result<()> {
+    let mut update = text_field_in_window();
+    update.nodes[1].children = Box::new([TEXT_BOX_ID, CARET_ID]);
+    update.nodes.push(caret(0.0));
+    scope(WINDOW_TITLE, update, TEXT_FIELD_ID, |s| {
+        let received = subscribe(s, UIA_Text_TextSelectionChangedEventId)?;
+        s.update(TreeUpdate {
+            nodes: vec![caret(24.0)],
+            focus: Some(TEXT_FIELD_ID),
+            ..Default::default()
+        });
+        received.wait_for(UIA_Text_TextSelectionChangedEventId);
+        Ok(())
+    })
+}
+
+#[test]
+fn text_pattern_reports_font_weight_of_range() -> Result<()> {
+    let mut update = text_field_in_window();
+    update.nodes[1] = Node {
+        value: Some("Hello world".into()),
+        text_selection: None,
+        children: Box::new([BOLD_RUN_ID, PLAIN_RUN_ID]),
+        ..text_field("", 0)
+    };
+    update.nodes[2] = run(BOLD_RUN_ID, "Hello ", true);
+    update.nodes.push(run(PLAIN_RUN_ID, "world", false));
+    scope(WINDOW_TITLE, update, TEXT_FIELD_ID, |s| {
+        let element = s.find_element_by_name("Input")?;
+        let pattern = unsafe { element.GetCurrentPattern(UIA_TextPatternId) }?
+            .cast::<IUIAutomationTextPattern>()?;
+        let range = unsafe { pattern.DocumentRange() }?;
+        assert_eq!("Hello world", unsafe { range.GetText(-1) }?.to_string());
+        let weight = unsafe { range.GetAttributeValue(UIA_FontWeightAttributeId) }?;
+        let weight = unsafe { &weight.Anonymous.Anonymous };
+        assert_eq!(VT_UNKNOWN.0 as u16, weight.vt);
+        let mixed = unsafe { s.uia.ReservedMixedAttributeValue() }?;
+        assert_eq!(
+            Some(mixed),
+            Option::clone(unsafe { &weight.Anonymous.punkVal })
+        );
+
+        // The first word includes the space after it, so it's all bold.
+        unsafe { range.ExpandToEnclosingUnit(TextUnit_Word) }?;
+        assert_eq!("Hello ", unsafe { range.GetText(-1) }?.to_string());
+        let weight = unsafe { range.GetAttributeValue(UIA_FontWeightAttributeId) }?;
+        let weight = unsafe { &weight.Anonymous.Anonymous };
+        assert_eq!(VT_I4.0 as u16, weight.vt);
+        assert_eq!(700, unsafe { weight.Anonymous.lVal });
+        Ok(())
+    })
+}
+
+fn run_at(id: NodeId, text: &str, left: f32) -> Node {
+    Node {
+        bounds: Some(RelativeBounds {
+            offset_container: Some(TEXT_FIELD_ID),
+            rect: Rect {
+                left,
+                top: 2.0,
+                width: 8.0 * text.len() as f32,
+                height: 16.0,
+            },
+            transform: None,
+        }),
+        ..run(id, text, false)
+    }
+}
+
+#[test]
+fn caret_range_is_at_caret_position() -> Result<()> {
+    let mut update = text_field_in_window();
+    update.nodes[1] = Node {
+        value: Some("Hello world".into()),
+        text_selection: None,
+        children: Box::new([BOLD_RUN_ID, PLAIN_RUN_ID, CARET_ID]),
+        ..text_field("", 0)
+    };
+    update.nodes[2] = run_at(BOLD_RUN_ID, "Hello ", 0.0);
+    update.nodes.push(run_at(PLAIN_RUN_ID, "world", 48.0));
+    update.nodes.push(caret(50.0));
+    scope(WINDOW_TITLE, update, TEXT_FIELD_ID, |s| {
+        let element = s.find_element_by_name("Input")?;
+        let pattern = unsafe { element.GetCurrentPattern(UIA_TextPattern2Id) }?
+            .cast::<IUIAutomationTextPattern2>()?;
+        let mut is_active = BOOL::default();
+        let range = unsafe { pattern.GetCaretRange(&mut is_active) }?;
+        assert!(is_active.as_bool());
+        assert_eq!("", unsafe { range.GetText(-1) }?.to_string());
+        unsafe { range.ExpandToEnclosingUnit(TextUnit_Character) }?;
+        assert_eq!("w", unsafe { range.GetText(-1) }?.to_string());
+        Ok(())
+    })
+}
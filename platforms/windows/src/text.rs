@@ -0,0 +1,689 @@
+// Copyright 2021 The AccessKit Authors. All rights reserved.
+// Licensed under the Apache License, Version 2.0 (found in
+// the LICENSE-APACHE file) or the MIT license (found in
+// the LICENSE-MIT file), at your option.
+
+#![allow(non_upper_case_globals)]
+
+use std::{
+    collections::BTreeSet,
+    convert::TryFrom,
+    ops::Range,
+    sync::{Arc, Mutex, Weak},
+};
+
+use accesskit_consumer::Node;
+use accesskit_schema::{NodeId, StringEncoding, TextDecoration, TextSelection};
+use windows as Windows;
+use windows::{
+    core::*,
+    Win32::{Foundation::*, System::Com::*, UI::Accessibility::*},
+};
+
+use crate::{
+    node::{PlatformNode, ResolvedPlatformNode},
+    util::*,
+};
+
+// UIA reports font sizes in points, while AccessKit uses pixels.
+const POINTS_PER_PIXEL: f64 = 0.75;
+
+const FONT_WEIGHT_NORMAL: i32 = 400;
+const FONT_WEIGHT_BOLD: i32 = 700;
+
+/// The value of a single text attribute of one node.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TextAttributeValue {
+    String(String),
+    Float(f64),
+    Int(i32),
+    Bool(bool),
+}
+
+impl From<TextAttributeValue> for VariantFactory {
+    fn from(value: TextAttributeValue) -> Self {
+        match value {
+            TextAttributeValue::String(value) => value.into(),
+            TextAttributeValue::Float(value) => value.into(),
+            TextAttributeValue::Int(value) => value.into(),
+            TextAttributeValue::Bool(value) => value.into(),
+        }
+    }
+}
+
+/// The value of a text attribute over a range of nodes, as returned by
+/// `ITextRangeProvider::GetAttributeValue`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RangeAttributeValue {
+    Value(TextAttributeValue),
+    /// The nodes in the range don't all have the same value.
+    Mixed,
+    NotSupported,
+}
+
+impl From<RangeAttributeValue> for VariantFactory {
+    fn from(value: RangeAttributeValue) -> Self {
+        match value {
+            RangeAttributeValue::Value(value) => value.into(),
+            RangeAttributeValue::Mixed => unsafe { UiaGetReservedMixedAttributeValue() }
+                .unwrap()
+                .into(),
+            RangeAttributeValue::NotSupported => {
+                unsafe { UiaGetReservedNotSupportedValue() }.unwrap().into()
+            }
+        }
+    }
+}
+
+fn text_decoration_line_style(decoration: Option<TextDecoration>) -> i32 {
+    match decoration {
+        None => TextDecorationLineStyle_None,
+        Some(TextDecoration::Solid) => TextDecorationLineStyle_Single,
+        Some(TextDecoration::Dotted) => TextDecorationLineStyle_Dot,
+        Some(TextDecoration::Dashed) => TextDecorationLineStyle_Dash,
+        Some(TextDecoration::Double) => TextDecorationLineStyle_Double,
+        Some(TextDecoration::Wavy) => TextDecorationLineStyle_Wavy,
+    }
+}
+
+// AccessKit colors are RGBA, while UIA expects a COLORREF (0x00BBGGRR).
+fn colorref(rgba: u32) -> i32 {
+    let [red, green, blue, _] = rgba.to_be_bytes();
+    i32::from_le_bytes([red, green, blue, 0])
+}
+
+/// Returns the value of the given UIA text attribute for a single node,
+/// or `None` if the node doesn't specify it or the attribute
+/// isn't supported.
+pub(crate) fn text_attribute_value(node: &Node, attribute_id: i32) -> Option<TextAttributeValue> {
+    let data = node.data();
+    match attribute_id {
+        UIA_FontNameAttributeId => data
+            .font_family
+            .as_deref()
+            .map(|family| TextAttributeValue::String(family.into())),
+        UIA_FontSizeAttributeId => data
+            .font_size
+            .map(|size| TextAttributeValue::Float(f64::from(size) * POINTS_PER_PIXEL)),
+        UIA_FontWeightAttributeId => Some(TextAttributeValue::Int(match data.font_weight {
+            Some(weight) => weight as i32,
+            None if data.bold => FONT_WEIGHT_BOLD,
+            None => FONT_WEIGHT_NORMAL,
+        })),
+        UIA_IsItalicAttributeId => Some(TextAttributeValue::Bool(data.italic)),
+        UIA_StrikethroughStyleAttributeId => Some(TextAttributeValue::Int(
+            text_decoration_line_style(data.strikethrough),
+        )),
+        UIA_UnderlineStyleAttributeId => Some(TextAttributeValue::Int(text_decoration_line_style(
+            data.underline,
+        ))),
+        UIA_ForegroundColorAttributeId => data
+            .foreground_color
+            .map(|color| TextAttributeValue::Int(colorref(color))),
+        UIA_BackgroundColorAttributeId => data
+            .background_color
+            .map(|color| TextAttributeValue::Int(colorref(color))),
+        // Text is read-only unless the text field it's in is editable.
+        UIA_IsReadOnlyAttributeId => {
+            let text_field = if node.is_text_field() {
+                Some(*node)
+            } else {
+                node.containing_text_field()
+            };
+            Some(TextAttributeValue::Bool(
+                !text_field.is_some_and(|text_field| text_field.is_editable_text()),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Combines the values of a text attribute over the nodes in a range.
+/// If the nodes disagree, including when only some of them specify
+/// the attribute, the result is [`RangeAttributeValue::Mixed`].
+pub(crate) fn range_attribute_value<'a>(
+    nodes: impl IntoIterator<Item = Node<'a>>,
+    attribute_id: i32,
+) -> RangeAttributeValue {
+    let mut nodes = nodes.into_iter();
+    let first = match nodes.next() {
+        Some(node) => text_attribute_value(&node, attribute_id),
+        None => return RangeAttributeValue::NotSupported,
+    };
+    for node in nodes {
+        if text_attribute_value(&node, attribute_id) != first {
+            return RangeAttributeValue::Mixed;
+        }
+    }
+    first.map_or(
+        RangeAttributeValue::NotSupported,
+        RangeAttributeValue::Value,
+    )
+}
+
+// Converts an offset into `text` in the tree's source encoding
+// to UTF-16 code units, and back.
+fn utf16_offset(text: &str, encoding: StringEncoding, offset: usize) -> usize {
+    match encoding {
+        StringEncoding::Utf8 => text.get(..offset).unwrap_or(text).encode_utf16().count(),
+        StringEncoding::Utf16 => offset,
+    }
+}
+
+fn source_offset(text: &str, encoding: StringEncoding, utf16_offset: usize) -> usize {
+    match encoding {
+        StringEncoding::Utf8 => {
+            let mut units = 0;
+            for (offset, c) in text.char_indices() {
+                if units >= utf16_offset {
+                    return offset;
+                }
+                units += c.len_utf16();
+            }
+            text.len()
+        }
+        StringEncoding::Utf16 => utf16_offset,
+    }
+}
+
+struct TextRun<'a> {
+    node: Node<'a>,
+    start: usize,
+    len: usize,
+}
+
+impl TextRun<'_> {
+    fn end(&self) -> usize {
+        self.start + self.len
+    }
+}
+
+/// The text of a text field or document, i.e. the text of its
+/// [text runs](Node::text_runs) joined together. Positions in the text
+/// are in UTF-16 code units, which is what UIA measures text ranges in.
+pub(crate) struct TextContent<'a> {
+    runs: Vec<TextRun<'a>>,
+    encoding: StringEncoding,
+}
+
+impl<'a> TextContent<'a> {
+    pub(crate) fn new(container: Node<'a>) -> Self {
+        let mut start = 0;
+        let runs = container
+            .text_runs()
+            .into_iter()
+            .map(|node| {
+                let len = node.text().encode_utf16().count();
+                let run = TextRun { node, start, len };
+                start += len;
+                run
+            })
+            .collect();
+        Self {
+            runs,
+            encoding: container.tree_reader.string_encoding(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.runs.last().map_or(0, TextRun::end)
+    }
+
+    fn text(&self, range: Range<usize>) -> String {
+        let units: Vec<u16> = self
+            .runs
+            .iter()
+            .flat_map(|run| run.node.text().encode_utf16())
+            .collect();
+        String::from_utf16_lossy(&units[range])
+    }
+
+    /// Returns each node that holds part of the text, along with
+    /// the position at which its text starts.
+    pub(crate) fn run_starts(&self) -> impl Iterator<Item = (usize, Node<'a>)> + '_ {
+        self.runs.iter().map(|run| (run.start, run.node))
+    }
+
+    // The run that contains the given position; at the boundary between
+    // two runs, that's the one that starts there.
+    fn run_at(&self, position: usize) -> Option<&TextRun<'a>> {
+        self.runs
+            .iter()
+            .find(|run| position < run.end())
+            .or_else(|| self.runs.last())
+    }
+
+    /// Returns the nodes that hold the text in the given range. An empty
+    /// range is in the node that contains its position.
+    pub(crate) fn nodes(&self, range: Range<usize>) -> Vec<Node<'a>> {
+        if range.is_empty() {
+            return self
+                .run_at(range.start)
+                .map(|run| run.node)
+                .into_iter()
+                .collect();
+        }
+        self.runs
+            .iter()
+            .filter(|run| run.start < range.end && range.start < run.end())
+            .map(|run| run.node)
+            .collect()
+    }
+
+    fn position(&self, node: NodeId, offset: usize) -> Option<usize> {
+        let run = self.runs.iter().find(|run| run.node.id() == node)?;
+        let offset = utf16_offset(run.node.text(), self.encoding, offset);
+        Some(run.start + offset.min(run.len))
+    }
+
+    fn selection_endpoint(&self, position: usize) -> Option<(NodeId, usize)> {
+        let run = self.run_at(position)?;
+        let offset = source_offset(run.node.text(), self.encoding, position - run.start);
+        Some((run.node.id(), offset))
+    }
+
+    /// Returns the range that's selected in the given text field or
+    /// document, if its selection is within this text.
+    pub(crate) fn selection(&self, container: &Node) -> Option<Range<usize>> {
+        let selection = container.data().text_selection?;
+        let anchor = self.position(selection.anchor_node, selection.anchor_offset)?;
+        let focus = self.position(selection.focus_node, selection.focus_offset)?;
+        Some(anchor.min(focus)..anchor.max(focus))
+    }
+
+    fn text_selection(&self, range: Range<usize>) -> Option<TextSelection> {
+        let (anchor_node, anchor_offset) = self.selection_endpoint(range.start)?;
+        let (focus_node, focus_offset) = self.selection_endpoint(range.end)?;
+        Some(TextSelection {
+            anchor_node,
+            anchor_offset,
+            focus_node,
+            focus_offset,
+        })
+    }
+
+    /// Returns the positions at which each unit of the given kind starts,
+    /// along with the start and end of the text, in ascending order.
+    /// The text isn't laid out into lines, paragraphs or pages here,
+    /// so as UIA requires, those fall back to the next larger unit
+    /// that is supported, the whole document.
+    fn boundaries(&self, unit: TextUnit) -> Vec<usize> {
+        let mut boundaries = BTreeSet::new();
+        boundaries.insert(0);
+        boundaries.insert(self.len());
+        match unit {
+            TextUnit_Character => {
+                for run in &self.runs {
+                    let mut position = run.start;
+                    for c in run.node.text().chars() {
+                        boundaries.insert(position);
+                        position += c.len_utf16();
+                    }
+                }
+            }
+            TextUnit_Format => boundaries.extend(self.runs.iter().map(|run| run.start)),
+            TextUnit_Word => {
+                for run in &self.runs {
+                    let text = run.node.text();
+                    boundaries.extend(
+                        run.node
+                            .word_ranges()
+                            .into_iter()
+                            .map(|word| run.start + utf16_offset(text, self.encoding, word.start)),
+                    );
+                }
+            }
+            _ => (),
+        }
+        boundaries.into_iter().collect()
+    }
+
+    /// Returns the unit of the given kind that contains the position.
+    /// At the end of the text, that's the last unit.
+    fn enclosing_unit(&self, position: usize, unit: TextUnit) -> Range<usize> {
+        let boundaries = self.boundaries(unit);
+        if boundaries.len() < 2 {
+            return position..position;
+        }
+        let end_index = boundaries
+            .partition_point(|boundary| *boundary <= position)
+            .clamp(1, boundaries.len() - 1);
+        boundaries[end_index - 1]..boundaries[end_index]
+    }
+}
+
+// Moves the position by up to `count` of the given boundaries, returning
+// the new position and the number of boundaries actually moved across,
+// which is less than `count` at the start or end of the text.
+fn move_position(boundaries: &[usize], position: usize, count: i32) -> (usize, i32) {
+    if count > 0 {
+        let following = &boundaries[boundaries.partition_point(|b| *b <= position)..];
+        match following
+            .get(count as usize - 1)
+            .or_else(|| following.last())
+        {
+            Some(target) => (*target, saturating_i32(following.len()).min(count)),
+            None => (position, 0),
+        }
+    } else if count < 0 {
+        let preceding = &boundaries[..boundaries.partition_point(|b| *b < position)];
+        let moved = preceding.len().min(count.unsigned_abs() as usize);
+        match moved {
+            0 => (position, 0),
+            _ => (preceding[preceding.len() - moved], -saturating_i32(moved)),
+        }
+    } else {
+        (position, 0)
+    }
+}
+
+// UIA passes ranges back to us as interface pointers, e.g. to compare
+// two ranges, and there's no way to get from an interface pointer to
+// the object that implements it. So each range registers its endpoints
+// under its interface pointer. A range's entry goes away when the range
+// is released, so a new range at the same address replaces it.
+struct RangeState {
+    container: NodeId,
+    endpoints: Mutex<Range<usize>>,
+}
+
+static RANGES: Mutex<Vec<(usize, Weak<RangeState>)>> = Mutex::new(Vec::new());
+
+fn interface_key(range: &ITextRangeProvider) -> usize {
+    unsafe { std::mem::transmute_copy(range) }
+}
+
+fn registered_range(range: &Option<ITextRangeProvider>) -> Result<Arc<RangeState>> {
+    let key = range.as_ref().map(interface_key);
+    RANGES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(registered_key, _)| Some(*registered_key) == key)
+        .and_then(|(_, state)| state.upgrade())
+        .ok_or_else(|| Error::new(E_INVALIDARG, "".into()))
+}
+
+/// Creates a range of the text in the given text field or document.
+pub(crate) fn text_range(
+    container: &ResolvedPlatformNode,
+    range: Range<usize>,
+) -> ITextRangeProvider {
+    let state = Arc::new(RangeState {
+        container: container.node.id(),
+        endpoints: Mutex::new(range),
+    });
+    let provider: ITextRangeProvider = TextRange {
+        container: container.downgrade(),
+        state: state.clone(),
+    }
+    .into();
+    let mut ranges = RANGES.lock().unwrap();
+    ranges.retain(|(_, state)| state.strong_count() > 0);
+    ranges.push((interface_key(&provider), Arc::downgrade(&state)));
+    provider
+}
+
+#[implement(Windows::Win32::UI::Accessibility::ITextRangeProvider)]
+pub(crate) struct TextRange {
+    container: PlatformNode,
+    state: Arc<RangeState>,
+}
+
+#[allow(non_snake_case)]
+impl TextRange {
+    // Resolves the container and its text, passing the range's endpoints,
+    // which `f` may change. If the text has become shorter since
+    // the range was created, the endpoints are first clamped to it.
+    fn resolve<F, T>(&self, f: F) -> Result<T>
+    where
+        for<'a> F:
+            FnOnce(&ResolvedPlatformNode<'a>, &TextContent<'a>, &mut Range<usize>) -> Result<T>,
+    {
+        self.container.resolve(|resolved| {
+            let content = TextContent::new(resolved.node);
+            let mut endpoints = self.state.endpoints.lock().unwrap();
+            endpoints.end = endpoints.end.min(content.len());
+            endpoints.start = endpoints.start.min(endpoints.end);
+            f(&resolved, &content, &mut *endpoints)
+        })
+    }
+
+    // Returns the position of the given endpoint of another range, which
+    // must be in the same container as this one. This is done before
+    // locking this range's endpoints, since the other range may be this one.
+    fn other_endpoint(
+        &self,
+        range: &Option<ITextRangeProvider>,
+        endpoint: TextPatternRangeEndpoint,
+    ) -> Result<usize> {
+        let other = registered_range(range)?;
+        if other.container != self.state.container {
+            return Err(Error::new(E_INVALIDARG, "".into()));
+        }
+        let endpoints = other.endpoints.lock().unwrap();
+        Ok(endpoint_position(&endpoints, endpoint))
+    }
+
+    fn Clone(&self) -> Result<ITextRangeProvider> {
+        self.resolve(|resolved, _content, range| Ok(text_range(resolved, range.clone())))
+    }
+
+    fn Compare(&self, range: &Option<ITextRangeProvider>) -> Result<BOOL> {
+        let other = registered_range(range)?;
+        if other.container != self.state.container {
+            return Ok(false.into());
+        }
+        let other = other.endpoints.lock().unwrap().clone();
+        self.resolve(|_resolved, _content, range| Ok((*range == other).into()))
+    }
+
+    fn CompareEndpoints(
+        &self,
+        endpoint: TextPatternRangeEndpoint,
+        target_range: &Option<ITextRangeProvider>,
+        target_endpoint: TextPatternRangeEndpoint,
+    ) -> Result<i32> {
+        let target = self.other_endpoint(target_range, target_endpoint)?;
+        self.resolve(|_resolved, _content, range| {
+            Ok(endpoint_position(range, endpoint).cmp(&target) as i32)
+        })
+    }
+
+    fn ExpandToEnclosingUnit(&self, unit: TextUnit) -> Result<()> {
+        self.resolve(|_resolved, content, range| {
+            *range = content.enclosing_unit(range.start, unit);
+            Ok(())
+        })
+    }
+
+    // Searching by attribute value would require comparing arbitrary
+    // `VARIANT`s, and no client that we know of depends on it.
+    fn FindAttribute(
+        &self,
+        _attribute_id: i32,
+        _value: &VARIANT,
+        _backward: BOOL,
+    ) -> Result<ITextRangeProvider> {
+        Err(Error::new(E_NOTIMPL, "".into()))
+    }
+
+    fn FindText(
+        &self,
+        text: &BSTR,
+        backward: BOOL,
+        ignore_case: BOOL,
+    ) -> Result<ITextRangeProvider> {
+        let needle: Vec<u16> = if ignore_case.as_bool() {
+            text.to_string().to_lowercase().encode_utf16().collect()
+        } else {
+            text.to_string().encode_utf16().collect()
+        };
+        self.resolve(|resolved, content, range| {
+            let haystack = content.text(range.clone());
+            let haystack: Vec<u16> = if ignore_case.as_bool() {
+                haystack.to_lowercase().encode_utf16().collect()
+            } else {
+                haystack.encode_utf16().collect()
+            };
+            if needle.is_empty() || needle.len() > haystack.len() {
+                return Err(Error::OK);
+            }
+            let mut starts = 0..=(haystack.len() - needle.len());
+            let matches = |start: &usize| haystack[*start..(*start + needle.len())] == needle[..];
+            let start = if backward.as_bool() {
+                starts.rev().find(matches)
+            } else {
+                starts.find(matches)
+            };
+            match start {
+                Some(start) => {
+                    let start = range.start + start;
+                    Ok(text_range(resolved, start..(start + needle.len())))
+                }
+                None => Err(Error::OK),
+            }
+        })
+    }
+
+    fn GetAttributeValue(&self, attribute_id: i32) -> Result<VARIANT> {
+        self.resolve(|_resolved, content, range| {
+            let value = range_attribute_value(content.nodes(range.clone()), attribute_id);
+            Ok(VariantFactory::from(value).into())
+        })
+    }
+
+    // Each run is reported as a single rectangle, since we don't have
+    // the bounds of lines within a run.
+    fn GetBoundingRectangles(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|resolved, content, range| {
+            let mut rects = Vec::new();
+            if !range.is_empty() {
+                for node in content.nodes(range.clone()) {
+                    if node.bounds().is_none() {
+                        continue;
+                    }
+                    let rect = resolved.relative(node).bounding_rectangle();
+                    rects.extend([rect.left, rect.top, rect.width, rect.height]);
+                }
+            }
+            Ok(safe_array_from_f64_slice(&rects))
+        })
+    }
+
+    fn GetEnclosingElement(&self) -> Result<IRawElementProviderSimple> {
+        self.resolve(|resolved, _content, _range| Ok(resolved.downgrade().into()))
+    }
+
+    fn GetText(&self, max_length: i32) -> Result<BSTR> {
+        self.resolve(|_resolved, content, range| {
+            let end = match usize::try_from(max_length) {
+                Ok(max_length) => range.end.min(range.start + max_length),
+                Err(_) => range.end,
+            };
+            Ok(content.text(range.start..end).as_str().into())
+        })
+    }
+
+    // A nonempty range is moved a whole unit at a time, so it first becomes
+    // the unit that contains its start, while an empty range stays empty.
+    fn Move(&self, unit: TextUnit, count: i32) -> Result<i32> {
+        self.resolve(|_resolved, content, range| {
+            if range.is_empty() {
+                let (position, moved) =
+                    move_position(&content.boundaries(unit), range.start, count);
+                *range = position..position;
+                return Ok(moved);
+            }
+            let start = content.enclosing_unit(range.start, unit).start;
+            let mut boundaries = content.boundaries(unit);
+            if boundaries.len() > 1 {
+                boundaries.pop();
+            }
+            let (position, moved) = move_position(&boundaries, start, count);
+            *range = content.enclosing_unit(position, unit);
+            Ok(moved)
+        })
+    }
+
+    fn MoveEndpointByUnit(
+        &self,
+        endpoint: TextPatternRangeEndpoint,
+        unit: TextUnit,
+        count: i32,
+    ) -> Result<i32> {
+        self.resolve(|_resolved, content, range| {
+            let (position, moved) = move_position(
+                &content.boundaries(unit),
+                endpoint_position(range, endpoint),
+                count,
+            );
+            set_endpoint(range, endpoint, position);
+            Ok(moved)
+        })
+    }
+
+    fn MoveEndpointByRange(
+        &self,
+        endpoint: TextPatternRangeEndpoint,
+        target_range: &Option<ITextRangeProvider>,
+        target_endpoint: TextPatternRangeEndpoint,
+    ) -> Result<()> {
+        let target = self.other_endpoint(target_range, target_endpoint)?;
+        self.resolve(|_resolved, _content, range| {
+            set_endpoint(range, endpoint, target);
+            Ok(())
+        })
+    }
+
+    fn Select(&self) -> Result<()> {
+        self.resolve(|resolved, content, range| {
+            if let Some(selection) = content.text_selection(range.clone()) {
+                resolved.node.set_text_selection(selection);
+            }
+            Ok(())
+        })
+    }
+
+    // Only a single selection is supported.
+    fn AddToSelection(&self) -> Result<()> {
+        Err(Error::new(HRESULT(UIA_E_INVALIDOPERATION), "".into()))
+    }
+
+    fn RemoveFromSelection(&self) -> Result<()> {
+        Err(Error::new(HRESULT(UIA_E_INVALIDOPERATION), "".into()))
+    }
+
+    fn ScrollIntoView(&self, _align_to_top: BOOL) -> Result<()> {
+        self.resolve(|_resolved, content, range| {
+            if let Some(node) = content.nodes(range.clone()).first() {
+                node.scroll_into_view();
+            }
+            Ok(())
+        })
+    }
+
+    // We don't expose embedded objects within text.
+    fn GetChildren(&self) -> Result<*mut SAFEARRAY> {
+        self.resolve(|_resolved, _content, _range| Ok(safe_array_from_interfaces(&[])))
+    }
+}
+
+fn endpoint_position(range: &Range<usize>, endpoint: TextPatternRangeEndpoint) -> usize {
+    if endpoint == TextPatternRangeEndpoint_Start {
+        range.start
+    } else {
+        range.end
+    }
+}
+
+// Moving one endpoint past the other collapses the range to the new position.
+fn set_endpoint(range: &mut Range<usize>, endpoint: TextPatternRangeEndpoint, position: usize) {
+    if endpoint == TextPatternRangeEndpoint_Start {
+        range.start = position;
+        range.end = range.end.max(position);
+    } else {
+        range.end = position;
+        range.start = range.start.min(position);
+    }
+}
@@ -3,10 +3,18 @@
 // the LICENSE-APACHE file) or the MIT license (found in
 // the LICENSE-MIT file), at your option.
 
-use std::{convert::TryInto, mem::ManuallyDrop};
-use windows::Win32::{
-    Foundation::*,
-    System::{Com::*, Ole::*},
+use std::{
+    convert::TryInto,
+    ffi::c_void,
+    mem::ManuallyDrop,
+    sync::atomic::{AtomicU32, Ordering},
+};
+use windows::{
+    core::IUnknown,
+    Win32::{
+        Foundation::*,
+        System::{Com::*, Ole::*},
+    },
 };
 
 pub(crate) struct VariantFactory(VARENUM, VARIANT_0_0_0);
@@ -48,12 +56,50 @@ impl From<&str> for VariantFactory {
     }
 }
 
+impl From<String> for VariantFactory {
+    fn from(value: String) -> Self {
+        value.as_str().into()
+    }
+}
+
 impl From<i32> for VariantFactory {
     fn from(value: i32) -> Self {
         Self(VT_I4, VARIANT_0_0_0 { lVal: value })
     }
 }
 
+impl From<f64> for VariantFactory {
+    fn from(value: f64) -> Self {
+        Self(VT_R8, VARIANT_0_0_0 { dblVal: value })
+    }
+}
+
+impl From<IUnknown> for VariantFactory {
+    fn from(value: IUnknown) -> Self {
+        Self(
+            VT_UNKNOWN,
+            VARIANT_0_0_0 {
+                punkVal: ManuallyDrop::new(Some(value)),
+            },
+        )
+    }
+}
+
+// UIA uses its own default, an empty array, for an empty property value.
+impl From<Vec<IUnknown>> for VariantFactory {
+    fn from(value: Vec<IUnknown>) -> Self {
+        if value.is_empty() {
+            return Self::empty();
+        }
+        Self(
+            VARENUM(VT_ARRAY.0 | VT_UNKNOWN.0),
+            VARIANT_0_0_0 {
+                parray: safe_array_from_interfaces(&value),
+            },
+        )
+    }
+}
+
 const VARIANT_FALSE: i16 = 0i16;
 const VARIANT_TRUE: i16 = -1i16;
 
@@ -74,6 +120,33 @@ impl<T: Into<VariantFactory>> From<Option<T>> for VariantFactory {
     }
 }
 
+/// The factor by which bounds in the tree, which are in logical pixels,
+/// are multiplied to get the physical pixels that UIA expects. It's shared
+/// between the manager and its nodes, so the manager can update it when
+/// the window moves to a monitor with a different DPI.
+pub(crate) struct ScaleFactor(AtomicU32);
+
+impl ScaleFactor {
+    pub(crate) fn new(factor: f32) -> Self {
+        Self(AtomicU32::new(factor.to_bits()))
+    }
+
+    pub(crate) fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set(&self, factor: f32) {
+        self.0.store(factor.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Converts a count, index, or level to the `i32` that UIA expects.
+/// Values that don't fit are clamped to `i32::MAX` rather than wrapping
+/// around to negative numbers, which UIA clients would misinterpret.
+pub(crate) fn saturating_i32(value: usize) -> i32 {
+    value.try_into().unwrap_or(i32::MAX)
+}
+
 fn safe_array_from_slice<T>(vt: VARENUM, slice: &[T]) -> *mut SAFEARRAY {
     let sa = unsafe { SafeArrayCreateVector(vt.0 as u16, 0, slice.len().try_into().unwrap()) };
     if sa.is_null() {
@@ -89,3 +162,39 @@ fn safe_array_from_slice<T>(vt: VARENUM, slice: &[T]) -> *mut SAFEARRAY {
 pub(crate) fn safe_array_from_i32_slice(slice: &[i32]) -> *mut SAFEARRAY {
     safe_array_from_slice(VT_I4, slice)
 }
+
+pub(crate) fn safe_array_from_f64_slice(slice: &[f64]) -> *mut SAFEARRAY {
+    safe_array_from_slice(VT_R8, slice)
+}
+
+// Strings and interfaces are passed to `SafeArrayPutElement` as the
+// pointers themselves, rather than pointers to them, and the array
+// stores its own copy or reference.
+fn safe_array_from_pointers(vt: VARENUM, pointers: &[*const c_void]) -> *mut SAFEARRAY {
+    let sa = unsafe { SafeArrayCreateVector(vt.0 as u16, 0, pointers.len().try_into().unwrap()) };
+    if sa.is_null() {
+        panic!("SAFEARRAY allocation failed");
+    }
+    for (i, pointer) in pointers.iter().enumerate() {
+        let i: i32 = i.try_into().unwrap();
+        unsafe { SafeArrayPutElement(sa, &i, *pointer) }.unwrap();
+    }
+    sa
+}
+
+pub(crate) fn safe_array_from_strings(strings: &[&str]) -> *mut SAFEARRAY {
+    let strings: Vec<BSTR> = strings.iter().map(|s| (*s).into()).collect();
+    let pointers: Vec<*const c_void> = strings
+        .iter()
+        .map(|s| unsafe { std::mem::transmute_copy(s) })
+        .collect();
+    safe_array_from_pointers(VT_BSTR, &pointers)
+}
+
+pub(crate) fn safe_array_from_interfaces(interfaces: &[IUnknown]) -> *mut SAFEARRAY {
+    let pointers: Vec<*const c_void> = interfaces
+        .iter()
+        .map(|interface| unsafe { std::mem::transmute_copy(interface) })
+        .collect();
+    safe_array_from_pointers(VT_UNKNOWN, &pointers)
+}
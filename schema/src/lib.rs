@@ -13,6 +13,8 @@ use enumset::{EnumSet, EnumSetType};
 use schemars::JsonSchema;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::ops::Range;
 
 /// The type of an accessibility node.
@@ -309,7 +311,8 @@ pub enum Action {
 
     /// Replace the value of the control with the specified value and
     /// reset the selection, if applicable. Requires [`ActionRequest::data`]
-    /// to be set to [`ActionData::Value`].
+    /// to be set to [`ActionData::Value`] or, for range controls,
+    /// [`ActionData::NumericValue`].
     SetValue,
 
     ShowContextMenu,
@@ -380,6 +383,31 @@ pub enum DropEffect {
     Popup,
 }
 
+/// How urgently changes to a live region should be announced,
+/// as given by the tokens of `aria-live`. See [`parse_live_status`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum LivePoliteness {
+    Off,
+    Polite,
+    Assertive,
+}
+
+/// A kind of change to a live region that should be announced,
+/// as given by the tokens of `aria-relevant`. See [`parse_live_relevant`].
+#[derive(EnumSetType, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[cfg_attr(feature = "serde", enumset(serialize_as_list))]
+pub enum LiveRelevant {
+    Additions,
+    Removals,
+    Text,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
@@ -450,6 +478,43 @@ pub enum DefaultActionVerb {
     Select,
 }
 
+impl DefaultActionVerb {
+    /// Returns a stable key that identifies this verb in a platform
+    /// adapter's or application's table of localized strings. Keys are
+    /// lowercase and hyphenated, and don't change between releases.
+    pub fn as_localization_key(&self) -> &'static str {
+        match self {
+            Self::Activate => "activate",
+            Self::Check => "check",
+            Self::Uncheck => "uncheck",
+            Self::Click => "click",
+            Self::ClickAncestor => "click-ancestor",
+            Self::Jump => "jump",
+            Self::Open => "open",
+            Self::Press => "press",
+            Self::Select => "select",
+        }
+    }
+
+    /// Returns the English description of this verb, for platform
+    /// adapters to use when no localized string is available.
+    pub fn english(&self) -> &'static str {
+        match self {
+            Self::ClickAncestor => "click ancestor",
+            _ => self.as_localization_key(),
+        }
+    }
+}
+
+/// Returns the English name of a default action verb, as used by
+/// the platform accessibility APIs that describe the default action
+/// with a string, such as MSAA's `accDefaultAction`. Platform adapters
+/// that support localization use this when no localized string is provided.
+/// This is the same as [`DefaultActionVerb::english`].
+pub fn default_action_verb_string(verb: DefaultActionVerb) -> &'static str {
+    verb.english()
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
@@ -547,11 +612,150 @@ pub enum StringEncoding {
 pub type NodeIdContent = std::num::NonZeroU64;
 
 /// The stable identity of a node, unique within the node's tree.
+/// Zero isn't a valid ID; use [`NodeId::try_from`] to convert
+/// a raw ID that might be zero.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize))]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct NodeId(pub NodeIdContent);
 
+/// The error returned when converting a raw node ID of zero
+/// to a [`NodeId`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZeroNodeIdError;
+
+impl std::fmt::Display for ZeroNodeIdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "node ID 0 is invalid; node IDs must be nonzero")
+    }
+}
+
+impl std::error::Error for ZeroNodeIdError {}
+
+impl TryFrom<u64> for NodeId {
+    type Error = ZeroNodeIdError;
+
+    fn try_from(id: u64) -> Result<Self, Self::Error> {
+        NodeIdContent::new(id).map(NodeId).ok_or(ZeroNodeIdError)
+    }
+}
+
+// The derived implementation would report a zero ID as a generic
+// "expected a nonzero u64" error, which doesn't say what was wrong.
+// Like the derived one, this deserializes a newtype struct, to match
+// the derived `Serialize`, but formats that treat newtypes as
+// transparent hand the visitor the bare `u64`.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{self, SeqAccess, Visitor};
+
+        struct NodeIdVisitor;
+
+        impl<'de> Visitor<'de> for NodeIdVisitor {
+            type Value = NodeId;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "a nonzero node ID")
+            }
+
+            fn visit_u64<E: de::Error>(self, id: u64) -> Result<NodeId, E> {
+                NodeId::try_from(id).map_err(E::custom)
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<NodeId, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                let id = u64::deserialize(deserializer)?;
+                self.visit_u64(id)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<NodeId, A::Error> {
+                let id: u64 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                self.visit_u64(id)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct("NodeId", NodeIdVisitor)
+    }
+}
+
+/// Hands out unique [`NodeId`]s for a tree that's built procedurally.
+/// IDs are allocated in increasing order starting at 1, skipping any
+/// that have been [reserved](IdAllocator::reserve), and are never reused.
+/// [`IdAllocator::id_for_key`] maps an application-level key, such as
+/// a widget handle, to the same ID every time it's called with that key,
+/// so that a widget keeps its ID from one frame to the next, as platform
+/// adapters expect.
+#[derive(Clone, Debug)]
+pub struct IdAllocator {
+    /// The lowest ID that might still be free, or `None` once
+    /// `u64::MAX` has been allocated.
+    next: Option<u64>,
+    reserved: Vec<Range<u64>>,
+    keys: HashMap<u64, NodeId>,
+}
+
+impl IdAllocator {
+    pub fn new() -> Self {
+        Self {
+            next: Some(1),
+            reserved: Vec::new(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Returns an ID that hasn't been allocated or reserved before.
+    /// Panics if every ID has been used.
+    pub fn allocate(&mut self) -> NodeId {
+        let mut id = self.next.expect("node IDs exhausted");
+        while let Some(range) = self.reserved.iter().find(|range| range.contains(&id)) {
+            id = range.end;
+        }
+        self.next = id.checked_add(1);
+        NodeId::try_from(id).unwrap()
+    }
+
+    /// Prevents the raw IDs in `range` from being allocated, e.g. because
+    /// the application assigns them to fixed nodes such as the root.
+    /// IDs in the range that were already allocated are unaffected.
+    pub fn reserve(&mut self, range: Range<u64>) {
+        if !range.is_empty() {
+            self.reserved.push(range);
+        }
+    }
+
+    /// Returns the ID for the given key, allocating one the first time
+    /// the key is seen.
+    pub fn id_for_key(&mut self, key: u64) -> NodeId {
+        if let Some(id) = self.keys.get(&key) {
+            return *id;
+        }
+        let id = self.allocate();
+        self.keys.insert(key, id);
+        id
+    }
+
+    /// Forgets the given key, e.g. because its widget was destroyed,
+    /// and returns the ID it was mapped to. The ID isn't reused, so if
+    /// the key is seen again, it gets a new ID.
+    pub fn remove_key(&mut self, key: u64) -> Option<NodeId> {
+        self.keys.remove(&key)
+    }
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// The globally unique ID of a tree. The format of this ID
 /// is up to the implementer. A UUID v4 is a safe choice.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -581,6 +785,85 @@ pub struct Rect {
     pub height: f32,
 }
 
+impl Rect {
+    /// Returns the area that the rectangles have in common, or `None` if
+    /// they don't overlap. Rectangles that only touch at an edge
+    /// don't overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = (self.left + self.width).min(other.left + other.width);
+        let bottom = (self.top + self.height).min(other.top + other.height);
+        if right > left && bottom > top {
+            Some(Rect {
+                left,
+                top,
+                width: right - left,
+                height: bottom - top,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns this rectangle with its position and size multiplied by
+    /// `factor`, e.g. to convert from logical pixels to physical pixels
+    /// on a display whose scale factor isn't 1.
+    pub fn scale(&self, factor: f32) -> Rect {
+        Rect {
+            left: self.left * factor,
+            top: self.top * factor,
+            width: self.width * factor,
+            height: self.height * factor,
+        }
+    }
+}
+
+/// A color with 8-bit red, green, blue, and alpha components.
+/// The color attributes of a [`Node`], such as [`Node::color_value`],
+/// store colors as a `u32` in RGBA order, i.e. `0xRRGGBBAA`, which
+/// converts to and from this type. A color is displayed in the same
+/// order, as a hex string like `#FF8000FF`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub alpha: u8,
+}
+
+impl From<u32> for Color {
+    fn from(rgba: u32) -> Color {
+        let [red, green, blue, alpha] = rgba.to_be_bytes();
+        Color {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+}
+
+impl From<Color> for u32 {
+    fn from(color: Color) -> u32 {
+        u32::from_be_bytes([color.red, color.green, color.blue, color.alpha])
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.red, self.green, self.blue, self.alpha
+        )
+    }
+}
+
 /// 4x4 transformation matrix.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -591,6 +874,18 @@ pub struct Transform {
     pub matrix: [f32; 16],
 }
 
+impl Transform {
+    /// Returns a transform that scales the x and y axes by `factor`.
+    pub fn scale(factor: f32) -> Transform {
+        Transform {
+            matrix: [
+                factor, 0.0, 0.0, 0.0, 0.0, factor, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0,
+                1.0,
+            ],
+        }
+    }
+}
+
 /// The relative bounding box of a [`Node`].
 ///
 /// This is an efficient, compact, serializable representation of a node's
@@ -625,6 +920,21 @@ pub struct RelativeBounds {
     pub transform: Option<Box<Transform>>,
 }
 
+impl RelativeBounds {
+    /// Returns true if the bounds are equal, except that each coordinate
+    /// of the rectangles may differ by up to `epsilon`, e.g. because of
+    /// sub-pixel differences in layout.
+    pub fn approx_eq(&self, other: &RelativeBounds, epsilon: f32) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() <= epsilon;
+        self.offset_container == other.offset_container
+            && self.transform == other.transform
+            && close(self.rect.left, other.rect.left)
+            && close(self.rect.top, other.rect.top)
+            && close(self.rect.width, other.rect.width)
+            && close(self.rect.height, other.rect.height)
+    }
+}
+
 /// A marker spanning a range within text.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -650,6 +960,21 @@ pub struct CustomAction {
     pub description: Box<str>,
 }
 
+/// An app-specific key/value pair for metadata that doesn't correspond
+/// to any other attribute of [`Node`]. Platform adapters may expose
+/// custom attributes, e.g. through a platform-specific property,
+/// but they're also free to ignore them, so nothing that assistive
+/// technologies need should depend on them.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CustomAttribute {
+    pub key: Box<str>,
+    pub value: Box<str>,
+}
+
 // Helper for skipping false values in serialization.
 #[cfg(feature = "serde")]
 fn is_false(b: &bool) -> bool {
@@ -670,13 +995,47 @@ fn is_empty<T>(slice: &[T]) -> bool {
 #[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct TextSelection {
-    anchor_node: NodeId,
-    anchor_offset: usize,
-    focus_node: NodeId,
-    focus_offset: usize,
+    pub anchor_node: NodeId,
+    pub anchor_offset: usize,
+    pub focus_node: NodeId,
+    pub focus_offset: usize,
+}
+
+impl TextSelection {
+    /// Returns true if the selection is empty, i.e. just a caret.
+    pub fn is_collapsed(&self) -> bool {
+        self.anchor_node == self.focus_node && self.anchor_offset == self.focus_offset
+    }
+}
+
+/// A problem found by [`Node::validate_actions`]: an action that the node
+/// claims to support, but which doesn't make sense given its role and state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionWarning {
+    /// [`Action::Increment`] or [`Action::Decrement`] on a node that
+    /// isn't a range control and has no [`Node::value_for_range`].
+    NotRange(Action),
+    /// [`Action::Expand`] or [`Action::Collapse`] on a node whose
+    /// [`Node::expanded`] state is unset.
+    NotExpandable(Action),
+    /// A directional scroll action on a node that isn't
+    /// [`Node::scrollable`].
+    NotScrollable(Action),
+    /// [`Action::ReplaceSelectedText`] on a node that isn't
+    /// [`Node::editable`].
+    NotEditable(Action),
+    /// [`Action::CustomAction`] on a node with no
+    /// [`Node::custom_actions`].
+    NoCustomActions,
 }
 
 /// A single accessible object. A complete UI is represented as a tree of these.
+///
+/// When serialized, false flags, unset values, and empty collections,
+/// including an empty list of children, are omitted, and are restored
+/// to those defaults when deserialized. So only the ID, the role, and the
+/// [attributes that are present](Node::attributes) take up space, and a
+/// node created with [`Node::new`] serializes to just its ID and role.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
@@ -933,6 +1292,11 @@ pub struct Node {
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_empty"))]
     pub custom_actions: Box<[CustomAction]>,
 
+    /// Keys should be unique; see [`Node::custom_attribute`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_empty"))]
+    pub custom_attributes: Box<[CustomAttribute]>,
+
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub access_key: Option<Box<str>>,
 
@@ -983,8 +1347,10 @@ pub struct Node {
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub language: Option<Box<str>>,
 
+    /// See [`parse_live_relevant`].
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub live_relevant: Option<Box<str>>,
+    /// See [`parse_live_status`].
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub live_status: Option<Box<str>>,
 
@@ -1140,8 +1506,15 @@ pub struct Node {
     pub min_value_for_range: Option<f32>,
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub max_value_for_range: Option<f32>,
+    /// The amount by which [`Action::Increment`] and [`Action::Decrement`]
+    /// change the value, e.g. when the user presses an arrow key.
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub step_value_for_range: Option<f32>,
+    /// The amount by which a larger adjustment changes the value, e.g. when
+    /// the user presses Page Up or Page Down. If this isn't set,
+    /// platform adapters assume 10 times [`Node::step_value_for_range`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub large_step_value_for_range: Option<f32>,
 
     // Text attributes.
     /// Font size is in pixels.
@@ -1222,6 +1595,7 @@ impl Node {
             character_offsets: Default::default(),
             words: Default::default(),
             custom_actions: Default::default(),
+            custom_attributes: Default::default(),
             access_key: None,
             invalid_state: None,
             auto_complete: None,
@@ -1295,11 +1669,861 @@ impl Node {
             min_value_for_range: None,
             max_value_for_range: None,
             step_value_for_range: None,
+            large_step_value_for_range: None,
             font_size: None,
             font_weight: None,
             text_indent: None,
         }
     }
+
+    /// The keys of the pairs returned by [`Node::to_row`], in order.
+    /// Useful as the header of a CSV or TSV dump.
+    pub const ROW_KEYS: [&'static str; 12] = [
+        "id",
+        "role",
+        "name",
+        "value",
+        "focusable",
+        "disabled",
+        "invisible",
+        "ignored",
+        "expanded",
+        "selected",
+        "checked",
+        "bounds",
+    ];
+
+    /// Flattens the most commonly inspected fields of this node into
+    /// `(key, value)` pairs, in the order given by [`Node::ROW_KEYS`].
+    ///
+    /// This is a lossy, human-readable view intended for tabular dumps
+    /// that are diffed across builds; it isn't a serialization format.
+    /// Absent values are represented by empty strings. Bounds are
+    /// formatted as `left,top,width,height` and are relative, as in
+    /// [`RelativeBounds::rect`].
+    pub fn to_row(&self) -> Vec<(&'static str, String)> {
+        fn optional<T: ToString>(value: Option<T>) -> String {
+            value.map_or_else(String::new, |value| value.to_string())
+        }
+
+        let values = [
+            self.id.0.to_string(),
+            format!("{:?}", self.role),
+            optional(self.name.as_deref()),
+            optional(self.value.as_deref()),
+            self.focusable.to_string(),
+            self.disabled.to_string(),
+            self.invisible.to_string(),
+            self.ignored.to_string(),
+            optional(self.expanded),
+            optional(self.selected),
+            optional(self.checked_state.map(|state| format!("{:?}", state))),
+            optional(self.bounds.as_ref().map(|bounds| {
+                let rect = &bounds.rect;
+                format!("{},{},{},{}", rect.left, rect.top, rect.width, rect.height)
+            })),
+        ];
+        Self::ROW_KEYS.iter().copied().zip(values).collect()
+    }
+
+    /// Checks each of [`Node::actions`] against the node's role and state,
+    /// returning a warning for each combination that doesn't make sense,
+    /// such as incrementing a button. This is a lint rather than
+    /// a validity requirement; it's meant to be run in tests.
+    pub fn validate_actions(&self) -> Vec<ActionWarning> {
+        let is_range = self.value_for_range.is_some()
+            || matches!(self.role, Role::Slider | Role::SpinButton | Role::ScrollBar);
+        self.actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::Increment | Action::Decrement if !is_range => {
+                    Some(ActionWarning::NotRange(action))
+                }
+                Action::Expand | Action::Collapse if self.expanded.is_none() => {
+                    Some(ActionWarning::NotExpandable(action))
+                }
+                Action::ScrollBackward
+                | Action::ScrollDown
+                | Action::ScrollForward
+                | Action::ScrollLeft
+                | Action::ScrollRight
+                | Action::ScrollUp
+                | Action::SetScrollOffset
+                    if !self.scrollable =>
+                {
+                    Some(ActionWarning::NotScrollable(action))
+                }
+                Action::ReplaceSelectedText if !self.editable => {
+                    Some(ActionWarning::NotEditable(action))
+                }
+                Action::CustomAction if self.custom_actions.is_empty() => {
+                    Some(ActionWarning::NoCustomActions)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the value of the [custom attribute](Node::custom_attributes)
+    /// with the given key, if any.
+    pub fn custom_attribute(&self, key: &str) -> Option<&str> {
+        self.custom_attributes
+            .iter()
+            .find(|attribute| &*attribute.key == key)
+            .map(|attribute| &*attribute.value)
+    }
+
+    /// Returns true if the nodes are equal in every respect other than
+    /// [`Node::bounds`]. This is useful in tests, where bounds computed
+    /// by layout aren't always stable.
+    pub fn eq_ignoring_bounds(&self, other: &Node) -> bool {
+        self.without_bounds() == other.without_bounds()
+    }
+
+    /// Returns true if the nodes are equal, except that their
+    /// [`Node::bounds`] may differ as allowed by [`RelativeBounds::approx_eq`].
+    pub fn eq_with_bounds_tolerance(&self, other: &Node, epsilon: f32) -> bool {
+        let bounds_eq = match (&self.bounds, &other.bounds) {
+            (Some(a), Some(b)) => a.approx_eq(b, epsilon),
+            (None, None) => true,
+            _ => false,
+        };
+        bounds_eq && self.eq_ignoring_bounds(other)
+    }
+
+    fn without_bounds(&self) -> Node {
+        Node {
+            bounds: None,
+            ..self.clone()
+        }
+    }
+}
+
+// Rewrites the node IDs in attribute values for [`Node::map_node_ids`].
+trait MapNodeIds {
+    fn map_node_ids(&mut self, f: &mut dyn FnMut(NodeId) -> NodeId);
+}
+
+impl MapNodeIds for NodeId {
+    fn map_node_ids(&mut self, f: &mut dyn FnMut(NodeId) -> NodeId) {
+        *self = f(*self);
+    }
+}
+
+impl<T: MapNodeIds> MapNodeIds for Option<T> {
+    fn map_node_ids(&mut self, f: &mut dyn FnMut(NodeId) -> NodeId) {
+        if let Some(value) = self {
+            value.map_node_ids(f);
+        }
+    }
+}
+
+impl<T: MapNodeIds> MapNodeIds for Box<[T]> {
+    fn map_node_ids(&mut self, f: &mut dyn FnMut(NodeId) -> NodeId) {
+        for item in self.iter_mut() {
+            item.map_node_ids(f);
+        }
+    }
+}
+
+impl MapNodeIds for RelativeBounds {
+    fn map_node_ids(&mut self, f: &mut dyn FnMut(NodeId) -> NodeId) {
+        self.offset_container.map_node_ids(f);
+    }
+}
+
+impl MapNodeIds for TextSelection {
+    fn map_node_ids(&mut self, f: &mut dyn FnMut(NodeId) -> NodeId) {
+        self.anchor_node.map_node_ids(f);
+        self.focus_node.map_node_ids(f);
+    }
+}
+
+impl<T: EnumSetType> MapNodeIds for EnumSet<T> {
+    fn map_node_ids(&mut self, _f: &mut dyn FnMut(NodeId) -> NodeId) {}
+}
+
+macro_rules! no_node_ids {
+    ($($type:ty),+) => {
+        $(impl MapNodeIds for $type {
+            fn map_node_ids(&mut self, _f: &mut dyn FnMut(NodeId) -> NodeId) {}
+        })+
+    };
+}
+
+no_node_ids!(
+    bool,
+    f32,
+    usize,
+    u32,
+    Box<str>,
+    TreeId,
+    NameFrom,
+    DescriptionFrom,
+    Orientation,
+    TextDirection,
+    InvalidState,
+    CheckedState,
+    DefaultActionVerb,
+    SortDirection,
+    AriaCurrent,
+    HasPopup,
+    ListStyle,
+    TextAlign,
+    VerticalOffset,
+    TextDecoration,
+    TextMarker,
+    CustomAction,
+    CustomAttribute,
+    Range<usize>
+);
+
+// Generates [`NodeAttribute`], [`NodeAttributeKind`], and the methods
+// that convert between them and the fields of [`Node`]. Flags are
+// `bool` fields, which are present when true; values are `Option` fields;
+// collections are fields that are present when not empty.
+macro_rules! node_attributes {
+    (
+        flags: { $($flag:ident => $flag_variant:ident,)* },
+        values: { $($value:ident: $value_type:ty => $value_variant:ident,)* },
+        collections: { $($collection:ident: $collection_type:ty => $collection_variant:ident,)* },
+    ) => {
+        /// A single attribute of a [`Node`], i.e. anything other than its
+        /// ID, role, and children. This makes it possible to refer to
+        /// individual attributes, e.g. to update one attribute of a node
+        /// without sending the whole node; see [`NodePatch`].
+        #[derive(Clone, Debug, PartialEq)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        #[cfg_attr(feature = "schemars", derive(JsonSchema))]
+        #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+        pub enum NodeAttribute {
+            $($flag_variant,)*
+            $($value_variant($value_type),)*
+            $($collection_variant($collection_type),)*
+        }
+
+        /// Identifies a kind of [`NodeAttribute`] without its value.
+        ///
+        /// Every attribute of [`Node`] has a kind, and this enum is
+        /// deliberately not `#[non_exhaustive]`: code that maps attributes
+        /// to a platform API should `match` on the kind without a wildcard
+        /// arm, so that adding an attribute fails to compile until
+        /// the mapping decides how to handle it.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        #[cfg_attr(feature = "schemars", derive(JsonSchema))]
+        #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+        pub enum NodeAttributeKind {
+            $($flag_variant,)*
+            $($value_variant,)*
+            $($collection_variant,)*
+        }
+
+        impl NodeAttributeKind {
+            /// All of the kinds, in declaration order.
+            pub const ALL: &'static [NodeAttributeKind] = &[
+                $(Self::$flag_variant,)*
+                $(Self::$value_variant,)*
+                $(Self::$collection_variant,)*
+            ];
+
+            /// Returns the name of the corresponding field of [`Node`].
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$flag_variant => stringify!($flag),)*
+                    $(Self::$value_variant => stringify!($value),)*
+                    $(Self::$collection_variant => stringify!($collection),)*
+                }
+            }
+        }
+
+        impl NodeAttribute {
+            pub fn kind(&self) -> NodeAttributeKind {
+                match self {
+                    $(Self::$flag_variant => NodeAttributeKind::$flag_variant,)*
+                    $(Self::$value_variant(_) => NodeAttributeKind::$value_variant,)*
+                    $(Self::$collection_variant(_) => NodeAttributeKind::$collection_variant,)*
+                }
+            }
+
+            /// Replaces each node ID in the value of this attribute,
+            /// e.g. [`NodeAttribute::LabelledBy`], with the result of `f`.
+            /// See [`TreeUpdate::map_node_ids`].
+            pub fn map_node_ids(&mut self, mut f: impl FnMut(NodeId) -> NodeId) {
+                match self {
+                    $(Self::$flag_variant => (),)*
+                    $(Self::$value_variant(value) => value.map_node_ids(&mut f),)*
+                    $(Self::$collection_variant(value) => value.map_node_ids(&mut f),)*
+                }
+            }
+        }
+
+        impl Node {
+            /// Sets the given attribute, replacing any previous value
+            /// of the same kind.
+            pub fn set_attribute(&mut self, attribute: NodeAttribute) {
+                match attribute {
+                    $(NodeAttribute::$flag_variant => self.$flag = true,)*
+                    $(NodeAttribute::$value_variant(value) => self.$value = Some(value),)*
+                    $(NodeAttribute::$collection_variant(value) => self.$collection = value,)*
+                }
+            }
+
+            /// Sets each of the given attributes in order, as with
+            /// [`Node::set_attribute`]. If more than one attribute of the
+            /// same kind is given, the last one wins.
+            pub fn set_attributes(&mut self, attributes: impl IntoIterator<Item = NodeAttribute>) {
+                for attribute in attributes {
+                    self.set_attribute(attribute);
+                }
+            }
+
+            /// Resets the given kind of attribute to its default, absent state.
+            pub fn clear_attribute(&mut self, kind: NodeAttributeKind) {
+                match kind {
+                    $(NodeAttributeKind::$flag_variant => self.$flag = false,)*
+                    $(NodeAttributeKind::$value_variant => self.$value = None,)*
+                    $(NodeAttributeKind::$collection_variant => {
+                        self.$collection = Default::default()
+                    })*
+                }
+            }
+
+            /// Replaces the node's ID, the IDs of its children, and every
+            /// node ID in its attributes with the result of `f`.
+            /// See [`TreeUpdate::map_node_ids`].
+            pub fn map_node_ids(&mut self, mut f: impl FnMut(NodeId) -> NodeId) {
+                self.id = f(self.id);
+                self.children.map_node_ids(&mut f);
+                $(self.$value.map_node_ids(&mut f);)*
+                $(self.$collection.map_node_ids(&mut f);)*
+            }
+
+            /// Returns the kinds of attributes whose values differ between
+            /// this node and `other`, ordered by [`NodeAttributeKind`].
+            /// The IDs, roles, and children aren't compared.
+            pub fn changed_attribute_kinds(&self, other: &Node) -> Vec<NodeAttributeKind> {
+                let mut kinds = Vec::new();
+                $(if self.$flag != other.$flag {
+                    kinds.push(NodeAttributeKind::$flag_variant);
+                })*
+                $(if self.$value != other.$value {
+                    kinds.push(NodeAttributeKind::$value_variant);
+                })*
+                $(if self.$collection != other.$collection {
+                    kinds.push(NodeAttributeKind::$collection_variant);
+                })*
+                kinds
+            }
+
+            pub fn has_attribute(&self, kind: NodeAttributeKind) -> bool {
+                match kind {
+                    $(NodeAttributeKind::$flag_variant => self.$flag,)*
+                    $(NodeAttributeKind::$value_variant => self.$value.is_some(),)*
+                    $(NodeAttributeKind::$collection_variant => !self.$collection.is_empty(),)*
+                }
+            }
+
+            /// Returns all of the attributes that are present on this node,
+            /// ordered by [`NodeAttributeKind`]. Since each attribute is
+            /// stored in its own field, the order doesn't depend on the
+            /// order in which the attributes were set, so nodes with
+            /// the same attributes always produce the same list.
+            pub fn attributes(&self) -> Vec<NodeAttribute> {
+                let mut attributes = Vec::new();
+                $(if self.$flag {
+                    attributes.push(NodeAttribute::$flag_variant);
+                })*
+                $(if let Some(value) = &self.$value {
+                    attributes.push(NodeAttribute::$value_variant(value.clone()));
+                })*
+                $(if !self.$collection.is_empty() {
+                    attributes.push(NodeAttribute::$collection_variant(self.$collection.clone()));
+                })*
+                attributes
+            }
+        }
+    };
+}
+
+node_attributes! {
+    flags: {
+        autofill_available => AutofillAvailable,
+        default => Default,
+        editable => Editable,
+        focusable => Focusable,
+        hovered => Hovered,
+        ignored => Ignored,
+        invisible => Invisible,
+        linked => Linked,
+        multiline => Multiline,
+        multiselectable => Multiselectable,
+        protected => Protected,
+        required => Required,
+        visited => Visited,
+        busy => Busy,
+        nonatomic_text_field_root => NonatomicTextFieldRoot,
+        container_live_atomic => ContainerLiveAtomic,
+        container_live_busy => ContainerLiveBusy,
+        live_atomic => LiveAtomic,
+        modal => Modal,
+        canvas_has_fallback => CanvasHasFallback,
+        scrollable => Scrollable,
+        clickable => Clickable,
+        clips_children => ClipsChildren,
+        not_user_selectable_style => NotUserSelectableStyle,
+        selected_from_focus => SelectedFromFocus,
+        is_line_breaking_object => IsLineBreakingObject,
+        is_page_breaking_object => IsPageBreakingObject,
+        has_aria_attribute => HasAriaAttribute,
+        touch_pass_through => TouchPassThrough,
+        read_only => ReadOnly,
+        disabled => Disabled,
+        bold => Bold,
+        italic => Italic,
+    },
+    values: {
+        bounds: RelativeBounds => Bounds,
+        name: Box<str> => Name,
+        name_from: NameFrom => NameFrom,
+        description: Box<str> => Description,
+        description_from: DescriptionFrom => DescriptionFrom,
+        value: Box<str> => Value,
+        expanded: bool => Expanded,
+        orientation: Orientation => Orientation,
+        selected: bool => Selected,
+        grabbed: bool => Grabbed,
+        active_descendant: NodeId => ActiveDescendant,
+        error_message: NodeId => ErrorMessage,
+        in_page_link_target: NodeId => InPageLinkTarget,
+        member_of: NodeId => MemberOf,
+        next_on_line: NodeId => NextOnLine,
+        previous_on_line: NodeId => PreviousOnLine,
+        popup_for: NodeId => PopupFor,
+        text_direction: TextDirection => TextDirection,
+        access_key: Box<str> => AccessKey,
+        invalid_state: InvalidState => InvalidState,
+        auto_complete: Box<str> => AutoComplete,
+        checked_state: CheckedState => CheckedState,
+        checked_state_description: Box<str> => CheckedStateDescription,
+        child_tree: TreeId => ChildTree,
+        class_name: Box<str> => ClassName,
+        container_live_relevant: Box<str> => ContainerLiveRelevant,
+        container_live_status: Box<str> => ContainerLiveStatus,
+        css_display: Box<str> => CssDisplay,
+        font_family: Box<str> => FontFamily,
+        html_tag: Box<str> => HtmlTag,
+        inner_html: Box<str> => InnerHtml,
+        input_type: Box<str> => InputType,
+        key_shortcuts: Box<str> => KeyShortcuts,
+        language: Box<str> => Language,
+        live_relevant: Box<str> => LiveRelevant,
+        live_status: Box<str> => LiveStatus,
+        placeholder: Box<str> => Placeholder,
+        aria_role: Box<str> => AriaRole,
+        role_description: Box<str> => RoleDescription,
+        tooltip: Box<str> => Tooltip,
+        url: Box<str> => Url,
+        default_action_verb: DefaultActionVerb => DefaultActionVerb,
+        scroll_x: f32 => ScrollX,
+        scroll_x_min: f32 => ScrollXMin,
+        scroll_x_max: f32 => ScrollXMax,
+        scroll_y: f32 => ScrollY,
+        scroll_y_min: f32 => ScrollYMin,
+        scroll_y_max: f32 => ScrollYMax,
+        text_selection: TextSelection => TextSelection,
+        aria_column_count: usize => AriaColumnCount,
+        aria_cell_column_index: usize => AriaCellColumnIndex,
+        aria_cell_column_span: usize => AriaCellColumnSpan,
+        aria_row_count: usize => AriaRowCount,
+        aria_cell_row_index: usize => AriaCellRowIndex,
+        aria_cell_row_span: usize => AriaCellRowSpan,
+        table_row_count: usize => TableRowCount,
+        table_column_count: usize => TableColumnCount,
+        table_header: NodeId => TableHeader,
+        table_row_index: usize => TableRowIndex,
+        table_row_header: NodeId => TableRowHeader,
+        table_column_index: usize => TableColumnIndex,
+        table_column_header: NodeId => TableColumnHeader,
+        table_cell_column_index: usize => TableCellColumnIndex,
+        table_cell_column_span: usize => TableCellColumnSpan,
+        table_cell_row_index: usize => TableCellRowIndex,
+        table_cell_row_span: usize => TableCellRowSpan,
+        sort_direction: SortDirection => SortDirection,
+        hierarchical_level: usize => HierarchicalLevel,
+        set_size: usize => SetSize,
+        pos_in_set: usize => PosInSet,
+        color_value: u32 => ColorValue,
+        aria_current: AriaCurrent => AriaCurrent,
+        background_color: u32 => BackgroundColor,
+        foreground_color: u32 => ForegroundColor,
+        has_popup: HasPopup => HasPopup,
+        list_style: ListStyle => ListStyle,
+        text_align: TextAlign => TextAlign,
+        vertical_offset: VerticalOffset => VerticalOffset,
+        overline: TextDecoration => Overline,
+        strikethrough: TextDecoration => Strikethrough,
+        underline: TextDecoration => Underline,
+        previous_focus: NodeId => PreviousFocus,
+        next_focus: NodeId => NextFocus,
+        value_for_range: f32 => ValueForRange,
+        min_value_for_range: f32 => MinValueForRange,
+        max_value_for_range: f32 => MaxValueForRange,
+        step_value_for_range: f32 => StepValueForRange,
+        large_step_value_for_range: f32 => LargeStepValueForRange,
+        font_size: f32 => FontSize,
+        font_weight: f32 => FontWeight,
+        text_indent: f32 => TextIndent,
+    },
+    collections: {
+        actions: EnumSet<Action> => Actions,
+        drop_effects: EnumSet<DropEffect> => DropEffects,
+        indirect_children: Box<[NodeId]> => IndirectChildren,
+        controls: Box<[NodeId]> => Controls,
+        details: Box<[NodeId]> => Details,
+        described_by: Box<[NodeId]> => DescribedBy,
+        flow_to: Box<[NodeId]> => FlowTo,
+        labelled_by: Box<[NodeId]> => LabelledBy,
+        radio_groups: Box<[NodeId]> => RadioGroups,
+        markers: Box<[TextMarker]> => Markers,
+        character_offsets: Box<[f32]> => CharacterOffsets,
+        words: Box<[Range<usize>]> => Words,
+        custom_actions: Box<[CustomAction]> => CustomActions,
+        custom_attributes: Box<[CustomAttribute]> => CustomAttributes,
+    },
+}
+
+/// An update to some of the attributes of a node that's already in the tree.
+/// This is more compact than sending the whole [`Node`] when only one or two
+/// attributes change frequently, e.g. the value of a progress indicator.
+///
+/// When a patch is deserialized from a human-readable format such as JSON,
+/// attributes that this version of the schema doesn't recognize, e.g.
+/// because the patch came from a newer producer, don't cause
+/// deserialization to fail. Instead, their tags are collected in
+/// [`NodePatch::unknown_attributes`], so that the consumer can log or
+/// count them. Compact formats such as bincode aren't self-describing,
+/// so an unknown attribute can't be skipped, and it's still an error.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[cfg_attr(feature = "serde", serde(from = "SerializedNodePatch"))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct NodePatch {
+    pub id: NodeId,
+    /// Attributes to set, replacing any previous values of the same kind.
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_empty"))]
+    pub set: Vec<NodeAttribute>,
+    /// Kinds of attributes to remove. Removals are applied before
+    /// the attributes in [`NodePatch::set`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_empty"))]
+    pub remove: Vec<NodeAttributeKind>,
+    /// The tags of any attributes in `set` or `remove` that weren't
+    /// recognized when the patch was deserialized. These attributes
+    /// are ignored when the patch is applied, and aren't serialized.
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub unknown_attributes: Vec<Box<str>>,
+}
+
+impl NodePatch {
+    pub fn new(id: NodeId) -> NodePatch {
+        NodePatch {
+            id,
+            set: Vec::new(),
+            remove: Vec::new(),
+            unknown_attributes: Vec::new(),
+        }
+    }
+
+    /// Applies this patch to the given node, which must have the same ID.
+    pub fn apply(self, node: &mut Node) {
+        assert_eq!(self.id, node.id);
+        for kind in self.remove {
+            node.clear_attribute(kind);
+        }
+        for attribute in self.set {
+            node.set_attribute(attribute);
+        }
+    }
+
+    /// Returns the kinds of attributes that appear more than once in
+    /// [`NodePatch::set`], in [`NodeAttributeKind`] order. Only the last
+    /// of those attributes takes effect, so a duplicate is usually a bug
+    /// in the code that built the patch. A [`Node`] can't have duplicate
+    /// attributes, since each one is stored in its own field.
+    pub fn duplicate_attribute_kinds(&self) -> Vec<NodeAttributeKind> {
+        let mut seen = HashSet::new();
+        let mut duplicates: Vec<_> = self
+            .set
+            .iter()
+            .map(NodeAttribute::kind)
+            .filter(|kind| !seen.insert(*kind))
+            .collect();
+        duplicates.sort();
+        duplicates.dedup();
+        duplicates
+    }
+}
+
+// The tag of an attribute that isn't a variant of `NodeAttribute` or
+// `NodeAttributeKind`, e.g. one that was added in a newer version of
+// the schema. With an externally tagged enum, a flag is serialized as
+// just its tag, and any other attribute as a map with a single entry
+// whose key is the tag.
+#[cfg(feature = "serde")]
+struct UnknownAttributeTag(Box<str>);
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for UnknownAttributeTag {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::{self, IgnoredAny, IntoDeserializer, MapAccess, Visitor};
+
+        // An attribute with a known tag that still failed to deserialize,
+        // e.g. because its value has the wrong type, is an error.
+        fn unknown<E: de::Error>(tag: &str) -> Result<UnknownAttributeTag, E> {
+            let deserializer: de::value::StrDeserializer<E> = tag.into_deserializer();
+            match NodeAttributeKind::deserialize(deserializer) {
+                Ok(_) => Err(E::custom(format_args!(
+                    "invalid value for attribute {}",
+                    tag
+                ))),
+                Err(_) => Ok(UnknownAttributeTag(tag.into())),
+            }
+        }
+
+        struct TagVisitor;
+
+        impl<'de> Visitor<'de> for TagVisitor {
+            type Value = UnknownAttributeTag;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("an attribute tag, or a map from a tag to a value")
+            }
+
+            fn visit_str<E: de::Error>(self, tag: &str) -> Result<Self::Value, E> {
+                unknown(tag)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let (tag, _) = map
+                    .next_entry::<String, IgnoredAny>()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                if map.next_key::<IgnoredAny>()?.is_some() {
+                    return Err(de::Error::invalid_length(2, &self));
+                }
+                unknown(&tag)
+            }
+        }
+
+        deserializer.deserialize_any(TagVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+enum MaybeKnownAttribute<T> {
+    Known(T),
+    Unknown(UnknownAttributeTag),
+}
+
+// Trying each variant in turn requires buffering the attribute, which
+// needs `deserialize_any`, so it's only done for human-readable formats,
+// which are self-describing.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SelfDescribingAttribute<T> {
+    Known(T),
+    Unknown(UnknownAttributeTag),
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MaybeKnownAttribute<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return T::deserialize(deserializer).map(MaybeKnownAttribute::Known);
+        }
+        Ok(match SelfDescribingAttribute::deserialize(deserializer)? {
+            SelfDescribingAttribute::Known(attribute) => MaybeKnownAttribute::Known(attribute),
+            SelfDescribingAttribute::Unknown(tag) => MaybeKnownAttribute::Unknown(tag),
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn partition_known<T>(
+    attributes: Vec<MaybeKnownAttribute<T>>,
+    unknown_attributes: &mut Vec<Box<str>>,
+) -> Vec<T> {
+    attributes
+        .into_iter()
+        .filter_map(|attribute| match attribute {
+            MaybeKnownAttribute::Known(attribute) => Some(attribute),
+            MaybeKnownAttribute::Unknown(UnknownAttributeTag(tag)) => {
+                unknown_attributes.push(tag);
+                None
+            }
+        })
+        .collect()
+}
+
+// The serialized form of `NodePatch`, which tolerates unknown attributes.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+struct SerializedNodePatch {
+    id: NodeId,
+    #[serde(default)]
+    set: Vec<MaybeKnownAttribute<NodeAttribute>>,
+    #[serde(default)]
+    remove: Vec<MaybeKnownAttribute<NodeAttributeKind>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SerializedNodePatch> for NodePatch {
+    fn from(patch: SerializedNodePatch) -> Self {
+        let mut unknown_attributes = Vec::new();
+        let set = partition_known(patch.set, &mut unknown_attributes);
+        let remove = partition_known(patch.remove, &mut unknown_attributes);
+        NodePatch {
+            id: patch.id,
+            set,
+            remove,
+            unknown_attributes,
+        }
+    }
+}
+
+/// Converts ARIA attributes, given as `(name, value)` pairs such as
+/// `("aria-checked", "mixed")`, into the corresponding [`NodeAttribute`]s.
+/// This is meant for porting UIs that already describe themselves with ARIA.
+///
+/// Names and enumerated values are matched case-insensitively, following
+/// HTML. Attributes that have no equivalent here, and values that can't be
+/// parsed, are ignored. ARIA attributes whose value means "not set", such as
+/// `aria-expanded="undefined"` or `aria-haspopup="false"`, produce nothing.
+/// Attributes that refer to other elements by ID, like `aria-controls`,
+/// aren't handled, since those IDs must first be mapped to [`NodeId`]s.
+pub fn attributes_from_aria(pairs: &[(&str, &str)]) -> Vec<NodeAttribute> {
+    fn parse_bool(value: &str) -> Option<bool> {
+        match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn parse_checked_state(value: &str) -> Option<CheckedState> {
+        match value {
+            "true" => Some(CheckedState::True),
+            "false" => Some(CheckedState::False),
+            "mixed" => Some(CheckedState::Mixed),
+            _ => None,
+        }
+    }
+
+    let mut attributes = Vec::new();
+    for (name, value) in pairs {
+        let name = name.trim().to_ascii_lowercase();
+        let raw_value = value.trim();
+        let value = raw_value.to_ascii_lowercase();
+        let value = value.as_str();
+        let flag = |attribute| parse_bool(value).filter(|set| *set).map(|_| attribute);
+        let string = |attribute: fn(Box<str>) -> NodeAttribute| {
+            (!raw_value.is_empty()).then(|| attribute(raw_value.into()))
+        };
+        let attribute = match name.as_str() {
+            "aria-label" => string(NodeAttribute::Name),
+            "aria-description" => string(NodeAttribute::Description),
+            "aria-roledescription" => string(NodeAttribute::RoleDescription),
+            "aria-valuetext" => string(NodeAttribute::Value),
+            "aria-placeholder" => string(NodeAttribute::Placeholder),
+            "aria-keyshortcuts" => string(NodeAttribute::KeyShortcuts),
+            "aria-autocomplete" => string(NodeAttribute::AutoComplete),
+            "aria-live" => string(NodeAttribute::LiveStatus),
+            "aria-relevant" => string(NodeAttribute::LiveRelevant),
+            "aria-atomic" => flag(NodeAttribute::LiveAtomic),
+            "aria-busy" => flag(NodeAttribute::Busy),
+            "aria-disabled" => flag(NodeAttribute::Disabled),
+            "aria-modal" => flag(NodeAttribute::Modal),
+            "aria-multiline" => flag(NodeAttribute::Multiline),
+            "aria-multiselectable" => flag(NodeAttribute::Multiselectable),
+            "aria-readonly" => flag(NodeAttribute::ReadOnly),
+            "aria-required" => flag(NodeAttribute::Required),
+            "aria-expanded" => parse_bool(value).map(NodeAttribute::Expanded),
+            "aria-selected" => parse_bool(value).map(NodeAttribute::Selected),
+            "aria-grabbed" => parse_bool(value).map(NodeAttribute::Grabbed),
+            "aria-checked" | "aria-pressed" => {
+                parse_checked_state(value).map(NodeAttribute::CheckedState)
+            }
+            "aria-invalid" => match value {
+                "" => None,
+                "false" => Some(InvalidState::False),
+                "true" => Some(InvalidState::True),
+                _ => Some(InvalidState::Other(value.into())),
+            }
+            .map(NodeAttribute::InvalidState),
+            "aria-current" => match value {
+                "" => None,
+                "false" => Some(AriaCurrent::False),
+                "page" => Some(AriaCurrent::Page),
+                "step" => Some(AriaCurrent::Step),
+                "location" => Some(AriaCurrent::Location),
+                "date" => Some(AriaCurrent::Date),
+                "time" => Some(AriaCurrent::Time),
+                // ARIA treats any other non-empty value as "true".
+                _ => Some(AriaCurrent::True),
+            }
+            .map(NodeAttribute::AriaCurrent),
+            "aria-haspopup" => match value {
+                "true" => Some(HasPopup::True),
+                "menu" => Some(HasPopup::Menu),
+                "listbox" => Some(HasPopup::Listbox),
+                "tree" => Some(HasPopup::Tree),
+                "grid" => Some(HasPopup::Grid),
+                "dialog" => Some(HasPopup::Dialog),
+                _ => None,
+            }
+            .map(NodeAttribute::HasPopup),
+            "aria-orientation" => match value {
+                "horizontal" => Some(Orientation::Horizontal),
+                "vertical" => Some(Orientation::Vertical),
+                _ => None,
+            }
+            .map(NodeAttribute::Orientation),
+            "aria-sort" => match value {
+                "none" => Some(SortDirection::Unsorted),
+                "ascending" => Some(SortDirection::Ascending),
+                "descending" => Some(SortDirection::Descending),
+                "other" => Some(SortDirection::Other),
+                _ => None,
+            }
+            .map(NodeAttribute::SortDirection),
+            "aria-valuenow" => value.parse().ok().map(NodeAttribute::ValueForRange),
+            "aria-valuemin" => value.parse().ok().map(NodeAttribute::MinValueForRange),
+            "aria-valuemax" => value.parse().ok().map(NodeAttribute::MaxValueForRange),
+            "aria-level" => value.parse().ok().map(NodeAttribute::HierarchicalLevel),
+            "aria-posinset" => value.parse().ok().map(NodeAttribute::PosInSet),
+            "aria-setsize" => value.parse().ok().map(NodeAttribute::SetSize),
+            "aria-colcount" => value.parse().ok().map(NodeAttribute::AriaColumnCount),
+            "aria-colindex" => value.parse().ok().map(NodeAttribute::AriaCellColumnIndex),
+            "aria-colspan" => value.parse().ok().map(NodeAttribute::AriaCellColumnSpan),
+            "aria-rowcount" => value.parse().ok().map(NodeAttribute::AriaRowCount),
+            "aria-rowindex" => value.parse().ok().map(NodeAttribute::AriaCellRowIndex),
+            "aria-rowspan" => value.parse().ok().map(NodeAttribute::AriaCellRowSpan),
+            _ => None,
+        };
+        attributes.extend(attribute);
+    }
+    attributes
 }
 
 /// The data associated with an accessibility tree that's global to the
@@ -1344,11 +2568,51 @@ impl Tree {
     }
 }
 
+/// Builds a [`Tree`] with chained setters. The source string encoding
+/// is [`StringEncoding::Utf8`] unless it's set.
+#[derive(Clone)]
+pub struct TreeBuilder {
+    tree: Tree,
+}
+
+impl TreeBuilder {
+    pub fn new(id: TreeId, root: NodeId) -> Self {
+        Self {
+            tree: Tree::new(id, root, StringEncoding::Utf8),
+        }
+    }
+
+    pub fn source_string_encoding(mut self, encoding: StringEncoding) -> Self {
+        self.tree.source_string_encoding = encoding;
+        self
+    }
+
+    pub fn parent(mut self, parent: TreeId) -> Self {
+        self.tree.parent = Some(parent);
+        self
+    }
+
+    pub fn root_scroller(mut self, root_scroller: NodeId) -> Self {
+        self.tree.root_scroller = Some(root_scroller);
+        self
+    }
+
+    pub fn build(self) -> Tree {
+        self.tree
+    }
+}
+
 /// A serializable representation of an atomic change to a tree.
 /// The sender and receiver must be in sync; the update is only meant
 /// to bring the tree from a specific previous state into its next state.
-/// Trying to apply it to the wrong tree should immediately panic.
-#[derive(Clone, PartialEq)]
+/// Trying to apply it to the wrong tree is an error; see `Tree::try_update`
+/// in the consumer crate.
+///
+/// Only [`TreeUpdate::patches`] tolerate attributes from a newer version
+/// of the schema; see [`NodePatch`]. A [`Node`] in [`TreeUpdate::nodes`]
+/// with a field that this version doesn't recognize fails to deserialize,
+/// as does any other unknown field.
+#[derive(Clone, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "schemars", derive(JsonSchema))]
 #[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
@@ -1402,6 +2666,374 @@ pub struct TreeUpdate {
     /// and the AccessKit platform adapters.
     #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub focus: Option<NodeId>,
+
+    /// Zero or more attribute-level updates, which are applied after
+    /// [`TreeUpdate::nodes`]. Each patch must refer to a node that is
+    /// in the tree once those node updates have been applied.
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_empty"))]
+    pub patches: Vec<NodePatch>,
+
+    /// Zero or more changes to the bounds of nodes, which are applied
+    /// after [`TreeUpdate::patches`]. Each one replaces the
+    /// [`Node::bounds`] of the given node, which must be in the tree once
+    /// the other updates have been applied. This has the same effect as
+    /// a patch that sets or removes [`NodeAttribute::Bounds`], but is
+    /// cheaper to send and apply when only bounds are changing, e.g.
+    /// while the user is scrolling.
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_empty"))]
+    pub bounds_updates: Vec<(NodeId, Option<RelativeBounds>)>,
+
+    /// An optional version number for this update. Once a tree has
+    /// received an update with a version, it rejects any later update
+    /// whose version isn't greater than the last one applied, which
+    /// protects against updates that are delivered out of order.
+    /// Updates without a version are always applied.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub version: Option<u64>,
+}
+
+impl TreeUpdate {
+    /// Returns true if the updates are equal, except for the bounds of
+    /// the nodes in [`TreeUpdate::nodes`], which are compared with
+    /// [`Node::eq_ignoring_bounds`]. Bounds set by patches are
+    /// still compared.
+    pub fn eq_ignoring_bounds(&self, other: &TreeUpdate) -> bool {
+        self.clear == other.clear
+            && self.tree == other.tree
+            && self.focus == other.focus
+            && self.patches == other.patches
+            && self.bounds_updates == other.bounds_updates
+            && self.version == other.version
+            && self.nodes.len() == other.nodes.len()
+            && self
+                .nodes
+                .iter()
+                .zip(&other.nodes)
+                .all(|(a, b)| a.eq_ignoring_bounds(b))
+    }
+
+    /// Replaces every node ID in the update with the result of `f`,
+    /// including the IDs of nodes and their children, IDs in attributes
+    /// such as [`Node::labelled_by`] and [`Node::active_descendant`],
+    /// and those in patches, [`TreeUpdate::focus`], and [`TreeUpdate::tree`].
+    /// This makes it possible to combine updates from several sources,
+    /// each with its own ID space, into one tree. `f` must map
+    /// different IDs to different IDs.
+    pub fn map_node_ids(&mut self, mut f: impl FnMut(NodeId) -> NodeId) {
+        self.clear.map_node_ids(&mut f);
+        for node in &mut self.nodes {
+            node.map_node_ids(&mut f);
+        }
+        if let Some(tree) = &mut self.tree {
+            tree.root.map_node_ids(&mut f);
+            tree.root_scroller.map_node_ids(&mut f);
+        }
+        self.focus.map_node_ids(&mut f);
+        for patch in &mut self.patches {
+            patch.id.map_node_ids(&mut f);
+            for attribute in &mut patch.set {
+                attribute.map_node_ids(&mut f);
+            }
+        }
+        for (id, bounds) in &mut self.bounds_updates {
+            id.map_node_ids(&mut f);
+            bounds.map_node_ids(&mut f);
+        }
+    }
+
+    /// Adds `offset` to every node ID in the update, as described in
+    /// [`TreeUpdate::map_node_ids`]. Panics if an ID overflows.
+    pub fn offset_node_ids(&mut self, offset: u64) {
+        self.map_node_ids(|id| {
+            NodeId(
+                id.0.checked_add(offset)
+                    .expect("node ID overflowed when offset"),
+            )
+        });
+    }
+
+    /// Removes every attribute for which `keep` returns false from the
+    /// nodes in the update and from [`NodePatch::set`], e.g. to drop
+    /// attributes that a particular platform never reads before sending
+    /// the update to it. Node IDs, roles, children, and patch removals
+    /// are left untouched.
+    pub fn retain_attributes(&mut self, keep: impl Fn(&NodeAttribute) -> bool) {
+        for node in &mut self.nodes {
+            for attribute in node.attributes() {
+                if !keep(&attribute) {
+                    node.clear_attribute(attribute.kind());
+                }
+            }
+        }
+        for patch in &mut self.patches {
+            patch.set.retain(&keep);
+        }
+        self.bounds_updates.retain(|(_, bounds)| match bounds {
+            Some(bounds) => keep(&NodeAttribute::Bounds(bounds.clone())),
+            None => true,
+        });
+    }
+
+    /// Combines `other`, which would otherwise be applied immediately
+    /// after this update, into this update, so that applying the result
+    /// has the same effect on the tree as applying both in sequence.
+    ///
+    /// A node that's updated by both keeps its position in this update's
+    /// [`TreeUpdate::nodes`], with the data from `other`, and patches and
+    /// bounds updates in this update for that node are dropped. Nodes that
+    /// this update adds but `other` removes again are dropped, along with
+    /// their patches and bounds updates.
+    /// The [`TreeUpdate::tree`] and [`TreeUpdate::version`] of `other`
+    /// take precedence if they're set, and its focus always does. If
+    /// `other` is stale relative to this update, as described in
+    /// [`TreeUpdate::version`], it's discarded, just as the tree would
+    /// discard it.
+    ///
+    /// A clear can only be expressed before all of the node updates, so if
+    /// `other` has a [`TreeUpdate::clear`], the updates can't be combined,
+    /// and `other` is returned unchanged in the error. Only a clear in this
+    /// update is preserved.
+    pub fn merge(&mut self, other: TreeUpdate) -> Result<(), Box<TreeUpdate>> {
+        if other.clear.is_some() {
+            return Err(Box::new(other));
+        }
+        if let (Some(version), Some(other_version)) = (self.version, other.version) {
+            if other_version <= version {
+                return Ok(());
+            }
+        }
+
+        let mut indices: HashMap<NodeId, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let mut replaced = HashSet::new();
+        let mut detached = Vec::new();
+        for node in other.nodes {
+            replaced.insert(node.id);
+            match indices.get(&node.id) {
+                Some(&index) => {
+                    let old_node = std::mem::replace(&mut self.nodes[index], node);
+                    let new_node = &self.nodes[index];
+                    detached.extend(
+                        old_node
+                            .children
+                            .iter()
+                            .filter(|id| !new_node.children.contains(id))
+                            .copied(),
+                    );
+                }
+                None => {
+                    indices.insert(node.id, self.nodes.len());
+                    self.nodes.push(node);
+                }
+            }
+        }
+
+        if other.tree.is_some() {
+            self.tree = other.tree;
+        }
+        let root = self.tree.as_ref().map(|tree| tree.root);
+
+        // A node that was a child in this update but isn't a child of any
+        // node after merging was removed by `other`, along with any of its
+        // descendants that weren't moved elsewhere. If it was added by this
+        // update, it would be an unattached node in the merged update.
+        let mut parent_counts: HashMap<NodeId, usize> = HashMap::new();
+        for node in &self.nodes {
+            for child_id in node.children.iter() {
+                *parent_counts.entry(*child_id).or_default() += 1;
+            }
+        }
+        let mut removed = HashSet::new();
+        while let Some(id) = detached.pop() {
+            if Some(id) == root || parent_counts.get(&id).copied().unwrap_or(0) > 0 {
+                continue;
+            }
+            if !removed.insert(id) {
+                continue;
+            }
+            if let Some(&index) = indices.get(&id) {
+                for child_id in self.nodes[index].children.iter() {
+                    let count = parent_counts.get_mut(child_id).unwrap();
+                    *count -= 1;
+                    detached.push(*child_id);
+                }
+            }
+        }
+        if !removed.is_empty() {
+            self.nodes.retain(|node| !removed.contains(&node.id));
+        }
+
+        self.patches
+            .retain(|patch| !replaced.contains(&patch.id) && !removed.contains(&patch.id));
+        // Bounds updates are applied after patches, so one in this update
+        // would otherwise override bounds set by a patch in `other`.
+        let patched_bounds: HashSet<NodeId> = other
+            .patches
+            .iter()
+            .filter(|patch| {
+                patch.remove.contains(&NodeAttributeKind::Bounds)
+                    || patch
+                        .set
+                        .iter()
+                        .any(|attribute| attribute.kind() == NodeAttributeKind::Bounds)
+            })
+            .map(|patch| patch.id)
+            .collect();
+        self.bounds_updates.retain(|(id, _)| {
+            !replaced.contains(id) && !removed.contains(id) && !patched_bounds.contains(id)
+        });
+        self.patches.extend(other.patches);
+        self.bounds_updates.extend(other.bounds_updates);
+        self.focus = other.focus;
+        if other.version.is_some() {
+            self.version = other.version;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a live status such as [`Node::live_status`], i.e. one of the
+/// `aria-live` tokens `"off"`, `"polite"`, or `"assertive"`, ignoring
+/// ASCII case and surrounding whitespace. Anything else is treated as
+/// `"off"`, which is the ARIA default.
+pub fn parse_live_status(s: &str) -> LivePoliteness {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("polite") {
+        LivePoliteness::Polite
+    } else if s.eq_ignore_ascii_case("assertive") {
+        LivePoliteness::Assertive
+    } else {
+        LivePoliteness::Off
+    }
+}
+
+/// Parses a whitespace-separated list of `aria-relevant` tokens such as
+/// [`Node::live_relevant`]: `"additions"`, `"removals"`, `"text"`, and
+/// `"all"`, which stands for all three. Case is ignored, and so are
+/// unknown tokens. If no token is recognized, the result is the ARIA
+/// default, `"additions text"`.
+pub fn parse_live_relevant(s: &str) -> EnumSet<LiveRelevant> {
+    let mut result = EnumSet::new();
+    for token in s.split_ascii_whitespace() {
+        if token.eq_ignore_ascii_case("additions") {
+            result |= LiveRelevant::Additions;
+        } else if token.eq_ignore_ascii_case("removals") {
+            result |= LiveRelevant::Removals;
+        } else if token.eq_ignore_ascii_case("text") {
+            result |= LiveRelevant::Text;
+        } else if token.eq_ignore_ascii_case("all") {
+            result = EnumSet::all();
+        }
+    }
+    if result.is_empty() {
+        LiveRelevant::Additions | LiveRelevant::Text
+    } else {
+        result
+    }
+}
+
+/// Returns the IDs that appear in the children of a node in
+/// [`TreeUpdate::nodes`] but that aren't themselves in the update,
+/// in the order in which they're first referenced. When an update
+/// initializes a tree, each of these is a placeholder that's never
+/// filled, which is a fatal error. A producer can use this to check
+/// its updates in tests; for an incremental update, a returned ID
+/// is only an error if it isn't already a child of the same node.
+pub fn find_dangling_children(update: &TreeUpdate) -> Vec<NodeId> {
+    let defined: HashSet<NodeId> = update.nodes.iter().map(|node| node.id).collect();
+    let mut reported = HashSet::new();
+    update
+        .nodes
+        .iter()
+        .flat_map(|node| node.children.iter().copied())
+        .filter(|id| !defined.contains(id) && reported.insert(*id))
+        .collect()
+}
+
+/// Assembles a [`TreeUpdate`] with chained setters.
+#[derive(Clone)]
+pub struct TreeUpdateBuilder {
+    update: TreeUpdate,
+    tree_id: TreeId,
+    root: Option<NodeId>,
+}
+
+impl TreeUpdateBuilder {
+    /// Starts an update for the tree with the given ID, which is used
+    /// if the tree data is created by [`TreeUpdateBuilder::root`].
+    pub fn new(tree_id: TreeId) -> Self {
+        Self {
+            update: TreeUpdate::default(),
+            tree_id,
+            root: None,
+        }
+    }
+
+    pub fn clear(mut self, id: NodeId) -> Self {
+        self.update.clear = Some(id);
+        self
+    }
+
+    /// Adds the root node, which is placed before any other nodes. When the
+    /// update is built, the root of [`TreeUpdate::tree`] is set to this
+    /// node's ID; if no tree was given, one is created with
+    /// [`TreeBuilder::new`] and the ID passed to [`TreeUpdateBuilder::new`].
+    pub fn root(mut self, node: Node) -> Self {
+        self.root = Some(node.id);
+        self.update.nodes.insert(0, node);
+        self
+    }
+
+    pub fn node(mut self, node: Node) -> Self {
+        self.update.nodes.push(node);
+        self
+    }
+
+    pub fn nodes(mut self, nodes: impl IntoIterator<Item = Node>) -> Self {
+        self.update.nodes.extend(nodes);
+        self
+    }
+
+    pub fn tree(mut self, tree: Tree) -> Self {
+        self.update.tree = Some(tree);
+        self
+    }
+
+    pub fn focus(mut self, id: NodeId) -> Self {
+        self.update.focus = Some(id);
+        self
+    }
+
+    pub fn patch(mut self, patch: NodePatch) -> Self {
+        self.update.patches.push(patch);
+        self
+    }
+
+    pub fn bounds(mut self, id: NodeId, bounds: Option<RelativeBounds>) -> Self {
+        self.update.bounds_updates.push((id, bounds));
+        self
+    }
+
+    pub fn version(mut self, version: u64) -> Self {
+        self.update.version = Some(version);
+        self
+    }
+
+    pub fn build(mut self) -> TreeUpdate {
+        if let Some(root) = self.root {
+            match &mut self.update.tree {
+                Some(tree) => tree.root = root,
+                None => self.update.tree = Some(TreeBuilder::new(self.tree_id, root).build()),
+            }
+        }
+        self.update
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1411,6 +3043,8 @@ pub struct TreeUpdate {
 pub enum ActionData {
     CustomAction(i32),
     Value(Box<str>),
+    /// The new value for [`Action::SetValue`] on a range control.
+    NumericValue(f64),
     /// Optional target rectangle for [`Action::ScrollIntoView`], in node-local
     /// coordinates.
     ScrollTargetRect(Rect),
@@ -1427,7 +3061,1228 @@ pub enum ActionData {
 #[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct ActionRequest {
-    action: Action,
-    target: NodeId,
-    data: Option<ActionData>,
+    pub action: Action,
+    pub target: NodeId,
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub data: Option<ActionData>,
+}
+
+/// Handles requests from assistive technologies or other clients.
+pub trait ActionHandler {
+    /// Perform the requested action. If the requested action is not supported,
+    /// this method must do nothing.
+    ///
+    /// This method may be called on any thread. In particular, on some
+    /// platforms, it may be called on a thread other than the UI thread,
+    /// so the implementation may need to forward the request to the UI thread.
+    fn do_action(&self, request: ActionRequest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU64;
+
+    #[test]
+    fn node_id_from_raw() {
+        assert_eq!(Ok(NodeId(NonZeroU64::new(7).unwrap())), NodeId::try_from(7));
+        assert_eq!(Err(ZeroNodeIdError), NodeId::try_from(0));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn zero_node_id_is_a_descriptive_error() {
+        use serde::de::{
+            value::{Error, MapDeserializer},
+            IntoDeserializer,
+        };
+
+        let id = NodeId::deserialize(IntoDeserializer::<Error>::into_deserializer(3u64)).unwrap();
+        assert_eq!(NodeId(NonZeroU64::new(3).unwrap()), id);
+        let error =
+            NodeId::deserialize(IntoDeserializer::<Error>::into_deserializer(0u64)).unwrap_err();
+        assert_eq!(ZeroNodeIdError.to_string(), error.to_string());
+
+        let error = Node::deserialize(MapDeserializer::<_, Error>::new(
+            vec![("id", 0u64)].into_iter(),
+        ))
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("node IDs must be nonzero"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn unknown_attribute_tags() {
+        use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+
+        let mut unknown_attributes = Vec::new();
+        let kinds =
+            Vec::<MaybeKnownAttribute<NodeAttributeKind>>::deserialize(
+                SeqDeserializer::<_, Error>::new(vec!["name", "sparkle", "busy"].into_iter()),
+            )
+            .unwrap();
+        assert_eq!(
+            vec![NodeAttributeKind::Name, NodeAttributeKind::Busy],
+            partition_known(kinds, &mut unknown_attributes)
+        );
+
+        let attribute = |tag: &'static str, value: f32| {
+            MaybeKnownAttribute::<NodeAttribute>::deserialize(MapDeserializer::<_, Error>::new(
+                std::iter::once((tag, value)),
+            ))
+        };
+        let attributes = vec![
+            attribute("valueForRange", 4.0).unwrap(),
+            attribute("glowRadius", 2.0).unwrap(),
+        ];
+        assert_eq!(
+            vec![NodeAttribute::ValueForRange(4.0)],
+            partition_known(attributes, &mut unknown_attributes)
+        );
+        let expected: Vec<Box<str>> = vec!["sparkle".into(), "glowRadius".into()];
+        assert_eq!(expected, unknown_attributes);
+
+        // A known attribute with a value of the wrong type is still an error.
+        assert!(attribute("name", 1.0).is_err());
+    }
+
+    // A minimal self-describing value, like a parsed JSON document,
+    // for deserializing whole payloads without a JSON parser.
+    #[cfg(feature = "serde")]
+    enum Value {
+        U64(u64),
+        Str(String),
+        Seq(Vec<Value>),
+        Map(Vec<(&'static str, Value)>),
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::de::IntoDeserializer<'de, serde::de::value::Error> for Value {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserializer<'de> for Value {
+        type Error = serde::de::value::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            use serde::de::value::{MapDeserializer, SeqDeserializer};
+
+            match self {
+                Value::U64(value) => visitor.visit_u64(value),
+                Value::Str(value) => visitor.visit_string(value),
+                Value::Seq(values) => visitor.visit_seq(SeqDeserializer::new(values.into_iter())),
+                Value::Map(entries) => visitor.visit_map(MapDeserializer::new(entries.into_iter())),
+            }
+        }
+
+        // A unit variant, such as a role, is just its name.
+        fn deserialize_enum<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            use serde::de::IntoDeserializer;
+
+            match self {
+                Value::Str(variant) => visitor.visit_enum(variant.into_deserializer()),
+                value => value.deserialize_any(visitor),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct identifier ignored_any
+        }
+    }
+
+    // The reverse, for inspecting a serialized payload without a JSON
+    // serializer. Only the kinds of data that `Value` can hold are
+    // supported.
+    #[cfg(feature = "serde")]
+    struct ValueSerializer;
+
+    #[cfg(feature = "serde")]
+    fn to_value<T: Serialize>(value: &T) -> Value {
+        value.serialize(ValueSerializer).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    fn unsupported() -> serde::de::value::Error {
+        serde::ser::Error::custom("not supported by Value")
+    }
+
+    #[cfg(feature = "serde")]
+    impl serde::Serializer for ValueSerializer {
+        type Ok = Value;
+        type Error = serde::de::value::Error;
+        type SerializeSeq = SerializeSeq;
+        type SerializeTuple = serde::ser::Impossible<Value, Self::Error>;
+        type SerializeTupleStruct = serde::ser::Impossible<Value, Self::Error>;
+        type SerializeTupleVariant = serde::ser::Impossible<Value, Self::Error>;
+        type SerializeMap = serde::ser::Impossible<Value, Self::Error>;
+        type SerializeStruct = SerializeStruct;
+        type SerializeStructVariant = serde::ser::Impossible<Value, Self::Error>;
+
+        fn serialize_u8(self, value: u8) -> Result<Value, Self::Error> {
+            self.serialize_u64(value.into())
+        }
+
+        fn serialize_u16(self, value: u16) -> Result<Value, Self::Error> {
+            self.serialize_u64(value.into())
+        }
+
+        fn serialize_u32(self, value: u32) -> Result<Value, Self::Error> {
+            self.serialize_u64(value.into())
+        }
+
+        fn serialize_u64(self, value: u64) -> Result<Value, Self::Error> {
+            Ok(Value::U64(value))
+        }
+
+        fn serialize_str(self, value: &str) -> Result<Value, Self::Error> {
+            Ok(Value::Str(value.into()))
+        }
+
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+        ) -> Result<Value, Self::Error> {
+            Ok(Value::Str(variant.into()))
+        }
+
+        fn serialize_newtype_struct<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<Value, Self::Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Self::Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_seq(self, _len: Option<usize>) -> Result<SerializeSeq, Self::Error> {
+            Ok(SerializeSeq(Vec::new()))
+        }
+
+        fn serialize_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<SerializeStruct, Self::Error> {
+            Ok(SerializeStruct(Vec::new()))
+        }
+
+        fn serialize_bool(self, _value: bool) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_i8(self, _value: i8) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_i16(self, _value: i16) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_i32(self, _value: i32) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_i64(self, _value: i64) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_f32(self, _value: f32) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_f64(self, _value: f64) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_char(self, _value: char) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_bytes(self, _value: &[u8]) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_none(self) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_unit(self) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _value: &T,
+        ) -> Result<Value, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+            Err(unsupported())
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeStructVariant, Self::Error> {
+            Err(unsupported())
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    struct SerializeSeq(Vec<Value>);
+
+    #[cfg(feature = "serde")]
+    impl serde::ser::SerializeSeq for SerializeSeq {
+        type Ok = Value;
+        type Error = serde::de::value::Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<(), Self::Error> {
+            self.0.push(value.serialize(ValueSerializer)?);
+            Ok(())
+        }
+
+        fn end(self) -> Result<Value, Self::Error> {
+            Ok(Value::Seq(self.0))
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    struct SerializeStruct(Vec<(&'static str, Value)>);
+
+    #[cfg(feature = "serde")]
+    impl serde::ser::SerializeStruct for SerializeStruct {
+        type Ok = Value;
+        type Error = serde::de::value::Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Self::Error> {
+            self.0.push((key, value.serialize(ValueSerializer)?));
+            Ok(())
+        }
+
+        fn end(self) -> Result<Value, Self::Error> {
+            Ok(Value::Map(self.0))
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn keys(value: &Value) -> Vec<&'static str> {
+        match value {
+            Value::Map(entries) => entries.iter().map(|(key, _)| *key).collect(),
+            _ => panic!("expected a map"),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn defaults_are_omitted_from_serialization() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        assert_eq!(
+            vec!["id", "role"],
+            keys(&to_value(&Node::new(id, Role::Button)))
+        );
+        let request = ActionRequest {
+            action: Action::Focus,
+            target: id,
+            data: None,
+        };
+        assert_eq!(vec!["action", "target"], keys(&to_value(&request)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn custom_attributes_round_trip() {
+        let node = Node {
+            custom_attributes: Box::new([
+                CustomAttribute {
+                    key: "shape".into(),
+                    value: "hexagon".into(),
+                },
+                CustomAttribute {
+                    key: "owner".into(),
+                    value: "player 2".into(),
+                },
+            ]),
+            ..Node::new(NodeId(NonZeroU64::new(1).unwrap()), Role::GenericContainer)
+        };
+        let value = to_value(&node);
+        assert_eq!(vec!["id", "role", "customAttributes"], keys(&value));
+        assert_eq!(node, Node::deserialize(value).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patch_with_unknown_attributes() {
+        let payload = Value::Map(vec![
+            ("id", Value::U64(2)),
+            (
+                "set",
+                Value::Seq(vec![
+                    Value::Map(vec![("name", Value::Str("OK".into()))]),
+                    Value::Map(vec![("glowRadius", Value::U64(2))]),
+                ]),
+            ),
+            (
+                "remove",
+                Value::Seq(vec![
+                    Value::Str("busy".into()),
+                    Value::Str("sparkle".into()),
+                ]),
+            ),
+        ]);
+        let patch = NodePatch::deserialize(payload).unwrap();
+        let expected_unknown: Vec<Box<str>> = vec!["glowRadius".into(), "sparkle".into()];
+        assert_eq!(
+            NodePatch {
+                set: vec![NodeAttribute::Name("OK".into())],
+                remove: vec![NodeAttributeKind::Busy],
+                unknown_attributes: expected_unknown,
+                ..NodePatch::new(NodeId(NonZeroU64::new(2).unwrap()))
+            },
+            patch
+        );
+    }
+
+    #[test]
+    fn node_to_row() {
+        let node = Node {
+            bounds: Some(RelativeBounds {
+                offset_container: None,
+                rect: Rect {
+                    left: 10.0,
+                    top: 20.5,
+                    width: 100.0,
+                    height: 30.0,
+                },
+                transform: None,
+            }),
+            name: Some("Accept terms".into()),
+            focusable: true,
+            checked_state: Some(CheckedState::Mixed),
+            ..Node::new(NodeId(NonZeroU64::new(42).unwrap()), Role::CheckBox)
+        };
+        assert_eq!(
+            vec![
+                ("id", "42".to_string()),
+                ("role", "CheckBox".to_string()),
+                ("name", "Accept terms".to_string()),
+                ("value", "".to_string()),
+                ("focusable", "true".to_string()),
+                ("disabled", "false".to_string()),
+                ("invisible", "false".to_string()),
+                ("ignored", "false".to_string()),
+                ("expanded", "".to_string()),
+                ("selected", "".to_string()),
+                ("checked", "Mixed".to_string()),
+                ("bounds", "10,20.5,100,30".to_string()),
+            ],
+            node.to_row()
+        );
+    }
+
+    #[test]
+    fn node_patch() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let mut node = Node {
+            name: Some("Volume".into()),
+            focusable: true,
+            value_for_range: Some(3.0),
+            ..Node::new(id, Role::Slider)
+        };
+        assert_eq!(
+            vec![
+                NodeAttribute::Focusable,
+                NodeAttribute::Name("Volume".into()),
+                NodeAttribute::ValueForRange(3.0),
+            ],
+            node.attributes()
+        );
+        NodePatch {
+            id,
+            set: vec![
+                NodeAttribute::ValueForRange(4.0),
+                NodeAttribute::Actions(Action::Increment | Action::Decrement),
+            ],
+            remove: vec![NodeAttributeKind::Focusable, NodeAttributeKind::Name],
+            unknown_attributes: vec![],
+        }
+        .apply(&mut node);
+        assert!(!node.has_attribute(NodeAttributeKind::Focusable));
+        assert!(!node.has_attribute(NodeAttributeKind::Name));
+        assert_eq!(Some(4.0), node.value_for_range);
+        assert_eq!(Action::Increment | Action::Decrement, node.actions);
+
+        let mut rebuilt = Node::new(id, Role::Slider);
+        for attribute in node.attributes() {
+            rebuilt.set_attribute(attribute);
+        }
+        assert_eq!(node, rebuilt);
+    }
+
+    #[test]
+    fn attribute_order_is_canonical() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let attributes = vec![
+            NodeAttribute::Url("https://example.com/".into()),
+            NodeAttribute::Focusable,
+            NodeAttribute::Name("Home".into()),
+            NodeAttribute::Visited,
+        ];
+        let mut forward = Node::new(id, Role::Link);
+        for attribute in attributes.iter().cloned() {
+            forward.set_attribute(attribute);
+        }
+        let mut reverse = Node::new(id, Role::Link);
+        for attribute in attributes.into_iter().rev() {
+            reverse.set_attribute(attribute);
+        }
+        assert_eq!(forward, reverse);
+        assert_eq!(forward.attributes(), reverse.attributes());
+        let kinds = forward
+            .attributes()
+            .iter()
+            .map(NodeAttribute::kind)
+            .collect::<Vec<_>>();
+        let mut sorted_kinds = kinds.clone();
+        sorted_kinds.sort();
+        assert_eq!(sorted_kinds, kinds);
+    }
+
+    #[test]
+    fn all_attribute_kinds() {
+        // Each kind appears exactly once, at the position of its
+        // discriminant, so the list covers every variant.
+        for (i, kind) in NodeAttributeKind::ALL.iter().enumerate() {
+            assert_eq!(i, *kind as usize);
+        }
+        let names = NodeAttributeKind::ALL
+            .iter()
+            .map(|kind| kind.name())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(NodeAttributeKind::ALL.len(), names.len());
+        assert!(names.contains("name"));
+        assert!(names.contains("custom_attributes"));
+        assert!(!names.contains("children"));
+
+        // Clearing every kind resets a node to its defaults.
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let mut node = Node {
+            name: Some("Volume".into()),
+            focusable: true,
+            value_for_range: Some(3.0),
+            actions: Action::Increment | Action::Decrement,
+            ..Node::new(id, Role::Slider)
+        };
+        for kind in NodeAttributeKind::ALL {
+            node.clear_attribute(*kind);
+        }
+        assert_eq!(Node::new(id, Role::Slider), node);
+    }
+
+    #[test]
+    fn custom_attributes() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let custom_attributes: Box<[CustomAttribute]> = Box::new([
+            CustomAttribute {
+                key: "shape".into(),
+                value: "hexagon".into(),
+            },
+            CustomAttribute {
+                key: "owner".into(),
+                value: "player 2".into(),
+            },
+        ]);
+        let node = Node {
+            custom_attributes: custom_attributes.clone(),
+            ..Node::new(id, Role::GenericContainer)
+        };
+        assert_eq!(Some("hexagon"), node.custom_attribute("shape"));
+        assert_eq!(Some("player 2"), node.custom_attribute("owner"));
+        assert_eq!(None, node.custom_attribute("color"));
+        assert_eq!(
+            vec![NodeAttribute::CustomAttributes(custom_attributes)],
+            node.attributes()
+        );
+
+        let mut rebuilt = Node::new(id, Role::GenericContainer);
+        for attribute in node.attributes() {
+            rebuilt.set_attribute(attribute);
+        }
+        assert_eq!(node, rebuilt);
+
+        let mut patched = Node::new(id, Role::GenericContainer);
+        let mut patch = NodePatch::new(id);
+        patch.set = node.attributes();
+        patch.apply(&mut patched);
+        assert_eq!(node, patched);
+    }
+
+    #[test]
+    fn tree_update_builder() {
+        let window_id = NodeId(NonZeroU64::new(1).unwrap());
+        let button_id = NodeId(NonZeroU64::new(2).unwrap());
+        let scroller_id = NodeId(NonZeroU64::new(3).unwrap());
+        let window = Node {
+            children: Box::new([button_id, scroller_id]),
+            ..Node::new(window_id, Role::Window)
+        };
+        let button = Node::new(button_id, Role::Button);
+        let scroller = Node::new(scroller_id, Role::ScrollView);
+        let tree = TreeBuilder::new(TreeId("test".into()), window_id)
+            .source_string_encoding(StringEncoding::Utf16)
+            .parent(TreeId("parent".into()))
+            .root_scroller(scroller_id)
+            .build();
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .tree(tree)
+            .node(button.clone())
+            .root(window.clone())
+            .nodes(vec![scroller.clone()])
+            .focus(button_id)
+            .build();
+        let expected = TreeUpdate {
+            nodes: vec![window.clone(), button, scroller],
+            tree: Some(Tree {
+                parent: Some(TreeId("parent".into())),
+                root_scroller: Some(scroller_id),
+                ..Tree::new(TreeId("test".into()), window_id, StringEncoding::Utf16)
+            }),
+            focus: Some(button_id),
+            ..Default::default()
+        };
+        assert!(update == expected);
+
+        let update = TreeUpdateBuilder::new(TreeId("other".into()))
+            .root(window)
+            .build();
+        let tree = update.tree.unwrap();
+        assert_eq!(TreeId("other".into()), tree.id);
+        assert_eq!(window_id, tree.root);
+        assert_eq!(StringEncoding::Utf8, tree.source_string_encoding);
+    }
+
+    #[test]
+    fn validate_actions() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let slider = Node {
+            actions: Action::Focus | Action::Increment | Action::Decrement,
+            ..Node::new(id, Role::Slider)
+        };
+        assert_eq!(Vec::<ActionWarning>::new(), slider.validate_actions());
+        let combo_box = Node {
+            actions: Action::Expand.into(),
+            expanded: Some(false),
+            ..Node::new(id, Role::ComboBoxMenuButton)
+        };
+        assert_eq!(Vec::<ActionWarning>::new(), combo_box.validate_actions());
+        let text_field = Node {
+            actions: Action::ReplaceSelectedText | Action::SetValue,
+            editable: true,
+            ..Node::new(id, Role::TextField)
+        };
+        assert_eq!(Vec::<ActionWarning>::new(), text_field.validate_actions());
+
+        let button = Node {
+            actions: Action::Default | Action::Increment | Action::Expand,
+            ..Node::new(id, Role::Button)
+        };
+        assert_eq!(
+            vec![
+                ActionWarning::NotExpandable(Action::Expand),
+                ActionWarning::NotRange(Action::Increment),
+            ],
+            button.validate_actions()
+        );
+        let list = Node {
+            actions: Action::ScrollDown | Action::CustomAction,
+            ..Node::new(id, Role::List)
+        };
+        assert_eq!(
+            vec![
+                ActionWarning::NoCustomActions,
+                ActionWarning::NotScrollable(Action::ScrollDown),
+            ],
+            list.validate_actions()
+        );
+    }
+
+    #[test]
+    fn aria_attributes() {
+        assert_eq!(
+            vec![
+                NodeAttribute::Name("Volume".into()),
+                NodeAttribute::CheckedState(CheckedState::Mixed),
+                NodeAttribute::ValueForRange(42.5),
+                NodeAttribute::HasPopup(HasPopup::Listbox),
+                NodeAttribute::AriaCurrent(AriaCurrent::Page),
+                NodeAttribute::Required,
+                NodeAttribute::Expanded(false),
+            ],
+            attributes_from_aria(&[
+                ("aria-label", "Volume"),
+                ("aria-checked", "mixed"),
+                ("aria-valuenow", "42.5"),
+                ("aria-haspopup", "listbox"),
+                ("aria-current", "page"),
+                ("aria-required", "true"),
+                ("aria-expanded", "false"),
+            ])
+        );
+    }
+
+    #[test]
+    fn aria_attribute_values() {
+        assert_eq!(
+            vec![NodeAttribute::CheckedState(CheckedState::True)],
+            attributes_from_aria(&[("ARIA-CHECKED", " True ")])
+        );
+        assert_eq!(
+            vec![NodeAttribute::HasPopup(HasPopup::True)],
+            attributes_from_aria(&[("aria-haspopup", "true")])
+        );
+        assert_eq!(
+            Vec::<NodeAttribute>::new(),
+            attributes_from_aria(&[
+                ("aria-haspopup", "false"),
+                ("aria-checked", "undefined"),
+                ("aria-valuenow", "lots"),
+                ("aria-required", "false"),
+                ("aria-label", ""),
+                ("aria-controls", "other-element"),
+                ("data-foo", "bar"),
+            ])
+        );
+    }
+
+    fn bounds(left: f32) -> Option<RelativeBounds> {
+        Some(RelativeBounds {
+            offset_container: None,
+            rect: Rect {
+                left,
+                top: 0.0,
+                width: 100.0,
+                height: 20.0,
+            },
+            transform: None,
+        })
+    }
+
+    #[test]
+    fn eq_ignoring_bounds() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let node = Node {
+            name: Some("OK".into()),
+            bounds: bounds(10.0),
+            ..Node::new(id, Role::Button)
+        };
+        let moved = Node {
+            bounds: bounds(10.25),
+            ..node.clone()
+        };
+        assert_ne!(node, moved);
+        assert!(node.eq_ignoring_bounds(&moved));
+        assert!(node.eq_with_bounds_tolerance(&moved, 0.5));
+        assert!(!node.eq_with_bounds_tolerance(&moved, 0.1));
+        let without_bounds = Node {
+            bounds: None,
+            ..node.clone()
+        };
+        assert!(node.eq_ignoring_bounds(&without_bounds));
+        assert!(!node.eq_with_bounds_tolerance(&without_bounds, 0.5));
+        let renamed = Node {
+            name: Some("Cancel".into()),
+            ..node.clone()
+        };
+        assert!(!node.eq_ignoring_bounds(&renamed));
+
+        let update = |node: Node| TreeUpdate {
+            nodes: vec![node],
+            tree: Some(Tree::new(TreeId("test".into()), id, StringEncoding::Utf8)),
+            ..Default::default()
+        };
+        assert!(update(node.clone()) != update(moved.clone()));
+        assert!(update(node.clone()).eq_ignoring_bounds(&update(moved)));
+        assert!(!update(node).eq_ignoring_bounds(&update(renamed)));
+    }
+
+    #[test]
+    fn dangling_children() {
+        let id = |n| NodeId(NonZeroU64::new(n).unwrap());
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([id(2), id(3), id(4)]),
+                ..Node::new(id(1), Role::Window)
+            })
+            .node(Node {
+                children: Box::new([id(3), id(5)]),
+                ..Node::new(id(2), Role::Group)
+            })
+            .node(Node::new(id(5), Role::Button))
+            .build();
+        assert_eq!(vec![id(3), id(4)], find_dangling_children(&update));
+
+        let update = TreeUpdateBuilder::new(TreeId("test".into()))
+            .root(Node {
+                children: Box::new([id(2)]),
+                ..Node::new(id(1), Role::Window)
+            })
+            .node(Node::new(id(2), Role::Button))
+            .build();
+        assert!(find_dangling_children(&update).is_empty());
+    }
+
+    #[test]
+    fn remapped_updates_dont_collide() {
+        let id = |n| NodeId(NonZeroU64::new(n).unwrap());
+        // Each module numbers its nodes from 1.
+        let module_update = || {
+            TreeUpdateBuilder::new(TreeId("test".into()))
+                .root(Node {
+                    children: Box::new([id(2), id(3)]),
+                    active_descendant: Some(id(3)),
+                    ..Node::new(id(1), Role::List)
+                })
+                .node(Node {
+                    labelled_by: Box::new([id(3)]),
+                    bounds: Some(RelativeBounds {
+                        offset_container: Some(id(1)),
+                        rect: Rect {
+                            left: 0.0,
+                            top: 0.0,
+                            width: 10.0,
+                            height: 10.0,
+                        },
+                        transform: None,
+                    }),
+                    ..Node::new(id(2), Role::ListItem)
+                })
+                .node(Node::new(id(3), Role::StaticText))
+                .focus(id(2))
+                .patch(NodePatch {
+                    set: vec![NodeAttribute::Controls(Box::new([id(2)]))],
+                    ..NodePatch::new(id(3))
+                })
+                .build()
+        };
+        let first = module_update();
+        let mut second = module_update();
+        second.offset_node_ids(100);
+
+        let ids: HashSet<NodeId> = first
+            .nodes
+            .iter()
+            .chain(&second.nodes)
+            .map(|node| node.id)
+            .collect();
+        assert_eq!(first.nodes.len() + second.nodes.len(), ids.len());
+
+        assert_eq!(id(101), second.tree.as_ref().unwrap().root);
+        assert_eq!(Some(id(102)), second.focus);
+        let root = &second.nodes[0];
+        assert_eq!(id(101), root.id);
+        assert_eq!(&[id(102), id(103)], &*root.children);
+        assert_eq!(Some(id(103)), root.active_descendant);
+        let item = &second.nodes[1];
+        assert_eq!(&[id(103)], &*item.labelled_by);
+        assert_eq!(
+            Some(id(101)),
+            item.bounds.as_ref().unwrap().offset_container
+        );
+        assert_eq!(id(103), second.patches[0].id);
+        assert_eq!(
+            NodeAttribute::Controls(Box::new([id(102)])),
+            second.patches[0].set[0]
+        );
+    }
+
+    #[test]
+    fn color_from_rgba() {
+        let color = Color::from(0xFF80_00C0);
+        assert_eq!(
+            Color {
+                red: 0xFF,
+                green: 0x80,
+                blue: 0x00,
+                alpha: 0xC0,
+            },
+            color
+        );
+        assert_eq!(0xFF80_00C0, u32::from(color));
+        assert_eq!("#FF8000C0", color.to_string());
+        assert_eq!("#00000000", Color::from(0).to_string());
+    }
+
+    #[test]
+    fn default_action_verb_strings() {
+        for (verb, expected) in [
+            (DefaultActionVerb::Activate, "activate"),
+            (DefaultActionVerb::Check, "check"),
+            (DefaultActionVerb::Uncheck, "uncheck"),
+            (DefaultActionVerb::Click, "click"),
+            (DefaultActionVerb::ClickAncestor, "click ancestor"),
+            (DefaultActionVerb::Jump, "jump"),
+            (DefaultActionVerb::Open, "open"),
+            (DefaultActionVerb::Press, "press"),
+            (DefaultActionVerb::Select, "select"),
+        ] {
+            assert_eq!(expected, default_action_verb_string(verb));
+        }
+    }
+
+    #[test]
+    fn default_action_verb_localization_keys() {
+        let verbs = [
+            DefaultActionVerb::Activate,
+            DefaultActionVerb::Check,
+            DefaultActionVerb::Uncheck,
+            DefaultActionVerb::Click,
+            DefaultActionVerb::ClickAncestor,
+            DefaultActionVerb::Jump,
+            DefaultActionVerb::Open,
+            DefaultActionVerb::Press,
+            DefaultActionVerb::Select,
+        ];
+        let mut keys = HashSet::new();
+        for verb in verbs {
+            let key = verb.as_localization_key();
+            assert!(!key.is_empty(), "{:?}", verb);
+            assert!(keys.insert(key), "duplicate key {}", key);
+            assert!(!verb.english().is_empty(), "{:?}", verb);
+        }
+        assert_eq!(
+            "click-ancestor",
+            DefaultActionVerb::ClickAncestor.as_localization_key()
+        );
+    }
+
+    #[test]
+    fn scale_rect() {
+        let rect = Rect {
+            left: 10.0,
+            top: 20.0,
+            width: 30.0,
+            height: 40.0,
+        };
+        assert_eq!(
+            Rect {
+                left: 15.0,
+                top: 30.0,
+                width: 45.0,
+                height: 60.0,
+            },
+            rect.scale(1.5)
+        );
+        assert_eq!(rect, rect.scale(1.0));
+    }
+
+    #[test]
+    fn merge_clear_and_stale_version() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let update = |name: &str, version: u64| TreeUpdate {
+            nodes: vec![Node {
+                name: Some(name.into()),
+                ..Node::new(id, Role::Button)
+            }],
+            version: Some(version),
+            ..Default::default()
+        };
+
+        let mut merged = update("First", 2);
+        let clearing = TreeUpdate {
+            clear: Some(id),
+            ..update("Second", 3)
+        };
+        assert!(*merged.merge(clearing.clone()).unwrap_err() == clearing);
+        assert!(merged == update("First", 2));
+
+        assert!(merged.merge(update("Stale", 1)).is_ok());
+        assert!(merged == update("First", 2));
+
+        assert!(merged.merge(update("Second", 3)).is_ok());
+        assert!(merged == update("Second", 3));
+    }
+
+    #[test]
+    fn retain_attributes() {
+        let root = NodeId(NonZeroU64::new(1).unwrap());
+        let child = NodeId(NonZeroU64::new(2).unwrap());
+        let mut update = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([child]),
+                    name: Some("Page".into()),
+                    css_display: Some("block".into()),
+                    inner_html: Some("<p>Text</p>".into()),
+                    focusable: true,
+                    ..Node::new(root, Role::Window)
+                },
+                Node {
+                    value: Some("Text".into()),
+                    ..Node::new(child, Role::StaticText)
+                },
+            ],
+            patches: vec![NodePatch {
+                set: vec![
+                    NodeAttribute::CssDisplay("inline".into()),
+                    NodeAttribute::Name("Paragraph".into()),
+                ],
+                remove: vec![NodeAttributeKind::InnerHtml],
+                ..NodePatch::new(child)
+            }],
+            ..Default::default()
+        };
+        update.retain_attributes(|attribute| {
+            !matches!(
+                attribute.kind(),
+                NodeAttributeKind::CssDisplay | NodeAttributeKind::InnerHtml
+            )
+        });
+
+        let expected = TreeUpdate {
+            nodes: vec![
+                Node {
+                    children: Box::new([child]),
+                    name: Some("Page".into()),
+                    focusable: true,
+                    ..Node::new(root, Role::Window)
+                },
+                Node {
+                    value: Some("Text".into()),
+                    ..Node::new(child, Role::StaticText)
+                },
+            ],
+            patches: vec![NodePatch {
+                set: vec![NodeAttribute::Name("Paragraph".into())],
+                remove: vec![NodeAttributeKind::InnerHtml],
+                ..NodePatch::new(child)
+            }],
+            ..Default::default()
+        };
+        assert!(update == expected);
+    }
+
+    #[test]
+    fn live_status_tokens() {
+        assert_eq!(LivePoliteness::Off, parse_live_status("off"));
+        assert_eq!(LivePoliteness::Polite, parse_live_status("polite"));
+        assert_eq!(LivePoliteness::Assertive, parse_live_status(" Assertive "));
+        assert_eq!(LivePoliteness::Off, parse_live_status("rude"));
+        assert_eq!(LivePoliteness::Off, parse_live_status(""));
+    }
+
+    #[test]
+    fn live_relevant_tokens() {
+        assert_eq!(
+            EnumSet::only(LiveRelevant::Additions),
+            parse_live_relevant("additions")
+        );
+        assert_eq!(
+            EnumSet::only(LiveRelevant::Removals),
+            parse_live_relevant("removals")
+        );
+        assert_eq!(
+            EnumSet::only(LiveRelevant::Text),
+            parse_live_relevant("TEXT")
+        );
+        assert_eq!(EnumSet::all(), parse_live_relevant("all"));
+        assert_eq!(
+            LiveRelevant::Removals | LiveRelevant::Text,
+            parse_live_relevant("  removals\ttext bogus ")
+        );
+        assert_eq!(
+            LiveRelevant::Additions | LiveRelevant::Text,
+            parse_live_relevant("bogus")
+        );
+        assert_eq!(
+            LiveRelevant::Additions | LiveRelevant::Text,
+            parse_live_relevant("")
+        );
+    }
+
+    #[test]
+    fn set_attributes_last_wins() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let mut node = Node {
+            name: Some("Old".into()),
+            ..Node::new(id, Role::Button)
+        };
+        node.set_attributes(vec![
+            NodeAttribute::Name("First".into()),
+            NodeAttribute::Busy,
+            NodeAttribute::ValueForRange(1.0),
+            NodeAttribute::Name("Second".into()),
+            NodeAttribute::ValueForRange(2.0),
+        ]);
+        assert_eq!(
+            vec![
+                NodeAttribute::Busy,
+                NodeAttribute::Name("Second".into()),
+                NodeAttribute::ValueForRange(2.0)
+            ],
+            node.attributes()
+        );
+    }
+
+    #[test]
+    fn allocate_ids() {
+        let mut allocator = IdAllocator::new();
+        allocator.reserve(1..3);
+        allocator.reserve(4..5);
+        let ids: Vec<u64> = (0..4).map(|_| allocator.allocate().0.get()).collect();
+        assert_eq!(vec![3, 5, 6, 7], ids);
+        allocator.reserve(7..10);
+        assert_eq!(10, allocator.allocate().0.get());
+    }
+
+    #[test]
+    #[should_panic(expected = "node IDs exhausted")]
+    fn allocate_last_id() {
+        let mut allocator = IdAllocator::new();
+        allocator.reserve(1..u64::MAX);
+        assert_eq!(u64::MAX, allocator.allocate().0.get());
+        allocator.allocate();
+    }
+
+    #[test]
+    fn ids_for_keys() {
+        let mut allocator = IdAllocator::new();
+        let button = allocator.id_for_key(0xb077);
+        let label = allocator.id_for_key(0x1abe1);
+        let other = allocator.allocate();
+        assert_ne!(button, label);
+        assert_ne!(label, other);
+        assert_eq!(button, allocator.id_for_key(0xb077));
+        assert_eq!(label, allocator.id_for_key(0x1abe1));
+
+        // The same keys requested in the same order get the same IDs.
+        let mut next_frame = IdAllocator::new();
+        assert_eq!(button, next_frame.id_for_key(0xb077));
+        assert_eq!(label, next_frame.id_for_key(0x1abe1));
+
+        assert_eq!(Some(button), allocator.remove_key(0xb077));
+        assert_eq!(None, allocator.remove_key(0xb077));
+        let button_again = allocator.id_for_key(0xb077);
+        assert!(![button, label, other].contains(&button_again));
+    }
+
+    #[test]
+    fn new_node_has_no_attributes() {
+        let node = Node::new(NodeId(NonZeroU64::new(1).unwrap()), Role::Button);
+        assert!(node.attributes().is_empty());
+        assert!(NodeAttributeKind::ALL
+            .iter()
+            .all(|kind| !node.has_attribute(*kind)));
+    }
+
+    #[test]
+    fn changed_attribute_kinds() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let old = Node {
+            name: Some("Old".into()),
+            busy: true,
+            labelled_by: Box::new([id]),
+            ..Node::new(id, Role::Button)
+        };
+        let new = Node {
+            name: Some("New".into()),
+            focusable: true,
+            labelled_by: Box::new([id]),
+            ..Node::new(id, Role::CheckBox)
+        };
+        assert_eq!(
+            vec![
+                NodeAttributeKind::Focusable,
+                NodeAttributeKind::Busy,
+                NodeAttributeKind::Name
+            ],
+            old.changed_attribute_kinds(&new)
+        );
+        assert!(old.changed_attribute_kinds(&old).is_empty());
+    }
+
+    #[test]
+    fn duplicate_attribute_kinds() {
+        let id = NodeId(NonZeroU64::new(1).unwrap());
+        let patch = NodePatch {
+            set: vec![
+                NodeAttribute::ValueForRange(1.0),
+                NodeAttribute::Name("First".into()),
+                NodeAttribute::Busy,
+                NodeAttribute::Name("Second".into()),
+                NodeAttribute::ValueForRange(2.0),
+                NodeAttribute::Name("Third".into()),
+            ],
+            ..NodePatch::new(id)
+        };
+        assert_eq!(
+            vec![NodeAttributeKind::Name, NodeAttributeKind::ValueForRange],
+            patch.duplicate_attribute_kinds()
+        );
+        let patch = NodePatch {
+            set: vec![NodeAttribute::Busy, NodeAttribute::Name("Name".into())],
+            remove: vec![NodeAttributeKind::Busy],
+            ..NodePatch::new(id)
+        };
+        assert!(patch.duplicate_attribute_kinds().is_empty());
+    }
 }